@@ -0,0 +1,34 @@
+/* The C API of playing.rs, built with `cargo build -p playing-ffi --release`. */
+#ifndef PLAYING_H
+#define PLAYING_H
+
+typedef struct PlayingSession PlayingSession;
+typedef struct PlayingSubscription PlayingSubscription;
+
+/* Called with the status line, or NULL when nothing is playing; the line is only valid during the call. */
+typedef void (*playing_callback)(const char *line, void *user_data);
+
+/* Loads the config at `config_path`, or the usual one when NULL; NULL on error.
+   Commands are checked against the [access.library] rules of the config. */
+PlayingSession *playing_open(const char *config_path);
+void playing_close(PlayingSession *session);
+
+/* The line `status` prints, to free with playing_free_string; NULL when nothing plays or on error. */
+char *playing_now_playing(PlayingSession *session);
+void playing_free_string(char *string);
+
+/* 0, else the code the binary would exit with, or -1 when it panicked; seconds are backwards
+   when negative, and NaN, infinities and numbers too large fail as bad commands. */
+int playing_toggle(PlayingSession *session);
+int playing_seek(PlayingSession *session, double seconds);
+
+/* Calls `callback` from another thread whenever the status line changes, looking every `interval_ms`; NULL on error. */
+PlayingSubscription *playing_subscribe(PlayingSession *session, unsigned int interval_ms, playing_callback callback, void *user_data);
+/* Stops the callbacks; none runs once it returns. */
+void playing_unsubscribe(PlayingSubscription *subscription);
+
+/* What the last failing call of this thread failed with, valid until the next call. Functions
+   returning a pointer return NULL when they panicked, with a message here. */
+const char *playing_last_error(void);
+
+#endif
@@ -0,0 +1,191 @@
+//! The core of playing.rs as a C library, for bars and bindings linking it instead of running
+//! the binary. Build it with `cargo build -p playing-ffi --release`; `ffi/playing.h` declares it.
+
+use std::{cell::RefCell, ffi::{c_char, c_int, c_uint, c_void, CStr, CString}, panic::{self, AssertUnwindSafe}, path::Path, ptr, time::Duration};
+
+use playing_rs::{api::{Session, Subscription}, PlayingError};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail(e: impl ToString) {
+    let message = CString::new(e.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|l| *l.borrow_mut() = Some(message));
+}
+
+/// What the functions returning a code return when they panicked.
+const PANICKED: c_int = -1;
+
+/// Runs the body of an exported function, `fallback` when it panics: unwinding into the caller
+/// would abort its whole process.
+fn guard<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(value) => value,
+        Err(e) => {
+            let what = e.downcast_ref::<&str>().copied().or(e.downcast_ref::<String>().map(String::as_str)).unwrap_or("unknown");
+            fail(format!("panicked: {}", what));
+            fallback
+        }
+    }
+}
+
+fn code(result: Result<(), PlayingError>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            let code = e.code();
+            fail(e);
+            code
+        }
+    }
+}
+
+/// What the last failing call of this thread failed with, null if none did.
+#[no_mangle]
+pub extern "C" fn playing_last_error() -> *const c_char {
+    guard(ptr::null(), || LAST_ERROR.with(|l| l.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr())))
+}
+
+/// Loads the config at `config_path`, or the usual one when null.
+///
+/// # Safety
+/// `config_path` is null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn playing_open(config_path: *const c_char) -> *mut Session {
+    guard(ptr::null_mut(), || open(config_path))
+}
+
+unsafe fn open(config_path: *const c_char) -> *mut Session {
+    let path = match config_path.is_null() {
+        true => None,
+        false => match unsafe { CStr::from_ptr(config_path) }.to_str() {
+            Ok(p) => Some(Path::new(p)),
+            Err(e) => {
+                fail(e);
+                return ptr::null_mut()
+            }
+        },
+    };
+    match Session::new(path) {
+        Ok(s) => Box::into_raw(Box::new(s)),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `session` is null or was returned by [`playing_open`], and isn't used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn playing_close(session: *mut Session) {
+    guard((), || if !session.is_null() {
+        drop(unsafe { Box::from_raw(session) });
+    })
+}
+
+/// The line `status` prints, to free with [`playing_free_string`]; null when nothing plays or
+/// on error.
+///
+/// # Safety
+/// `session` was returned by [`playing_open`].
+#[no_mangle]
+pub unsafe extern "C" fn playing_now_playing(session: *mut Session) -> *mut c_char {
+    guard(ptr::null_mut(), || match unsafe { &*session }.now_playing() {
+        Ok(Some(line)) => CString::new(line.replace('\0', "")).unwrap_or_default().into_raw(),
+        Ok(None) => ptr::null_mut(),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        }
+    })
+}
+
+/// # Safety
+/// `string` is null or was returned by [`playing_now_playing`], and isn't used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn playing_free_string(string: *mut c_char) {
+    guard((), || if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    })
+}
+
+/// Toggles the player `op toggle` would, returning 0 or the code the binary would exit with.
+///
+/// # Safety
+/// `session` was returned by [`playing_open`].
+#[no_mangle]
+pub unsafe extern "C" fn playing_toggle(session: *mut Session) -> c_int {
+    guard(PANICKED, || code(unsafe { &*session }.toggle()))
+}
+
+/// Seeks by `seconds`, backwards when negative, returning 0 or the code the binary would exit
+/// with, that of a bad command for NaN, infinities and numbers too large.
+///
+/// # Safety
+/// `session` was returned by [`playing_open`].
+#[no_mangle]
+pub unsafe extern "C" fn playing_seek(session: *mut Session, seconds: f64) -> c_int {
+    guard(PANICKED, || code(unsafe { &*session }.seek(seconds)))
+}
+
+/// The data of the caller, handed back to its callback on the thread of the subscription.
+struct UserData(*mut c_void);
+
+// the caller subscribing vouches for it
+unsafe impl Send for UserData {}
+
+impl UserData {
+    // a method, for the closure to take the whole of it rather than the pointer
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Calls `callback` with the status line, or null when nothing is playing, whenever it changes,
+/// from another thread looking every `interval_ms`; null on error.
+///
+/// # Safety
+/// `session` was returned by [`playing_open`], and `user_data` can be used from another thread
+/// until [`playing_unsubscribe`].
+#[no_mangle]
+pub unsafe extern "C" fn playing_subscribe(
+    session: *mut Session,
+    interval_ms: c_uint,
+    callback: extern "C" fn(*const c_char, *mut c_void),
+    user_data: *mut c_void,
+) -> *mut Subscription {
+    guard(ptr::null_mut(), || subscribe(session, interval_ms, callback, user_data))
+}
+
+unsafe fn subscribe(
+    session: *mut Session,
+    interval_ms: c_uint,
+    callback: extern "C" fn(*const c_char, *mut c_void),
+    user_data: *mut c_void,
+) -> *mut Subscription {
+    let user_data = UserData(user_data);
+    let subscription = unsafe { &*session }.subscribe(Duration::from_millis(interval_ms.into()), move |line| {
+        let line = line.map(|l| CString::new(l.replace('\0', "")).unwrap_or_default());
+        callback(line.as_ref().map_or(ptr::null(), |l| l.as_ptr()), user_data.get());
+    });
+    match subscription {
+        Ok(s) => Box::into_raw(Box::new(s)),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Stops the callbacks of the subscription; none runs once it returns.
+///
+/// # Safety
+/// `subscription` is null or was returned by [`playing_subscribe`], and isn't used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn playing_unsubscribe(subscription: *mut Subscription) {
+    guard((), || if !subscription.is_null() {
+        drop(unsafe { Box::from_raw(subscription) });
+    })
+}
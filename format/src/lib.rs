@@ -0,0 +1,247 @@
+//! The status line templates of playing.rs, shared by the CLI and by web pages rendering the
+//! JSON state of `serve` themselves.
+//!
+//! The crate is `no_std`, needing only an allocator; `format/wasm` wraps it in a WASM module.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{borrow::ToOwned, format, string::String, vec, vec::Vec};
+use core::{fmt::Display, time::Duration};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[cfg(feature = "json")]
+mod state;
+#[cfg(feature = "json")]
+pub use state::{render_fields, render_state, LineOptions};
+
+pub const DEFAULT_FORMAT: &str = "{icon}{title} // {album} @ {artist}";
+
+/// Format of livestreams, which have no album and no end.
+pub const DEFAULT_LIVE_FORMAT: &str = "{icon}🔴 {title} @ {artist} ({uptime})";
+
+/// Status lines wider than this many columns are cut, unless told otherwise.
+pub const MAX_STATUS_LEN: usize = 70;
+
+/// What ends the lines and fields that were cut, unless told otherwise.
+pub const DEFAULT_ELLIPSIS: &str = "...";
+
+/// Space between the end of a scrolling line and its start coming around again.
+const SCROLL_GAP: &str = "   ";
+
+/// Placeholders available in status formats.
+pub const FIELDS: &[&str] = &["icon", "title", "album", "artist", "album_artist", "track_artist", "player", "name", "status", "url", "position", "length", "remaining", "live", "uptime", "event", "track_number", "playlist_position", "playlist_length", "ending", "state_icon", "favorite"];
+
+/// A format string such as `{title} - {artist}`. Braces are escaped by doubling them.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Template(Vec<Segment>);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Segment {
+    Literal(String),
+    Field(String),
+}
+
+#[derive(Debug)]
+pub struct TemplateError(String);
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl core::error::Error for TemplateError {}
+
+impl Template {
+    /// Parses `s`, accepting only the placeholders in `fields`.
+    pub fn parse(s: &str, fields: &[&str]) -> Result<Template, TemplateError> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(TemplateError(format!("unclosed placeholder `{{{}`", name))),
+                        }
+                    }
+                    if !fields.contains(&name.as_str()) {
+                        return Err(TemplateError(format!("unknown placeholder `{{{}}}`, expected one of: {}", name, fields.join(", "))))
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(core::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(name));
+                }
+                '}' => return Err(TemplateError("unmatched `}`, write `}}` for a literal brace".to_owned())),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Template(segments))
+    }
+
+    /// Whether the placeholder `name` appears in the template.
+    pub fn uses(&self, name: &str) -> bool {
+        self.0.iter().any(|s| matches!(s, Segment::Field(f) if f == name))
+    }
+
+    /// Renders the template, asking `value` for each placeholder.
+    pub fn render(&self, mut value: impl FnMut(&str) -> String) -> String {
+        let mut out = String::new();
+        for s in &self.0 {
+            match s {
+                Segment::Literal(l) => out.push_str(l),
+                Segment::Field(f) => out.push_str(&value(f)),
+            }
+        }
+        out
+    }
+}
+
+/// Formats `d` as `m:ss`, or `h:mm:ss` past the hour.
+pub fn duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Wall-clock time until the end of the track: at 1.5x a minute of media lasts 40 seconds.
+pub fn remaining_time(length: Duration, position: Duration, rate: f64) -> Duration {
+    let left = length.saturating_sub(position);
+    // a stopped or reversed rate says nothing about the speed it'll resume at
+    if rate > 0.0 {
+        left.div_f64(rate)
+    } else {
+        left
+    }
+}
+
+/// The default `{state_icon}` of a playback status, as named in the JSON state.
+pub fn state_icon(status: &str) -> &'static str {
+    match status {
+        "playing" => "▶",
+        "paused" => "⏸",
+        _ => "⏹",
+    }
+}
+
+/// The `{favorite}` of a track, a filled heart if it's a favorite and an empty one if not.
+pub fn heart(favorite: bool) -> &'static str {
+    if favorite { "♥" } else { "♡" }
+}
+
+/// Columns `s` takes in a terminal: two for CJK characters and most emoji, none for combining
+/// marks, summed over graphemes so that a flag or a ZWJ sequence counts once.
+pub fn width(s: &str) -> usize {
+    s.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// Cuts `line` down to `max` columns, ending it with `ellipsis` when that fits; it's cut between
+/// graphemes, so that no accent or wide character is split.
+pub fn truncate(line: String, max: usize, ellipsis: &str) -> String {
+    if width(&line) <= max {
+        return line
+    }
+    let ellipsis = if width(ellipsis) <= max { ellipsis } else { "" };
+    let room = max - width(ellipsis);
+    let mut used = 0;
+    let mut end = 0;
+    for (i, g) in line.grapheme_indices(true) {
+        used += g.width();
+        if used > room {
+            break
+        }
+        end = i + g.len();
+    }
+    format!("{}{}", &line[..end], ellipsis)
+}
+
+/// The window of `columns` columns of `line` scrolled by `offset` graphemes, for marquees: the
+/// line comes around again after a gap. Lines that fit are returned as they are, windows are
+/// padded to exactly `columns` so that a wide character at the edge doesn't make bars jitter.
+pub fn scroll(line: &str, offset: usize, columns: usize) -> String {
+    if width(line) <= columns {
+        return line.to_owned()
+    }
+    let graphemes: Vec<&str> = line.graphemes(true).chain(SCROLL_GAP.graphemes(true)).collect();
+    let mut window = String::new();
+    let mut used = 0;
+    for g in graphemes.iter().cycle().skip(offset % graphemes.len()) {
+        if used + g.width() > columns {
+            break
+        }
+        used += g.width();
+        window.push_str(g);
+    }
+    window.push_str(&" ".repeat(columns - used));
+    window
+}
+
+/// Markup a line is escaped for, so that e.g. an `&` in a title doesn't break the bar's.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Escape {
+    #[default]
+    None,
+    /// Pango markup, e.g. for waybar
+    Pango,
+    Html,
+}
+
+/// Escapes `s` for `markup`.
+pub fn escape(s: &str, markup: Escape) -> String {
+    match markup {
+        Escape::None => s.to_owned(),
+        Escape::Pango => escape_pango(s),
+        Escape::Html => escape_html(s),
+    }
+}
+
+/// Escapes `s` for Pango markup, which waybar and others render text as.
+pub fn escape_pango(s: &str) -> String {
+    escape_markup(s, "&apos;")
+}
+
+/// Escapes `s` for HTML, where `&apos;` is only known since HTML5.
+pub fn escape_html(s: &str) -> String {
+    escape_markup(s, "&#39;")
+}
+
+fn escape_markup(s: &str, apostrophe: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str(apostrophe),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes `s` for polybar, where `%{` starts a formatting tag.
+pub fn escape_polybar(s: &str) -> String {
+    s.replace('%', "%%")
+}
@@ -0,0 +1,130 @@
+use alloc::{borrow::ToOwned, string::{String, ToString}};
+use core::time::Duration;
+use serde_json::Value;
+
+use crate::{duration, escape, heart, remaining_time, state_icon, truncate, Escape, Template, DEFAULT_ELLIPSIS, MAX_STATUS_LEN};
+
+/// How the fields and the line are shown, the options `playing status` takes from its flags and
+/// from the `[status]` table of the config.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LineOptions {
+    pub no_icon: bool,
+    pub spaces_after_icon: usize,
+    pub max_title: Option<usize>,
+    pub max_album: Option<usize>,
+    pub max_artist: Option<usize>,
+    /// What `{artist}` shows of tracks without any artist.
+    pub unknown_artist: String,
+    /// Columns the whole line is cut to.
+    pub max_length: usize,
+    /// What ends the fields and lines that were cut.
+    pub ellipsis: String,
+    pub escape: Escape,
+}
+
+impl Default for LineOptions {
+    fn default() -> Self {
+        LineOptions {
+            no_icon: false,
+            spaces_after_icon: 1,
+            max_title: None,
+            max_album: None,
+            max_artist: None,
+            unknown_artist: "Unknown".to_owned(),
+            max_length: MAX_STATUS_LEN,
+            ellipsis: DEFAULT_ELLIPSIS.to_owned(),
+            escape: Escape::None,
+        }
+    }
+}
+
+impl LineOptions {
+    /// The options named as the keys of `[status]` in `options`, e.g. `{"max_title": 20}`, the
+    /// defaults for the others.
+    pub fn from_json(options: &Value) -> LineOptions {
+        let default = LineOptions::default();
+        let size = |key: &str| options[key].as_u64().map(|n| n as usize);
+        let text = |key: &str| options[key].as_str().map(ToOwned::to_owned);
+        LineOptions {
+            no_icon: options["no_icon"].as_bool().unwrap_or(default.no_icon),
+            spaces_after_icon: size("spaces_after_icon").unwrap_or(default.spaces_after_icon),
+            max_title: size("max_title"),
+            max_album: size("max_album"),
+            max_artist: size("max_artist"),
+            unknown_artist: text("unknown_artist").unwrap_or(default.unknown_artist),
+            max_length: size("max_length").unwrap_or(default.max_length),
+            ellipsis: text("ellipsis").unwrap_or(default.ellipsis),
+            escape: match options["escape"].as_str() {
+                Some("pango") => Escape::Pango,
+                Some("html") => Escape::Html,
+                _ => default.escape,
+            },
+        }
+    }
+
+    fn cut(&self, field: String, max: Option<usize>) -> String {
+        match max {
+            Some(max) => truncate(field, max, &self.ellipsis),
+            None => field,
+        }
+    }
+}
+
+fn seconds(v: &Value) -> Option<Duration> {
+    // negative or out of range values are as good as none
+    v.as_f64().and_then(|s| Duration::try_from_secs_f64(s).ok())
+}
+
+/// Renders the fields of `template` from `state`, as `playing status` does before cutting and
+/// escaping the line as a whole.
+///
+/// The state is the JSON of `GET /status` or of an `/events` message: fields it lacks render
+/// empty, and the position is taken as is, so pages extrapolating it from `updated_at` should
+/// update the state first. `{name}` and `{state_icon}` fall back to the player and to the
+/// default icons.
+pub fn render_fields(template: &Template, state: &Value, event: Option<&str>, opts: &LineOptions) -> String {
+    let text = |key: &str| state[key].as_str().map(ToOwned::to_owned);
+    let flag = |key: &str, name: &str| if state[key].as_bool().unwrap_or_default() { name } else { "" }.to_owned();
+    let number = |key: &str| state[key].as_u64().map(|n| n.to_string()).unwrap_or_default();
+    let live = state["live"].as_bool().unwrap_or_default();
+    let position = seconds(&state["position"]);
+    let length = seconds(&state["length"]);
+    let left = match (length, position) {
+        (Some(l), Some(p)) if !live => Some(remaining_time(l, p, state["rate"].as_f64().unwrap_or(1.0))),
+        _ => None,
+    };
+    template.render(|field| match field {
+        "icon" if opts.no_icon => String::new(),
+        "icon" => text("icon").unwrap_or_default() + &" ".repeat(opts.spaces_after_icon),
+        "title" => opts.cut(text("title").unwrap_or("Unknown".to_owned()), opts.max_title),
+        "album" => opts.cut(text("album").unwrap_or("Unknown".to_owned()), opts.max_album),
+        "artist" => opts.cut(text("artist").unwrap_or(opts.unknown_artist.clone()), opts.max_artist),
+        "album_artist" => opts.cut(text("album_artist").unwrap_or_default(), opts.max_artist),
+        "track_artist" => opts.cut(text("track_artist").unwrap_or_default(), opts.max_artist),
+        "player" => text("player").unwrap_or_default(),
+        "name" => text("name").or(text("player")).unwrap_or_default(),
+        "status" => text("status").unwrap_or("stopped".to_owned()),
+        "state_icon" => text("state_icon").unwrap_or(state_icon(&text("status").unwrap_or_default()).to_owned()),
+        "url" => text("url").unwrap_or_default(),
+        // how long a livestream has been watched, as it has no position within a track
+        "position" | "uptime" => position.map(duration).unwrap_or_default(),
+        "length" => length.map(duration).unwrap_or_default(),
+        "remaining" => left.map(duration).unwrap_or_default(),
+        "ending" => flag("ending", "ending"),
+        "live" => flag("live", "live"),
+        "event" => event.unwrap_or_default().to_owned(),
+        "track_number" => number("track_number"),
+        "playlist_position" => number("playlist_position"),
+        "playlist_length" => number("playlist_length"),
+        "favorite" => state["favorite"].as_bool().map(heart).unwrap_or_default().to_owned(),
+        _ => String::new(),
+    })
+}
+
+/// Renders `template` from `state` as `playing status` prints it with `opts`: the fields, then
+/// the line cut to `max_length` and escaped.
+pub fn render_state(template: &Template, state: &Value, event: Option<&str>, opts: &LineOptions) -> String {
+    let line = truncate(render_fields(template, state, event, opts), opts.max_length, &opts.ellipsis);
+    // escaped once cut, so that no entity is cut in half nor counted in the length
+    escape(&line, opts.escape)
+}
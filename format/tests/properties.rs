@@ -0,0 +1,116 @@
+use playing_format::{escape_html, escape_pango, escape_polybar, render_state, scroll, LineOptions, truncate, width, Template, FIELDS, MAX_STATUS_LEN};
+use proptest::prelude::*;
+use serde_json::json;
+
+/// Templates mixing the known fields, unknown ones, stray braces and any text.
+fn template() -> impl Strategy<Value = String> {
+    let piece = prop_oneof![
+        proptest::sample::select(FIELDS).prop_map(|f| format!("{{{}}}", f)),
+        "\\PC{0,8}",
+        Just("{{".to_owned()),
+        Just("}}".to_owned()),
+        Just("{".to_owned()),
+        Just("}".to_owned()),
+        "\\{[a-z_]{0,12}\\}",
+    ];
+    proptest::collection::vec(piece, 0..12).prop_map(|p| p.concat())
+}
+
+/// A state as `serve` publishes it, with any text in the track's fields.
+fn state() -> impl Strategy<Value = serde_json::Value> {
+    (
+        proptest::option::of(any::<String>()),
+        proptest::option::of(any::<String>()),
+        proptest::option::of(any::<String>()),
+        any::<String>(),
+        proptest::option::of(any::<f64>()),
+        proptest::option::of(any::<f64>()),
+        any::<bool>(),
+        proptest::sample::select(&["playing", "paused", "stopped"][..]),
+    ).prop_map(|(title, album, artist, player, position, length, live, status)| json!({
+        "title": title,
+        "album": album,
+        "artist": artist,
+        "player": player,
+        "position": position,
+        "length": length,
+        "live": live,
+        "status": status,
+    }))
+}
+
+/// Reverses [`escape_pango`].
+fn unescape_pango(s: &str) -> String {
+    s.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+proptest! {
+    #[test]
+    fn parsing_never_panics(s in "\\PC*") {
+        let _ = Template::parse(&s, FIELDS);
+    }
+
+    #[test]
+    fn rendering_never_panics_and_fits(template in template(), state in state()) {
+        if let Ok(template) = Template::parse(&template, FIELDS) {
+            let line = render_state(&template, &state, Some("seek"), &LineOptions::default());
+            prop_assert!(width(&line) <= MAX_STATUS_LEN);
+        }
+    }
+
+    #[test]
+    fn truncation_fits_and_keeps_short_lines(line in "\\PC*", max in 0..100usize, ellipsis in "\\PC{0,3}") {
+        let truncated = truncate(line.clone(), max, &ellipsis);
+        prop_assert!(width(&truncated) <= max);
+        if width(&line) <= max {
+            prop_assert_eq!(truncated, line);
+        } else {
+            prop_assert!(line.starts_with(truncated.strip_suffix(ellipsis.as_str()).unwrap_or(&truncated)));
+        }
+    }
+
+    #[test]
+    fn scrolled_windows_keep_their_width(line in "\\PC*", offset in 0..200usize, columns in 0..100usize) {
+        let window = scroll(&line, offset, columns);
+        if width(&line) <= columns {
+            prop_assert_eq!(window, line);
+        } else {
+            prop_assert_eq!(width(&window), columns);
+        }
+    }
+
+    #[test]
+    fn templates_without_fields_render_as_written(text in "[^{}]*") {
+        let template = Template::parse(&text, FIELDS).unwrap();
+        prop_assert_eq!(template.render(|_| unreachable!()), text);
+    }
+
+    #[test]
+    fn json_round_trips(line in any::<String>()) {
+        let text = json!({ "text": line }).to_string();
+        let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+        prop_assert_eq!(parsed["text"].as_str(), Some(line.as_str()));
+    }
+
+    #[test]
+    fn pango_round_trips(line in any::<String>()) {
+        let escaped = escape_pango(&line);
+        prop_assert!(!escaped.contains('<') && !escaped.contains('>'));
+        prop_assert_eq!(unescape_pango(&escaped), line);
+    }
+
+    #[test]
+    fn html_round_trips(line in any::<String>()) {
+        let escaped = escape_html(&line);
+        prop_assert!(!escaped.contains(['<', '>', '"', '\'']));
+        prop_assert_eq!(unescape_pango(&escaped.replace("&#39;", "&apos;")), line);
+    }
+
+    #[test]
+    fn polybar_round_trips(line in any::<String>()) {
+        let escaped = escape_polybar(&line);
+        // every `%` is doubled, so none can start a tag
+        prop_assert!(escaped.split("%%").all(|part| !part.contains('%')));
+        prop_assert_eq!(escaped.replace("%%", "%"), line);
+    }
+}
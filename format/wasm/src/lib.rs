@@ -0,0 +1,19 @@
+//! The status line templates of playing.rs as a WASM module, for web pages rendering the state
+//! that `serve` streams. Build it with `wasm-pack build format/wasm --target web`.
+
+use playing_format::{render_state, LineOptions, Template, FIELDS};
+use wasm_bindgen::prelude::*;
+
+/// Renders `template` from `state`, the JSON of `GET /status` or of an `/events` message,
+/// `event` being the name of that message, as `playing status` would with `options`, the JSON of
+/// keys of `[status]` such as `{"max_title": 20, "escape": "html"}`.
+#[wasm_bindgen]
+pub fn render(template: &str, state: &str, event: Option<String>, options: Option<String>) -> Result<String, JsError> {
+    let template = Template::parse(template, FIELDS).map_err(|e| JsError::new(&format!("{}", e)))?;
+    let state = serde_json::from_str(state).map_err(|e| JsError::new(&format!("invalid state: {}", e)))?;
+    let options = match options {
+        Some(o) => LineOptions::from_json(&serde_json::from_str(&o).map_err(|e| JsError::new(&format!("invalid options: {}", e)))?),
+        None => LineOptions::default(),
+    };
+    Ok(render_state(&template, &state, event.as_deref(), &options))
+}
@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fmt::Display};
+use serde::Deserialize;
+
+use crate::{Action, PlayingError};
+
+/// Where a command comes from.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Cli,
+    Http,
+    Mqtt,
+    Dbus,
+    /// Gamepads and MIDI controllers, see `[[input]]`.
+    Input,
+    /// Programs linking the library, e.g. through the C API of `playing-ffi`.
+    Library,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Cli => write!(f, "cli"),
+            Source::Http => write!(f, "http"),
+            Source::Mqtt => write!(f, "mqtt"),
+            Source::Dbus => write!(f, "dbus"),
+            Source::Input => write!(f, "input"),
+            Source::Library => write!(f, "library"),
+        }
+    }
+}
+
+/// Per-source permissions, read from the `[access.<source>]` tables of the config file:
+///
+/// ```toml
+/// [access.mqtt]
+/// allow = ["toggle", "next", "previous"]
+///
+/// [access.http]
+/// read_only = true
+/// ```
+///
+/// Commands are named after their subcommand (`toggle`, `seek-relative`, `status`, ...); every
+/// seeking operation also answers to `seek`, and `*` matches anything. Sources without a rule
+/// may run everything.
+#[derive(Deserialize, Default, Debug)]
+#[serde(transparent)]
+pub struct AccessRules(HashMap<Source, Rule>);
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    /// When set, only these commands are permitted.
+    allow: Option<Vec<String>>,
+    /// Commands that are never permitted, even when allowed.
+    deny: Vec<String>,
+    /// Only permit commands that don't change the players' state.
+    read_only: bool,
+}
+
+impl Rule {
+    fn permits(&self, names: &[&str], read_only: bool) -> bool {
+        let matches = |list: &[String]| list.iter().any(|l| l == "*" || names.contains(&l.as_str()));
+        if matches(&self.deny) || (self.read_only && !read_only) {
+            return false
+        }
+        match self.allow {
+            Some(ref allow) => matches(allow),
+            None => true,
+        }
+    }
+}
+
+impl AccessRules {
+    pub fn check(&self, source: Source, action: &Action) -> Result<(), PlayingError> {
+        self.check_names(source, action.permission_names(), action.is_read_only())
+    }
+
+    /// Checks a command that isn't an [`Action`], e.g. an endpoint of the http server.
+    pub fn check_names(&self, source: Source, names: &[&str], read_only: bool) -> Result<(), PlayingError> {
+        match self.0.get(&source) {
+            Some(rule) if !rule.permits(names, read_only) => {
+                Err(PlayingError::denied(format!("{} is not allowed to run `{}`", source, names[0])))
+            }
+            _ => Ok(()),
+        }
+    }
+}
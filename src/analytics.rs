@@ -0,0 +1,163 @@
+use std::{collections::HashMap, fs::OpenOptions, io::Write, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::MediaPlayer, config::Config, recent, Mode};
+
+/// Operations on another player this soon after the ranking's pick count as overriding it.
+const OVERRIDE_WINDOW: u64 = 10;
+/// A toggle undone this soon counts as the ranking's pick being wrong.
+const RETOGGLE_WINDOW: u64 = 5;
+/// Past this size the log is cut down to its newer half.
+const MAX_LOG_SIZE: u64 = 1 << 20;
+/// Overrides needed before `tune` suggests anything about a pair of players.
+const MIN_OVERRIDES: usize = 3;
+
+/// The `[analytics]` table of the config file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// Log which players operations picked, locally, for `tune` to learn from.
+    pub enabled: bool,
+}
+
+/// An operation run from any source, as logged.
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    /// Unix time it ran at.
+    at: u64,
+    operation: String,
+    /// Players it acted on.
+    targets: Vec<String>,
+    /// Whether `--player` named the player, rather than the ranking picking it.
+    explicit: bool,
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("analytics.jsonl")
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Logs that `operation` acted on `targets`, if analytics are enabled.
+pub fn record(config: &Config, operation: &str, targets: &[&dyn MediaPlayer]) {
+    if !config.analytics.enabled || targets.is_empty() {
+        return
+    }
+    let Some(path) = path() else { return };
+    let entry = Entry {
+        at: now(),
+        operation: operation.to_owned(),
+        targets: targets.iter().map(|p| p.identity().to_owned()).collect(),
+        explicit: config.player.as_deref().is_some_and(|p| p != recent::LAST_ACTIVE),
+    };
+    let written = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut f| writeln!(f, "{}", serde_json::to_string(&entry).unwrap_or_default()));
+    if let Err(e) = written {
+        eprintln!("cannot log to {}: {}", path.display(), e);
+        return
+    }
+    if path.metadata().is_ok_and(|m| m.len() > MAX_LOG_SIZE) {
+        let entries = std::fs::read_to_string(&path).unwrap_or_default();
+        let lines: Vec<&str> = entries.lines().collect();
+        let _ = crate::store::write_atomic(&path, (lines[lines.len() / 2..].join("\n") + "\n").as_bytes());
+    }
+}
+
+fn load() -> Vec<Entry> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|c| c.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// What the log says about the ranking's picks.
+#[derive(Default)]
+struct Findings {
+    picks: usize,
+    /// How often the ranking picked the first player and the user then named the second one.
+    overrides: HashMap<(String, String), usize>,
+    retoggles: usize,
+}
+
+fn analyze(entries: &[Entry]) -> Findings {
+    let mut findings = Findings::default();
+    for (i, e) in entries.iter().enumerate().filter(|(_, e)| !e.explicit) {
+        findings.picks += 1;
+        let Some(next) = entries.get(i + 1) else { continue };
+        if next.explicit && next.at.saturating_sub(e.at) <= OVERRIDE_WINDOW {
+            for picked in e.targets.iter().filter(|t| !next.targets.contains(t)) {
+                for wanted in &next.targets {
+                    *findings.overrides.entry((picked.clone(), wanted.clone())).or_default() += 1;
+                }
+            }
+        } else if e.operation == "toggle" && next.operation == "toggle" && next.targets == e.targets && next.at.saturating_sub(e.at) <= RETOGGLE_WINDOW {
+            findings.retoggles += 1;
+        }
+    }
+    findings
+}
+
+/// The ranking with every player the user keeps preferring moved right before the one the
+/// ranking picked instead.
+fn suggest(ranking: &[String], overrides: &HashMap<(String, String), usize>) -> Vec<String> {
+    let mut suggested = ranking.to_vec();
+    let mut pairs: Vec<(&(String, String), &usize)> = overrides.iter()
+        .filter(|((picked, wanted), n)| **n >= MIN_OVERRIDES && **n > overrides.get(&(wanted.clone(), picked.clone())).copied().unwrap_or_default())
+        .collect();
+    pairs.sort_by(|a, b| b.1.cmp(a.1));
+    for ((picked, wanted), _) in pairs {
+        let (Some(p), w) = (suggested.iter().position(|id| id == picked), suggested.iter().position(|id| id == wanted)) else { continue };
+        if w.is_some_and(|w| w < p) {
+            continue
+        }
+        if let Some(w) = w {
+            suggested.remove(w);
+        }
+        suggested.insert(p, wanted.clone());
+    }
+    suggested
+}
+
+/// Suggests a better ranking and strategy from the logged operations.
+pub fn tune(config: &Config) -> bool {
+    if !config.analytics.enabled {
+        eprintln!("analytics are disabled, set `enabled = true` in [analytics] in the config to start logging");
+        return false
+    }
+    let entries = load();
+    let findings = analyze(&entries);
+    if findings.picks == 0 {
+        println!("no operation was picked by the ranking yet, try again after using playing for a while");
+        return false
+    }
+    let overridden: usize = findings.overrides.values().sum();
+    println!("{} operations picked by the ranking: overridden {} times, toggled back right away {} times", findings.picks, overridden, findings.retoggles);
+    let mut overrides: Vec<_> = findings.overrides.iter().collect();
+    overrides.sort_by(|a, b| b.1.cmp(a.1));
+    for ((picked, wanted), n) in overrides {
+        println!("  picked `{}` but wanted `{}`: {} times", picked, wanted, n);
+    }
+
+    let ranking = config.ranking();
+    let suggested = suggest(&ranking, &findings.overrides);
+    let mut suggestions = false;
+    if suggested != ranking {
+        println!("suggested ranking:\n  ranking = {}", serde_json::to_string(&suggested).unwrap_or_default());
+        suggestions = true;
+    }
+    if findings.retoggles * 5 > findings.picks {
+        println!("toggles are often undone right away: `--player last-active` may pick better than the ranking");
+        suggestions = true;
+    }
+    if config.mode == Mode::Multiple && overridden * 5 > findings.picks {
+        println!("operations often act on too many players: `mode = \"single\"` may suit you better");
+        suggestions = true;
+    }
+    if !suggestions {
+        println!("the ranking picks well, nothing to suggest");
+    }
+    true
+}
@@ -0,0 +1,99 @@
+use std::{path::Path, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::JoinHandle, time::Duration};
+
+use crate::{access::Source, backend::{self, BackendKind}, config::Config, status::{self, StatusOptions}, Action, Operation, PlayingError, Status};
+
+/// The players as the command line sees them, with the config it would load; its commands are
+/// checked against the `[access.library]` rules.
+pub struct Session {
+    config: Arc<Config>,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Session {
+    /// Loads the config at `path`, else the usual one.
+    pub fn new(path: Option<&Path>) -> Result<Session, PlayingError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build()?;
+        Ok(Session { config: Arc::new(Config::load(path)?), runtime })
+    }
+
+    /// The line `status` prints, none when nothing is playing.
+    pub fn now_playing(&self) -> Result<Option<String>, PlayingError> {
+        self.config.access.check_names(Source::Library, &["status"], true)?;
+        now_playing(&self.config)
+    }
+
+    /// Toggles the player `op toggle` would.
+    pub fn toggle(&self) -> Result<(), PlayingError> {
+        self.run(Operation::Toggle)
+    }
+
+    /// Seeks the player `op seek-relative` would by `seconds`, backwards when negative; NaN,
+    /// infinities and more seconds than a track can last are refused.
+    pub fn seek(&self, seconds: f64) -> Result<(), PlayingError> {
+        if !seconds.is_finite() || seconds.abs() > f32::MAX.into() {
+            return Err(PlayingError::bad_command(format!("cannot seek by {:e} seconds", seconds)))
+        }
+        let seconds = seconds as f32;
+        crate::offset(seconds)?;
+        self.run(Operation::SeekRelative { seconds })
+    }
+
+    fn run(&self, op: Operation) -> Result<(), PlayingError> {
+        let action = Action::Operation(op);
+        self.runtime.block_on(crate::dispatch(&self.config, BackendKind::Auto, Source::Library, action)).map(|_| ())
+    }
+
+    /// Calls `callback` with the line of [`Session::now_playing`] whenever it changes, and once
+    /// at first, looking every `interval` until the subscription is dropped.
+    pub fn subscribe(&self, interval: Duration, mut callback: impl FnMut(Option<&str>) + Send + 'static) -> Result<Subscription, PlayingError> {
+        self.config.access.check_names(Source::Library, &["status"], true)?;
+        let (config, stopped) = (self.config.clone(), Arc::new(AtomicBool::new(false)));
+        let stop = stopped.clone();
+        let thread = std::thread::spawn(move || {
+            let mut last = None;
+            while !stop.load(Ordering::SeqCst) {
+                match now_playing(&config) {
+                    Ok(line) if Some(&line) != last.as_ref() => {
+                        callback(line.as_deref());
+                        last = Some(line);
+                    }
+                    Ok(_) => {}
+                    // the players may be back by the next look
+                    Err(e) => eprintln!("cannot tell what's playing: {}", e),
+                }
+                // woken up early by the drop of the subscription
+                std::thread::park_timeout(interval);
+            }
+        });
+        Ok(Subscription { stopped, thread: Some(thread) })
+    }
+}
+
+fn now_playing(config: &Config) -> Result<Option<String>, PlayingError> {
+    let backends = backend::open(BackendKind::Auto, config)?;
+    let players = backend::players(&backends)?;
+    let opts = StatusOptions::new(None, None, config, false, 1)?;
+    let policy = config.selection_policy("status");
+    let ranking = crate::selection::ranking(config, policy, &players)?;
+    // as `status`, which has a line only for the player it picks when that one plays
+    match crate::selected_player(&players, &ranking, policy)? {
+        Some(p) if p.status()? == Status::Playing => Ok(Some(status::render(p, &opts, None)?)),
+        _ => Ok(None),
+    }
+}
+
+/// The callbacks of [`Session::subscribe`], stopped once dropped.
+pub struct Subscription {
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
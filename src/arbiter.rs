@@ -0,0 +1,123 @@
+use std::{collections::HashMap, thread::sleep, time::Duration};
+
+use crate::{backend::{self, Backend, MediaPlayer, Status}, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A player that started playing and the players paused for it.
+struct Interruption {
+    by: String,
+    paused: Vec<String>,
+}
+
+/// What tells players apart, as several instances of e.g. mpv share their identity.
+fn key(p: &dyn MediaPlayer) -> String {
+    p.bus_name().unwrap_or(p.identity()).to_owned()
+}
+
+/// Pauses the other players whenever one starts playing, and resumes them once it stops.
+///
+/// Interruptions stack: music paused for a video paused for a call comes back after the video,
+/// which comes back after the call. A player resumed or stopped by hand in the meantime is left
+/// alone.
+///
+/// The players still paused for an interruption are resumed on SIGTERM or SIGINT, rather than
+/// left waiting for a daemon that's gone.
+pub fn run(backends: &[Box<dyn Backend>]) -> Result<bool, PlayingError> {
+    let mut last: Option<HashMap<String, Status>> = None;
+    let mut interruptions: Vec<Interruption> = vec![];
+    crate::shutdown::install();
+    loop {
+        if crate::shutdown::requested() {
+            release(backends, &interruptions);
+            return Ok(true)
+        }
+        match backend::players(backends) {
+            // players already playing together when starting are left to be
+            Ok(players) => match last {
+                Some(ref mut last) => arbitrate(&players, last, &mut interruptions),
+                None => last = Some(players.iter().filter_map(|p| Some((key(p.as_ref()), p.status().ok()?))).collect()),
+            },
+            Err(e) => eprintln!("cannot list the players: {}", e.inner),
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// Resumes the players paused for `interruptions` that are still paused.
+fn release(backends: &[Box<dyn Backend>], interruptions: &[Interruption]) {
+    let players = match backend::players(backends) {
+        Ok(players) => players,
+        Err(e) => return eprintln!("cannot list the players to resume them: {}", e.inner),
+    };
+    for k in interruptions.iter().flat_map(|i| &i.paused) {
+        let Some(p) = players.iter().find(|p| key(p.as_ref()) == *k) else { continue };
+        // resumed by hand in the meantime, or stopped
+        if !p.status().is_ok_and(|s| s == Status::Paused) {
+            continue
+        }
+        match p.play() {
+            Ok(_) => println!("resuming {}", p.identity()),
+            Err(e) => eprintln!("cannot resume {}: {}", p.identity(), e.inner),
+        }
+    }
+}
+
+fn arbitrate(players: &[Box<dyn MediaPlayer>], last: &mut HashMap<String, Status>, interruptions: &mut Vec<Interruption>) {
+    let statuses: HashMap<String, Status> = players.iter()
+        .filter_map(|p| Some((key(p.as_ref()), p.status().ok()?)))
+        .collect();
+    let find = |k: &str| players.iter().find(|p| key(p.as_ref()) == k);
+
+    // players that came back on their own were not waiting for anyone anymore
+    for i in interruptions.iter_mut() {
+        i.paused.retain(|k| statuses.get(k) == Some(&Status::Paused));
+    }
+
+    let started: Vec<&String> = statuses.iter()
+        .filter(|(k, s)| **s == Status::Playing && last.get(*k) != Some(&Status::Playing))
+        .map(|(k, _)| k)
+        .collect();
+    *last = statuses.clone();
+    if let Some(by) = started.first() {
+        let mut paused = vec![];
+        for (k, _) in statuses.iter().filter(|(k, s)| **s == Status::Playing && k != by) {
+            let Some(p) = find(k) else { continue };
+            match p.pause() {
+                Ok(_) => {
+                    println!("{} started playing, pausing {}", find(by).map_or(by.as_str(), |p| p.identity()), p.identity());
+                    paused.push(k.clone());
+                }
+                Err(e) => eprintln!("cannot pause {}: {}", p.identity(), e.inner),
+            }
+        }
+        interruptions.retain(|i| &i.by != *by);
+        if !paused.is_empty() {
+            interruptions.push(Interruption { by: (*by).clone(), paused: paused.clone() });
+        }
+        last.extend(paused.into_iter().map(|k| (k, Status::Paused)));
+    }
+
+    // an interruption ends when its player stops playing, resuming the others unless some
+    // other player took over in the meantime
+    let mut ended = vec![];
+    interruptions.retain(|i| {
+        let over = statuses.get(&i.by) != Some(&Status::Playing);
+        if over {
+            ended.push(i.paused.clone());
+        }
+        !over
+    });
+    if !last.values().any(|s| *s == Status::Playing) {
+        for k in ended.into_iter().flatten() {
+            let Some(p) = find(&k) else { continue };
+            match p.play() {
+                Ok(_) => {
+                    println!("resuming {}", p.identity());
+                    last.insert(k, Status::Playing);
+                }
+                Err(e) => eprintln!("cannot resume {}: {}", p.identity(), e.inner),
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::{process::Command, time::Duration};
+
+use super::{Backend, MediaPlayer, Status, Track};
+use crate::PlayingError;
+
+/// Apps scriptable through their AppleScript dictionary, as `(application, identity)`.
+const APPS: [(&str, &str); 2] = [("Spotify", "Spotify"), ("Music", "Music")];
+
+fn osascript(script: &str) -> Result<String, PlayingError> {
+    let out = Command::new("osascript").arg("-e").arg(script).output()?;
+    if out.status.success() {
+        Ok(String::from_utf8_lossy(&out.stdout).trim_end().to_owned())
+    } else {
+        Err(PlayingError::osascript(String::from_utf8_lossy(&out.stderr).trim_end().to_owned()))
+    }
+}
+
+/// Parses an AppleScript number, which uses the locale's decimal separator.
+fn number(s: &str) -> Option<f64> {
+    s.trim().replace(',', ".").parse().ok()
+}
+
+/// Music and Spotify, driven with AppleScript.
+pub struct MacBackend;
+
+impl Backend for MacBackend {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+        let mut players: Vec<Box<dyn MediaPlayer>> = vec![];
+        for (app, identity) in APPS {
+            // asking whether it's running doesn't launch the app, unlike `tell`
+            if osascript(&format!("application \"{}\" is running", app))? == "true" {
+                players.push(Box::new(MacPlayer { app, identity }));
+            }
+        }
+        Ok(players)
+    }
+}
+
+struct MacPlayer {
+    app: &'static str,
+    identity: &'static str,
+}
+
+impl MacPlayer {
+    fn tell(&self, script: &str) -> Result<String, PlayingError> {
+        osascript(&format!("tell application \"{}\"\n{}\nend tell", self.app, script))
+    }
+}
+
+impl MediaPlayer for MacPlayer {
+    fn identity(&self) -> &str {
+        self.identity
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        Ok(match self.tell("player state as string")?.as_str() {
+            "playing" => Status::Playing,
+            "paused" => Status::Paused,
+            _ => Status::Stopped,
+        })
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        // Spotify has urls and counts milliseconds, Music counts seconds
+        let url = if self.app == "Spotify" { "spotify url of t" } else { "\"\"" };
+        let out = self.tell(&format!(
+            "set t to current track\nreturn (name of t) & linefeed & (album of t) & linefeed & (album artist of t) & linefeed & (artist of t) & linefeed & (duration of t) & linefeed & (track number of t) & linefeed & ({})",
+            url,
+        ))?;
+        let mut lines = out.lines().map(str::to_owned);
+        let mut next = || lines.next().filter(|l| !l.is_empty());
+        let (title, album, album_artist, artist, duration, track_number, url) = (next(), next(), next(), next(), next(), next(), next());
+        let scale = if self.app == "Spotify" { 1000.0 } else { 1.0 };
+        Ok(Track {
+            title,
+            album,
+            album_artists: album_artist.into_iter().collect(),
+            artists: artist.into_iter().collect(),
+            url,
+            art_url: None,
+            length: duration.as_deref().and_then(number).map(|d| Duration::from_secs_f64(d.max(0.0) / scale)),
+            track_number: track_number.and_then(|n| n.parse().ok()).filter(|n| *n > 0),
+            live: false,
+        })
+    }
+
+    fn playlist(&self) -> Result<Option<(usize, usize)>, PlayingError> {
+        // Spotify doesn't expose its queue
+        if self.app != "Music" {
+            return Ok(None)
+        }
+        let out = self.tell("return (index of current track) & linefeed & (count of tracks of current playlist)")?;
+        let mut numbers = out.lines().map(|l| l.trim().parse::<usize>().ok());
+        Ok(numbers.next().flatten().zip(numbers.next().flatten()))
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        Ok(number(&self.tell("player position")?).map(|p| Duration::from_secs_f64(p.max(0.0))))
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        self.tell("play").map(|_| ())
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        self.tell("pause").map(|_| ())
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        self.tell("next track").map(|_| ())
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        self.tell("previous track").map(|_| ())
+    }
+
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError> {
+        self.tell(&format!("set player position to (player position + ({}))", offset_us as f64 / 1e6)).map(|_| ())
+    }
+
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError> {
+        self.tell(&format!("set player position to {}", position.as_secs_f64())).map(|_| ())
+    }
+}
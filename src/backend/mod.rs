@@ -0,0 +1,357 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Duration};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{config::Config, PlayingError};
+
+#[cfg(target_os = "macos")]
+mod macos;
+mod mpd;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod mpris;
+#[cfg(unix)]
+mod mpv;
+mod replay;
+#[cfg(windows)]
+mod smtc;
+
+pub use mpd::{MpdBackend, MpdConfig};
+pub use replay::{Frame, Recorded, ReplayBackend};
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use mpris::MprisBackend;
+#[cfg(unix)]
+pub use mpv::MpvIpc;
+
+/// The `[mpv]` table of the config file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MpvConfig {
+    /// Path of the socket mpv was started with `--input-ipc-server`.
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Playing => "playing",
+            Status::Paused => "paused",
+            Status::Stopped => "stopped",
+        }
+    }
+}
+
+/// What a player does once a track ends.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum LoopMode {
+    None,
+    Track,
+    Playlist,
+}
+
+impl LoopMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoopMode::None => "none",
+            LoopMode::Track => "track",
+            LoopMode::Playlist => "playlist",
+        }
+    }
+}
+
+/// Metadata of the current track, independent of where it came from.
+#[derive(Clone, Default, Debug)]
+pub struct Track {
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub album_artists: Vec<String>,
+    /// Artists of the track itself, the only ones most browsers tell.
+    pub artists: Vec<String>,
+    pub url: Option<String>,
+    /// Cover art of the track, usually a `file://` or `https://` URL.
+    pub art_url: Option<String>,
+    pub length: Option<Duration>,
+    /// Position of the track in its album.
+    pub track_number: Option<u32>,
+    /// The player flags the track as a livestream.
+    pub live: bool,
+}
+
+/// Lengths past this are made up, e.g. by livestreams reporting hours of buffer.
+const MAX_LENGTH: Duration = Duration::from_secs(6 * 3600);
+
+/// Whether `url` points to a livestream rather than a video on demand.
+fn is_live_url(url: &str) -> bool {
+    (url.contains("twitch.tv/") && !url.contains("/videos/") && !url.contains("/clip"))
+        || url.contains("youtube.com/live/")
+        || (url.contains("youtube.com/") && url.trim_end_matches('/').ends_with("/live"))
+}
+
+impl Track {
+    /// The first album artist, or else the first artist of the track.
+    pub fn artist(&self) -> Option<&String> {
+        self.album_artists.first().or(self.artists.first())
+    }
+
+    /// The album artists, or else the artists of the track.
+    pub fn artist_list(&self) -> &[String] {
+        if self.album_artists.is_empty() { &self.artists } else { &self.album_artists }
+    }
+
+    /// Whether the track is a livestream: flagged by the player, at a live URL, with an absurd
+    /// length (zero or longer than six hours) or streamed without any length.
+    pub fn is_live(&self) -> bool {
+        let url = self.url.as_deref().unwrap_or_default();
+        self.live
+            || is_live_url(url)
+            || matches!(self.length, Some(l) if l.is_zero() || l > MAX_LENGTH)
+            || (self.length.is_none() && (url.starts_with("http://") || url.starts_with("https://")))
+    }
+
+    /// The length, unless it's absurd.
+    pub fn plausible_length(&self) -> Option<Duration> {
+        self.length.filter(|_| !self.is_live())
+    }
+}
+
+/// A chapter of the current track.
+#[derive(Clone, Debug)]
+pub struct Chapter {
+    pub title: Option<String>,
+    pub start: Duration,
+}
+
+/// What a player claims to support.
+#[derive(Clone, Copy, Debug)]
+pub struct Capabilities {
+    pub control: bool,
+    pub play: bool,
+    pub pause: bool,
+    pub next: bool,
+    pub previous: bool,
+    pub seek: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities { control: true, play: true, pause: true, next: true, previous: true, seek: true }
+    }
+}
+
+/// A single controllable player exposed by a [`Backend`].
+pub trait MediaPlayer {
+    fn identity(&self) -> &str;
+    /// Name of the player's `.desktop` file, e.g. `org.mozilla.firefox`.
+    fn desktop_entry(&self) -> Option<String> {
+        None
+    }
+    /// Name the player owns on the session bus.
+    fn bus_name(&self) -> Option<&str> {
+        None
+    }
+    fn capabilities(&self) -> Result<Capabilities, PlayingError> {
+        Ok(Capabilities::default())
+    }
+    fn status(&self) -> Result<Status, PlayingError>;
+    fn track(&self) -> Result<Track, PlayingError>;
+    fn position(&self) -> Result<Option<Duration>, PlayingError>;
+    /// Playback speed, 1.0 being normal speed.
+    fn rate(&self) -> Result<f64, PlayingError> {
+        Ok(1.0)
+    }
+    fn play(&self) -> Result<(), PlayingError>;
+    fn pause(&self) -> Result<(), PlayingError>;
+    /// Stops playback, pausing the players that can't stop.
+    fn stop(&self) -> Result<(), PlayingError> {
+        self.pause()
+    }
+    fn next(&self) -> Result<(), PlayingError>;
+    fn previous(&self) -> Result<(), PlayingError>;
+    /// Seeks relative to the current position, as MPRIS' `Seek`.
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError>;
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError>;
+
+    /// Volume from 0.0 to 1.0, as in MPRIS.
+    fn volume(&self) -> Result<f64, PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't tell its volume", self.identity())))
+    }
+
+    fn set_volume(&self, _volume: f64) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support setting the volume", self.identity())))
+    }
+
+    /// Plays the file, URL or playlist at `uri`, replacing what was playing.
+    fn open(&self, _uri: &str) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support opening URIs", self.identity())))
+    }
+
+    fn loop_mode(&self) -> Result<LoopMode, PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't tell its loop mode", self.identity())))
+    }
+
+    fn set_loop_mode(&self, _mode: LoopMode) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support loop modes", self.identity())))
+    }
+
+    // Extended controls, only available through mpv's IPC for now.
+
+    fn chapter(&self, _offset: i64) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support chapter navigation", self.identity())))
+    }
+
+    /// Chapters of the current track, in order; none when the player doesn't tell.
+    fn chapters(&self) -> Result<Vec<Chapter>, PlayingError> {
+        Ok(vec![])
+    }
+
+    fn frame_step(&self, _back: bool) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support frame stepping", self.identity())))
+    }
+
+    /// The 1-based position of the current track in the playlist and the playlist's length.
+    fn playlist(&self) -> Result<Option<(usize, usize)>, PlayingError> {
+        Ok(None)
+    }
+
+    /// Jumps to the 1-based `position` in the playlist.
+    fn playlist_jump(&self, _position: usize) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support playlist jumps", self.identity())))
+    }
+
+    /// Sets the A, then the B point of the loop, then clears it.
+    fn ab_loop(&self) -> Result<(), PlayingError> {
+        Err(PlayingError::unsupported(format!("{} doesn't support A-B loops", self.identity())))
+    }
+}
+
+/// A source of players, e.g. the MPRIS players on the session bus.
+pub trait Backend {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum BackendKind {
+    /// The platform's native backend, plus MPD when its server is reachable
+    Auto,
+    /// MPRIS, with mpv over IPC when configured (Linux and BSDs)
+    Mpris,
+    Mpd,
+    /// Windows' media sessions
+    Smtc,
+    /// Music and Spotify through AppleScript
+    Macos,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn mpris(config: &Config) -> Result<Box<dyn Backend>, PlayingError> {
+    Ok(Box::new(mpris::MprisBackend::new(mpv::MpvIpc::connect(&config.mpv))?))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn mpris(_: &Config) -> Result<Box<dyn Backend>, PlayingError> {
+    Err(PlayingError::unsupported("mpris is not available on this platform"))
+}
+
+#[cfg(windows)]
+fn smtc() -> Result<Box<dyn Backend>, PlayingError> {
+    Ok(Box::new(smtc::SmtcBackend::new()?))
+}
+
+#[cfg(not(windows))]
+fn smtc() -> Result<Box<dyn Backend>, PlayingError> {
+    Err(PlayingError::unsupported("media sessions are only available on windows"))
+}
+
+#[cfg(target_os = "macos")]
+fn macos() -> Result<Box<dyn Backend>, PlayingError> {
+    Ok(Box::new(macos::MacBackend))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn macos() -> Result<Box<dyn Backend>, PlayingError> {
+    Err(PlayingError::unsupported("the AppleScript backend is only available on macos"))
+}
+
+/// The platform's own media player interface.
+pub fn native(config: &Config) -> Result<Box<dyn Backend>, PlayingError> {
+    if cfg!(windows) {
+        smtc()
+    } else if cfg!(target_os = "macos") {
+        macos()
+    } else {
+        mpris(config)
+    }
+}
+
+thread_local! {
+    /// The backends [`keep_open`] holds, handed out by [`open`] instead of connecting again.
+    static KEPT: RefCell<Vec<Rc<dyn Backend>>> = const { RefCell::new(vec![]) };
+}
+
+/// A backend held by [`keep_open`].
+struct Kept(Rc<dyn Backend>);
+
+impl Backend for Kept {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+        self.0.players()
+    }
+}
+
+/// Connects to the backends selected by `kind` and has every later [`open`] of this thread
+/// share them, whatever kind it asks for, e.g. for the commands read by `stdin`. Failing to
+/// connect lets [`open`] connect on its own again.
+pub fn keep_open(kind: BackendKind, config: &Config) -> Result<(), PlayingError> {
+    KEPT.with(|k| k.borrow_mut().clear());
+    let backends = open(kind, config)?;
+    KEPT.with(|k| *k.borrow_mut() = backends.into_iter().map(Rc::from).collect());
+    Ok(())
+}
+
+/// Connects to the backends selected by `kind`, unless `--fixture` gives the players or
+/// [`keep_open`] holds some already.
+pub fn open(kind: BackendKind, config: &Config) -> Result<Vec<Box<dyn Backend>>, PlayingError> {
+    let kept: Vec<Box<dyn Backend>> = KEPT.with(|k| k.borrow().iter().map(|b| Box::new(Kept(b.clone())) as Box<dyn Backend>).collect());
+    if !kept.is_empty() {
+        return Ok(kept)
+    }
+    let _span = crate::timing::span("connect");
+    if let Some(ref fixture) = config.fixture {
+        return Ok(vec![Box::new(ReplayBackend::load(fixture)?)])
+    }
+    match kind {
+        BackendKind::Mpris => Ok(vec![mpris(config)?]),
+        BackendKind::Smtc => Ok(vec![smtc()?]),
+        BackendKind::Macos => Ok(vec![macos()?]),
+        BackendKind::Mpd => Ok(vec![Box::new(mpd::MpdBackend::connect(&config.mpd)?)]),
+        BackendKind::Auto => {
+            let mpd = mpd::MpdBackend::connect(&config.mpd).ok();
+            let mut backends: Vec<Box<dyn Backend>> = vec![];
+            match native(config) {
+                Ok(b) => backends.push(b),
+                // without the native backend MPD alone is still useful
+                Err(e) if mpd.is_none() => return Err(e),
+                Err(_) => {}
+            }
+            if let Some(mpd) = mpd {
+                backends.push(Box::new(mpd));
+            }
+            Ok(backends)
+        }
+    }
+}
+
+/// All players of all `backends`.
+pub fn players(backends: &[Box<dyn Backend>]) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+    let _span = crate::timing::span("bus scan");
+    let mut players = vec![];
+    for b in backends {
+        players.extend(b.players()?);
+    }
+    Ok(players)
+}
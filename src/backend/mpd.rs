@@ -0,0 +1,242 @@
+use std::{cell::RefCell, io::{BufRead, BufReader, Read, Write}, net::{TcpStream, ToSocketAddrs}, path::PathBuf, rc::Rc, time::Duration};
+use serde::Deserialize;
+
+use super::{Backend, LoopMode, MediaPlayer, Status, Track};
+use crate::PlayingError;
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The `[mpd]` table of the config file. Unset fields fall back to `$MPD_HOST`/`$MPD_PORT`, then
+/// to the user socket in `$XDG_RUNTIME_DIR/mpd/socket` and finally to `localhost:6600`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MpdConfig {
+    /// Host name, or path of a unix socket when starting with `/`.
+    host: Option<String>,
+    port: Option<u16>,
+    password: Option<String>,
+}
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+struct Connection {
+    stream: RefCell<BufReader<Box<dyn Stream>>>,
+}
+
+impl Connection {
+    fn open(config: &MpdConfig) -> Result<Self, PlayingError> {
+        let mut host = config.host.clone().or_else(|| std::env::var("MPD_HOST").ok());
+        let mut password = config.password.clone();
+        // MPD_HOST may carry the password as `password@host`
+        if let Some((pass, h)) = host.as_deref().and_then(|h| h.split_once('@')) {
+            if !pass.is_empty() && !h.is_empty() {
+                password.get_or_insert_with(|| pass.to_owned());
+                host = Some(h.to_owned());
+            }
+        }
+        let host = host.or_else(|| {
+            let socket = PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR")?).join("mpd/socket");
+            socket.exists().then(|| socket.to_string_lossy().into_owned())
+        });
+
+        let stream: Box<dyn Stream> = match host {
+            #[cfg(unix)]
+            Some(h) if h.starts_with('/') => {
+                let s = std::os::unix::net::UnixStream::connect(h)?;
+                s.set_read_timeout(Some(TIMEOUT))?;
+                s.set_write_timeout(Some(TIMEOUT))?;
+                Box::new(s)
+            }
+            h => {
+                let port = config.port
+                    .or_else(|| std::env::var("MPD_PORT").ok()?.parse().ok())
+                    .unwrap_or(6600);
+                let addr = (h.as_deref().unwrap_or("localhost"), port)
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| PlayingError::mpd("cannot resolve mpd host"))?;
+                let s = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+                s.set_read_timeout(Some(TIMEOUT))?;
+                s.set_write_timeout(Some(TIMEOUT))?;
+                Box::new(s)
+            }
+        };
+
+        let conn = Connection { stream: RefCell::new(BufReader::new(stream)) };
+        let mut greeting = String::new();
+        conn.stream.borrow_mut().read_line(&mut greeting)?;
+        if !greeting.starts_with("OK MPD") {
+            return Err(PlayingError::mpd(format!("unexpected greeting: {}", greeting.trim_end())))
+        }
+        if let Some(pass) = password {
+            conn.command(&format!("password \"{}\"", pass.replace('\\', "\\\\").replace('"', "\\\"")))?;
+        }
+        Ok(conn)
+    }
+
+    /// Sends `cmd` and collects the `key: value` pairs of the response.
+    fn command(&self, cmd: &str) -> Result<Vec<(String, String)>, PlayingError> {
+        let mut stream = self.stream.borrow_mut();
+        stream.get_mut().write_all(format!("{}\n", cmd).as_bytes())?;
+        let mut pairs = vec![];
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line)? == 0 {
+                return Err(PlayingError::mpd("connection closed"))
+            }
+            let line = line.trim_end_matches('\n');
+            if line == "OK" {
+                return Ok(pairs)
+            } else if let Some(ack) = line.strip_prefix("ACK ") {
+                return Err(PlayingError::mpd(ack.to_owned()))
+            } else if let Some((k, v)) = line.split_once(": ") {
+                pairs.push((k.to_owned(), v.to_owned()));
+            }
+        }
+    }
+}
+
+fn get<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+fn get_all(pairs: &[(String, String)], key: &str) -> Vec<String> {
+    pairs.iter().filter(|(k, _)| k == key).map(|(_, v)| v.clone()).collect()
+}
+
+pub struct MpdBackend {
+    conn: Rc<Connection>,
+}
+
+impl MpdBackend {
+    pub fn connect(config: &MpdConfig) -> Result<Self, PlayingError> {
+        Ok(MpdBackend { conn: Rc::new(Connection::open(config)?) })
+    }
+}
+
+impl Backend for MpdBackend {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+        Ok(vec![Box::new(MpdPlayer { conn: self.conn.clone() })])
+    }
+}
+
+/// The MPD server seen as a single player.
+struct MpdPlayer {
+    conn: Rc<Connection>,
+}
+
+impl MediaPlayer for MpdPlayer {
+    fn identity(&self) -> &str {
+        "mpd"
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        Ok(match get(&self.conn.command("status")?, "state") {
+            Some("play") => Status::Playing,
+            Some("pause") => Status::Paused,
+            _ => Status::Stopped,
+        })
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        let song = self.conn.command("currentsong")?;
+        let length = get(&song, "duration")
+            .or_else(|| get(&song, "Time"))
+            .and_then(|d| d.parse::<f64>().ok())
+            .map(Duration::from_secs_f64);
+        Ok(Track {
+            title: get(&song, "Title").map(str::to_owned),
+            album: get(&song, "Album").map(str::to_owned),
+            album_artists: get_all(&song, "AlbumArtist"),
+            artists: get_all(&song, "Artist"),
+            url: get(&song, "file").map(str::to_owned),
+            art_url: None,
+            length,
+            track_number: get(&song, "Track").and_then(|t| t.split('/').next()?.trim().parse().ok()),
+            live: false,
+        })
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        Ok(get(&self.conn.command("status")?, "elapsed")
+            .and_then(|e| e.parse::<f64>().ok())
+            .map(Duration::from_secs_f64))
+    }
+
+    fn playlist(&self) -> Result<Option<(usize, usize)>, PlayingError> {
+        let status = self.conn.command("status")?;
+        let position = get(&status, "song").and_then(|s| s.parse::<usize>().ok());
+        let length = get(&status, "playlistlength").and_then(|l| l.parse::<usize>().ok());
+        Ok(position.zip(length).map(|(p, l)| (p + 1, l)))
+    }
+
+    fn volume(&self) -> Result<f64, PlayingError> {
+        // -1 without a mixer
+        match get(&self.conn.command("status")?, "volume").and_then(|v| v.parse::<f64>().ok()) {
+            Some(v) if v >= 0.0 => Ok(v / 100.0),
+            _ => Err(PlayingError::unsupported("mpd has no mixer to set the volume with")),
+        }
+    }
+
+    fn set_volume(&self, volume: f64) -> Result<(), PlayingError> {
+        self.conn.command(&format!("setvol {}", (volume.clamp(0.0, 1.0) * 100.0).round())).map(|_| ())
+    }
+
+    fn open(&self, uri: &str) -> Result<(), PlayingError> {
+        let quoted = format!("\"{}\"", uri.replace('\\', "\\\\").replace('"', "\\\""));
+        self.conn.command("clear")?;
+        // stored playlists are loaded, anything else is a song of the library or a URL
+        if self.conn.command(&format!("load {}", quoted)).is_err() {
+            self.conn.command(&format!("add {}", quoted))?;
+        }
+        self.conn.command("play").map(|_| ())
+    }
+
+    fn loop_mode(&self) -> Result<LoopMode, PlayingError> {
+        let status = self.conn.command("status")?;
+        Ok(match (get(&status, "repeat"), get(&status, "single")) {
+            (Some("1"), Some("1")) => LoopMode::Track,
+            (Some("1"), _) => LoopMode::Playlist,
+            _ => LoopMode::None,
+        })
+    }
+
+    fn set_loop_mode(&self, mode: LoopMode) -> Result<(), PlayingError> {
+        let (repeat, single) = match mode {
+            LoopMode::None => (0, 0),
+            LoopMode::Track => (1, 1),
+            LoopMode::Playlist => (1, 0),
+        };
+        self.conn.command(&format!("repeat {}", repeat))?;
+        self.conn.command(&format!("single {}", single)).map(|_| ())
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        self.conn.command("play").map(|_| ())
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        self.conn.command("pause 1").map(|_| ())
+    }
+
+    fn stop(&self) -> Result<(), PlayingError> {
+        self.conn.command("stop").map(|_| ())
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        self.conn.command("next").map(|_| ())
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        self.conn.command("previous").map(|_| ())
+    }
+
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError> {
+        self.conn.command(&format!("seekcur {:+}", offset_us as f64 / 1e6)).map(|_| ())
+    }
+
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError> {
+        self.conn.command(&format!("seekcur {}", position.as_secs_f64())).map(|_| ())
+    }
+}
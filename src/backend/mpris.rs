@@ -0,0 +1,182 @@
+use std::time::Duration;
+use dbus::blocking::Connection;
+use mpris::{LoopStatus, PlaybackStatus, Player, PlayerFinder};
+
+use super::{mpv::MpvIpc, Backend, Capabilities, LoopMode, MediaPlayer, Status, Track};
+use crate::{PlayingError, PlayingErrorKind};
+
+pub struct MprisBackend {
+    finder: PlayerFinder,
+    /// mpv instance whose operations go through its IPC socket instead of MPRIS.
+    mpv: Option<MpvIpc>,
+}
+
+impl MprisBackend {
+    pub fn new(mpv: Option<MpvIpc>) -> Result<Self, PlayingError> {
+        match PlayerFinder::new() {
+            Ok(finder) => Ok(MprisBackend { finder, mpv }),
+            Err(e) => Err(PlayingError {
+                kind: PlayingErrorKind::DBus,
+                code: 8,
+                inner: e.into(),
+            }),
+        }
+    }
+}
+
+impl Backend for MprisBackend {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+        let players = match self.finder.find_all() {
+            Ok(players) => players,
+            Err(e) => return Err(PlayingError {
+                kind: PlayingErrorKind::DBus,
+                code: 8,
+                inner: e.into(),
+            }),
+        };
+
+        let mpv_instances = players.iter().filter(|p| p.identity() == "mpv").count();
+        let mut routed = false;
+        let mut out: Vec<Box<dyn MediaPlayer>> = vec![];
+        for p in players {
+            match self.mpv {
+                Some(ref mpv) if p.identity() == "mpv" && mpv.is_same_instance(p.bus_name(), mpv_instances) => {
+                    out.push(mpv.player(Some(p.bus_name())));
+                    routed = true;
+                }
+                _ => out.push(Box::new(p)),
+            }
+        }
+        // mpv without the mpris plugin is still reachable over IPC
+        if let (Some(ref mpv), false) = (&self.mpv, routed) {
+            out.push(mpv.player(None));
+        }
+        Ok(out)
+    }
+}
+
+impl MediaPlayer for Player {
+    fn identity(&self) -> &str {
+        Player::identity(self)
+    }
+
+    fn desktop_entry(&self) -> Option<String> {
+        self.get_desktop_entry().ok().flatten()
+    }
+
+    fn bus_name(&self) -> Option<&str> {
+        Some(Player::bus_name(self))
+    }
+
+    fn capabilities(&self) -> Result<Capabilities, PlayingError> {
+        Ok(Capabilities {
+            control: self.can_control()?,
+            play: self.can_play()?,
+            pause: self.can_pause()?,
+            next: self.can_go_next()?,
+            previous: self.can_go_previous()?,
+            seek: self.can_seek()?,
+        })
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        Ok(match self.get_playback_status()? {
+            PlaybackStatus::Playing => Status::Playing,
+            PlaybackStatus::Paused => Status::Paused,
+            PlaybackStatus::Stopped => Status::Stopped,
+        })
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        let meta = self.get_metadata()?;
+        let owned = |v: Option<Vec<&str>>| v.unwrap_or_default().into_iter().map(str::to_owned).collect();
+        // not in the spec, but some players flag streams as e.g. `xesam:live`
+        let live = meta.iter().any(|(k, v)| {
+            let name = k.rsplit(':').next().unwrap_or(k);
+            (name.eq_ignore_ascii_case("live") || name.eq_ignore_ascii_case("islive")) && v.as_bool() == Some(true)
+        });
+        Ok(Track {
+            title: meta.title().map(str::to_owned),
+            album: meta.album_name().map(str::to_owned),
+            album_artists: owned(meta.album_artists()),
+            artists: owned(meta.artists()),
+            url: meta.url().map(str::to_owned),
+            art_url: meta.art_url().map(str::to_owned),
+            length: meta.length(),
+            track_number: meta.track_number().and_then(|n| u32::try_from(n).ok()),
+            live,
+        })
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        Ok(self.checked_get_position()?)
+    }
+
+    fn rate(&self) -> Result<f64, PlayingError> {
+        Ok(self.checked_get_playback_rate()?.unwrap_or(1.0))
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        Ok(Player::play(self)?)
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        Ok(Player::pause(self)?)
+    }
+
+    fn stop(&self) -> Result<(), PlayingError> {
+        Ok(Player::stop(self)?)
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        Ok(Player::next(self)?)
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        Ok(Player::previous(self)?)
+    }
+
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError> {
+        Ok(Player::seek(self, offset_us)?)
+    }
+
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError> {
+        if let Some(id) = self.get_metadata()?.track_id() {
+            Player::set_position(self, id, &position)?
+        }
+        Ok(())
+    }
+
+    fn volume(&self) -> Result<f64, PlayingError> {
+        Ok(self.get_volume()?)
+    }
+
+    fn set_volume(&self, volume: f64) -> Result<(), PlayingError> {
+        Ok(Player::set_volume(self, volume)?)
+    }
+
+    fn open(&self, uri: &str) -> Result<(), PlayingError> {
+        // the mpris crate doesn't wrap OpenUri
+        let dbus = |e: dbus::Error| PlayingError { kind: PlayingErrorKind::DBus, code: 2, inner: e.into() };
+        Connection::new_session().map_err(dbus)?
+            .with_proxy(self.bus_name(), "/org/mpris/MediaPlayer2", Duration::from_secs(5))
+            .method_call("org.mpris.MediaPlayer2.Player", "OpenUri", (uri,))
+            .map_err(dbus)
+    }
+
+    fn loop_mode(&self) -> Result<LoopMode, PlayingError> {
+        Ok(match self.get_loop_status()? {
+            LoopStatus::None => LoopMode::None,
+            LoopStatus::Track => LoopMode::Track,
+            LoopStatus::Playlist => LoopMode::Playlist,
+        })
+    }
+
+    fn set_loop_mode(&self, mode: LoopMode) -> Result<(), PlayingError> {
+        Ok(self.set_loop_status(match mode {
+            LoopMode::None => LoopStatus::None,
+            LoopMode::Track => LoopStatus::Track,
+            LoopMode::Playlist => LoopStatus::Playlist,
+        })?)
+    }
+}
@@ -0,0 +1,234 @@
+use std::{cell::{Cell, RefCell}, io::{BufRead, BufReader, Write}, os::unix::net::UnixStream, path::PathBuf, rc::Rc, time::Duration};
+use serde_json::{json, Value};
+
+use super::{Chapter, LoopMode, MediaPlayer, MpvConfig, Status, Track};
+use crate::PlayingError;
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+struct Ipc {
+    stream: RefCell<BufReader<UnixStream>>,
+    request_id: Cell<u64>,
+}
+
+impl Ipc {
+    fn connect(path: &PathBuf) -> Result<Self, PlayingError> {
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        Ok(Ipc { stream: RefCell::new(BufReader::new(stream)), request_id: Cell::new(0) })
+    }
+
+    /// Runs an IPC command and returns its `data`, skipping the events mpv interleaves.
+    fn command(&self, command: Value) -> Result<Value, PlayingError> {
+        let id = self.request_id.get() + 1;
+        self.request_id.set(id);
+        let mut stream = self.stream.borrow_mut();
+        let mut request = json!({ "command": command, "request_id": id }).to_string();
+        request.push('\n');
+        stream.get_mut().write_all(request.as_bytes())?;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line)? == 0 {
+                return Err(PlayingError::mpv("ipc connection closed"))
+            }
+            let response: Value = serde_json::from_str(&line)
+                .map_err(|e| PlayingError::mpv(format!("invalid ipc response: {}", e)))?;
+            if response["request_id"] != id {
+                continue
+            }
+            return match response["error"].as_str() {
+                Some("success") => Ok(response["data"].clone()),
+                Some(e) => Err(PlayingError::mpv(e.to_owned())),
+                None => Err(PlayingError::mpv("invalid ipc response")),
+            }
+        }
+    }
+
+    fn get(&self, property: &str) -> Result<Value, PlayingError> {
+        self.command(json!(["get_property", property]))
+    }
+
+    fn run(&self, command: Value) -> Result<(), PlayingError> {
+        self.command(command).map(|_| ())
+    }
+}
+
+/// Connection to the mpv instance listening on the configured IPC socket.
+pub struct MpvIpc {
+    ipc: Rc<Ipc>,
+    pid: Option<u64>,
+}
+
+impl MpvIpc {
+    /// Connects to the configured socket, if any. A stale socket just means mpv isn't running.
+    pub fn connect(config: &MpvConfig) -> Option<Self> {
+        let ipc = Ipc::connect(config.socket.as_ref()?).ok()?;
+        let pid = ipc.get("pid").ok()?.as_u64();
+        Some(MpvIpc { ipc: Rc::new(ipc), pid })
+    }
+
+    /// Whether the MPRIS player on `bus_name` is this mpv instance, as exposed by mpv-mpris.
+    pub fn is_same_instance(&self, bus_name: &str, mpv_instances: usize) -> bool {
+        match self.pid {
+            Some(pid) if bus_name.ends_with(&format!(".instance{}", pid)) => true,
+            _ => mpv_instances == 1 && bus_name == "org.mpris.MediaPlayer2.mpv",
+        }
+    }
+
+    /// The player over IPC, standing in for the MPRIS player on `bus_name` if mpv-mpris exposes
+    /// it, so that `--player` and `players` still know it by that name.
+    pub fn player(&self, bus_name: Option<&str>) -> Box<dyn MediaPlayer> {
+        Box::new(MpvPlayer { ipc: self.ipc.clone(), bus_name: bus_name.map(ToOwned::to_owned) })
+    }
+}
+
+struct MpvPlayer {
+    ipc: Rc<Ipc>,
+    bus_name: Option<String>,
+}
+
+impl MpvPlayer {
+    /// A property holding seconds, missing while nothing is loaded.
+    fn seconds(&self, property: &str) -> Option<Duration> {
+        self.ipc.get(property).ok()?.as_f64().filter(|s| *s >= 0.0).map(Duration::from_secs_f64)
+    }
+}
+
+impl MediaPlayer for MpvPlayer {
+    fn identity(&self) -> &str {
+        "mpv"
+    }
+
+    fn bus_name(&self) -> Option<&str> {
+        self.bus_name.as_deref()
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        if self.ipc.get("idle-active")?.as_bool().unwrap_or(false) {
+            Ok(Status::Stopped)
+        } else if self.ipc.get("pause")?.as_bool().unwrap_or(false) {
+            Ok(Status::Paused)
+        } else {
+            Ok(Status::Playing)
+        }
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        let meta = self.ipc.get("metadata").unwrap_or(Value::Null);
+        // tag names keep the case of the file, so look them up case-insensitively
+        let tag = |key: &str| meta.as_object()
+            .and_then(|m| m.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)))
+            .and_then(|(_, v)| v.as_str())
+            .map(str::to_owned);
+        Ok(Track {
+            title: tag("title").or_else(|| self.ipc.get("media-title").ok()?.as_str().map(str::to_owned)),
+            album: tag("album"),
+            album_artists: tag("album_artist").into_iter().collect(),
+            artists: tag("artist").into_iter().collect(),
+            url: self.ipc.get("path").ok().and_then(|p| p.as_str().map(str::to_owned)),
+            art_url: None,
+            length: self.seconds("duration"),
+            // tags may count the tracks as in `7/23`
+            track_number: tag("track").and_then(|t| t.split('/').next()?.trim().parse().ok()),
+            live: false,
+        })
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        Ok(self.seconds("time-pos"))
+    }
+
+    fn rate(&self) -> Result<f64, PlayingError> {
+        Ok(self.ipc.get("speed")?.as_f64().unwrap_or(1.0))
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["set_property", "pause", false]))
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["set_property", "pause", true]))
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["playlist-next"]))
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["playlist-prev"]))
+    }
+
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["seek", offset_us as f64 / 1e6, "relative+exact"]))
+    }
+
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["seek", position.as_secs_f64(), "absolute+exact"]))
+    }
+
+    fn volume(&self) -> Result<f64, PlayingError> {
+        Ok(self.ipc.get("volume")?.as_f64().unwrap_or(100.0) / 100.0)
+    }
+
+    fn set_volume(&self, volume: f64) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["set_property", "volume", volume.max(0.0) * 100.0]))
+    }
+
+    fn open(&self, uri: &str) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["loadfile", uri, "replace"]))?;
+        self.play()
+    }
+
+    fn loop_mode(&self) -> Result<LoopMode, PlayingError> {
+        // `no` (or false), `inf`, a count, or `force` for the playlist
+        let looping = |property| self.ipc.get(property).map(|v| v != false && v != "no");
+        Ok(if looping("loop-file")? {
+            LoopMode::Track
+        } else if looping("loop-playlist")? {
+            LoopMode::Playlist
+        } else {
+            LoopMode::None
+        })
+    }
+
+    fn set_loop_mode(&self, mode: LoopMode) -> Result<(), PlayingError> {
+        let (file, playlist) = match mode {
+            LoopMode::None => ("no", "no"),
+            LoopMode::Track => ("inf", "no"),
+            LoopMode::Playlist => ("no", "inf"),
+        };
+        self.ipc.run(json!(["set_property", "loop-file", file]))?;
+        self.ipc.run(json!(["set_property", "loop-playlist", playlist]))
+    }
+
+    fn chapter(&self, offset: i64) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["add", "chapter", offset]))
+    }
+
+    fn chapters(&self) -> Result<Vec<Chapter>, PlayingError> {
+        let list = self.ipc.get("chapter-list")?;
+        Ok(list.as_array().into_iter().flatten().map(|c| Chapter {
+            title: c["title"].as_str().map(str::to_owned),
+            start: Duration::from_secs_f64(c["time"].as_f64().unwrap_or(0.0).max(0.0)),
+        }).collect())
+    }
+
+    fn frame_step(&self, back: bool) -> Result<(), PlayingError> {
+        self.ipc.run(json!([if back { "frame-back-step" } else { "frame-step" }]))
+    }
+
+    fn playlist(&self) -> Result<Option<(usize, usize)>, PlayingError> {
+        let position = self.ipc.get("playlist-pos-1")?.as_u64().filter(|p| *p > 0);
+        let count = self.ipc.get("playlist-count")?.as_u64();
+        Ok(position.zip(count).map(|(p, c)| (p as usize, c as usize)))
+    }
+
+    fn playlist_jump(&self, position: usize) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["set_property", "playlist-pos", position.saturating_sub(1)]))
+    }
+
+    fn ab_loop(&self) -> Result<(), PlayingError> {
+        self.ipc.run(json!(["ab-loop"]))
+    }
+}
@@ -0,0 +1,235 @@
+use std::{cell::RefCell, fs, path::Path, rc::Rc, time::Duration};
+use serde::{Deserialize, Serialize};
+
+use super::{Backend, MediaPlayer, Status, Track};
+use crate::PlayingError;
+
+/// A player as recorded by `playing record`, one per player in every line of a fixture.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Recorded {
+    pub identity: String,
+    pub status: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub album: Option<String>,
+    /// The album artists, named before the artists of the track were recorded too.
+    #[serde(default)]
+    pub artists: Vec<String>,
+    #[serde(default)]
+    pub track_artists: Vec<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub art_url: Option<String>,
+    /// In seconds, as is the position.
+    #[serde(default)]
+    pub length: Option<f64>,
+    #[serde(default)]
+    pub track_number: Option<u32>,
+    #[serde(default)]
+    pub live: bool,
+    #[serde(default)]
+    pub position: Option<f64>,
+    #[serde(default = "default_rate")]
+    pub rate: f64,
+}
+
+/// The players at some time of a recording, a line of a fixture.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Frame {
+    /// Seconds since the recording started.
+    pub t: f64,
+    pub players: Vec<Recorded>,
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+
+fn seconds(s: f64) -> Duration {
+    Duration::try_from_secs_f64(s).unwrap_or_default()
+}
+
+impl Recorded {
+    pub fn take(p: &dyn MediaPlayer) -> Result<Recorded, PlayingError> {
+        let track = p.track()?;
+        Ok(Recorded {
+            identity: p.identity().to_owned(),
+            status: p.status()?.name().to_owned(),
+            title: track.title,
+            album: track.album,
+            artists: track.album_artists,
+            track_artists: track.artists,
+            url: track.url,
+            art_url: track.art_url,
+            length: track.length.map(|l| l.as_secs_f64()),
+            track_number: track.track_number,
+            live: track.live,
+            position: p.position()?.map(|p| p.as_secs_f64()),
+            rate: p.rate()?,
+        })
+    }
+}
+
+fn replayed<T>() -> Result<T, PlayingError> {
+    Err(PlayingError::unsupported("replayed players can't be controlled"))
+}
+
+impl MediaPlayer for Recorded {
+    fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        match self.status.as_str() {
+            "playing" => Ok(Status::Playing),
+            "paused" => Ok(Status::Paused),
+            "stopped" => Ok(Status::Stopped),
+            s => Err(PlayingError::config(format!("unknown status `{}` in the fixture", s))),
+        }
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        Ok(Track {
+            title: self.title.clone(),
+            album: self.album.clone(),
+            album_artists: self.artists.clone(),
+            artists: self.track_artists.clone(),
+            url: self.url.clone(),
+            art_url: self.art_url.clone(),
+            length: self.length.map(seconds),
+            track_number: self.track_number,
+            live: self.live,
+        })
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        Ok(self.position.map(seconds))
+    }
+
+    fn rate(&self) -> Result<f64, PlayingError> {
+        Ok(self.rate)
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn seek(&self, _offset_us: i64) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn set_position(&self, _position: Duration) -> Result<(), PlayingError> {
+        replayed()
+    }
+}
+
+/// A player of a [`ReplayBackend`], which can seek within the frame shown, the one control a
+/// recording can answer without making things up.
+struct Replayed {
+    recorded: Recorded,
+    frame: Rc<RefCell<Vec<Recorded>>>,
+    index: usize,
+}
+
+impl Replayed {
+    fn move_to(&self, position: f64) -> Result<(), PlayingError> {
+        let length = self.recorded.length.unwrap_or(f64::MAX);
+        if let Some(p) = self.frame.borrow_mut().get_mut(self.index) {
+            p.position = Some(position.clamp(0.0, length));
+        }
+        Ok(())
+    }
+}
+
+impl MediaPlayer for Replayed {
+    fn identity(&self) -> &str {
+        self.recorded.identity()
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        self.recorded.status()
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        self.recorded.track()
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        self.recorded.position()
+    }
+
+    fn rate(&self) -> Result<f64, PlayingError> {
+        self.recorded.rate()
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        replayed()
+    }
+
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError> {
+        self.move_to(self.recorded.position.unwrap_or_default() + offset_us as f64 / 1e6)
+    }
+
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError> {
+        self.move_to(position.as_secs_f64())
+    }
+}
+
+/// Players read from a fixture instead of a bus, showing whichever frame was set last.
+#[derive(Clone, Default)]
+pub struct ReplayBackend {
+    frame: Rc<RefCell<Vec<Recorded>>>,
+}
+
+impl ReplayBackend {
+    /// Shows the players of the last line of the fixture at `path`.
+    pub fn load(path: &Path) -> Result<ReplayBackend, PlayingError> {
+        let content = fs::read_to_string(path)?;
+        let Some((n, line)) = content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()).last() else {
+            return Err(PlayingError::config(format!("{} has no players recorded", path.display())))
+        };
+        let frame: Frame = serde_json::from_str(line)
+            .map_err(|e| PlayingError::config(format!("{}:{}: {}", path.display(), n + 1, e)))?;
+        let backend = ReplayBackend::default();
+        backend.set(frame.players);
+        Ok(backend)
+    }
+
+    pub fn set(&self, players: Vec<Recorded>) {
+        *self.frame.borrow_mut() = players;
+    }
+}
+
+impl Backend for ReplayBackend {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+        Ok(self.frame.borrow().iter().cloned().enumerate().map(|(index, recorded)| {
+            Box::new(Replayed { recorded, frame: self.frame.clone(), index }) as Box<dyn MediaPlayer>
+        }).collect())
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use windows::Media::Control::{
+    GlobalSystemMediaTransportControlsSession as Session,
+    GlobalSystemMediaTransportControlsSessionManager as SessionManager,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus as PlaybackStatus,
+};
+
+use super::{Backend, Capabilities, MediaPlayer, Status, Track};
+use crate::PlayingError;
+
+impl From<windows::core::Error> for PlayingError {
+    fn from(value: windows::core::Error) -> Self {
+        PlayingError::smtc(value)
+    }
+}
+
+/// Windows' media sessions (GlobalSystemMediaTransportControls).
+pub struct SmtcBackend {
+    manager: SessionManager,
+}
+
+impl SmtcBackend {
+    pub fn new() -> Result<Self, PlayingError> {
+        Ok(SmtcBackend { manager: SessionManager::RequestAsync()?.get()? })
+    }
+}
+
+impl Backend for SmtcBackend {
+    fn players(&self) -> Result<Vec<Box<dyn MediaPlayer>>, PlayingError> {
+        let mut players: Vec<Box<dyn MediaPlayer>> = vec![];
+        for session in self.manager.GetSessions()? {
+            let identity = identity(&session.SourceAppUserModelId()?.to_string_lossy());
+            players.push(Box::new(SmtcPlayer { session, identity }));
+        }
+        Ok(players)
+    }
+}
+
+/// Maps app user model ids (`Spotify.exe`, `Chrome`, ...) to the MPRIS identities of the ranking.
+fn identity(app_id: &str) -> String {
+    let lower = app_id.to_lowercase();
+    match () {
+        _ if lower.starts_with("spotify") => "Spotify",
+        _ if lower.contains("firefox") => "Mozilla firefox",
+        _ if lower.contains("chrome") => "chrome",
+        _ if lower.contains("vlc") => "vlc",
+        _ if lower.contains("mpv") => "mpv",
+        _ => app_id,
+    }.to_owned()
+}
+
+/// Time between Windows' epoch (1601) and the unix one.
+const WINDOWS_EPOCH_OFFSET: Duration = Duration::from_secs(11_644_473_600);
+
+/// A 100ns-tick `TimeSpan` as a duration.
+fn ticks(t: windows::Foundation::TimeSpan) -> Duration {
+    Duration::from_nanos(t.Duration.max(0) as u64 * 100)
+}
+
+struct SmtcPlayer {
+    session: Session,
+    identity: String,
+}
+
+impl SmtcPlayer {
+    fn check(&self, done: bool, what: &str) -> Result<(), PlayingError> {
+        if done {
+            Ok(())
+        } else {
+            Err(PlayingError::unsupported(format!("{} refused to {}", self.identity, what)))
+        }
+    }
+}
+
+impl MediaPlayer for SmtcPlayer {
+    fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    fn capabilities(&self) -> Result<Capabilities, PlayingError> {
+        let controls = self.session.GetPlaybackInfo()?.Controls()?;
+        Ok(Capabilities {
+            control: true,
+            play: controls.IsPlayEnabled()?,
+            pause: controls.IsPauseEnabled()?,
+            next: controls.IsNextEnabled()?,
+            previous: controls.IsPreviousEnabled()?,
+            seek: controls.IsPlaybackPositionEnabled()?,
+        })
+    }
+
+    fn status(&self) -> Result<Status, PlayingError> {
+        Ok(match self.session.GetPlaybackInfo()?.PlaybackStatus()? {
+            PlaybackStatus::Playing => Status::Playing,
+            PlaybackStatus::Paused => Status::Paused,
+            _ => Status::Stopped,
+        })
+    }
+
+    fn track(&self) -> Result<Track, PlayingError> {
+        let props = self.session.TryGetMediaPropertiesAsync()?.get()?;
+        let text = |s: windows::core::Result<windows::core::HSTRING>| s.ok().map(|s| s.to_string_lossy()).filter(|s| !s.is_empty());
+        let timeline = self.session.GetTimelineProperties()?;
+        Ok(Track {
+            title: text(props.Title()),
+            album: text(props.AlbumTitle()),
+            album_artists: text(props.AlbumArtist()).into_iter().collect(),
+            artists: text(props.Artist()).into_iter().collect(),
+            url: None,
+            art_url: None,
+            length: Some(ticks(timeline.EndTime()?)).filter(|l| !l.is_zero()),
+            track_number: props.TrackNumber().ok().and_then(|n| u32::try_from(n).ok()).filter(|n| *n > 0),
+            live: false,
+        })
+    }
+
+    fn position(&self) -> Result<Option<Duration>, PlayingError> {
+        let timeline = self.session.GetTimelineProperties()?;
+        let position = ticks(timeline.Position()?);
+        if self.status()? != Status::Playing {
+            return Ok(Some(position))
+        }
+        // the position is a snapshot taken at LastUpdatedTime, advancing at the playback rate
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default() + WINDOWS_EPOCH_OFFSET;
+        let updated = Duration::from_nanos(timeline.LastUpdatedTime()?.UniversalTime.max(0) as u64 * 100);
+        Ok(Some(position + now.saturating_sub(updated).mul_f64(self.rate()?.max(0.0))))
+    }
+
+    fn rate(&self) -> Result<f64, PlayingError> {
+        Ok(self.session.GetPlaybackInfo()?.PlaybackRate().and_then(|r| r.Value()).unwrap_or(1.0))
+    }
+
+    fn play(&self) -> Result<(), PlayingError> {
+        self.check(self.session.TryPlayAsync()?.get()?, "play")
+    }
+
+    fn pause(&self) -> Result<(), PlayingError> {
+        self.check(self.session.TryPauseAsync()?.get()?, "pause")
+    }
+
+    fn next(&self) -> Result<(), PlayingError> {
+        self.check(self.session.TrySkipNextAsync()?.get()?, "skip to the next track")
+    }
+
+    fn previous(&self) -> Result<(), PlayingError> {
+        self.check(self.session.TrySkipPreviousAsync()?.get()?, "skip to the previous track")
+    }
+
+    fn seek(&self, offset_us: i64) -> Result<(), PlayingError> {
+        let position = self.session.GetTimelineProperties()?.Position()?.Duration;
+        let target = (position + offset_us * 10).max(0);
+        self.check(self.session.TryChangePlaybackPositionAsync(target)?.get()?, "seek")
+    }
+
+    fn set_position(&self, position: Duration) -> Result<(), PlayingError> {
+        let target = (position.as_nanos() / 100) as i64;
+        self.check(self.session.TryChangePlaybackPositionAsync(target)?.get()?, "seek")
+    }
+}
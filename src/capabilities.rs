@@ -0,0 +1,95 @@
+use std::process::Command;
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::{backend::{self, BackendKind, MpdBackend}, config::Config, status::{Compat, Output}};
+
+/// Whether this build has the backend `kind`, and whether it can connect right now.
+fn backend(config: &Config, kind: BackendKind) -> Value {
+    let compiled = match kind {
+        BackendKind::Mpris => cfg!(all(unix, not(target_os = "macos"))),
+        BackendKind::Smtc => cfg!(windows),
+        BackendKind::Macos => cfg!(target_os = "macos"),
+        BackendKind::Mpd | BackendKind::Auto => true,
+    };
+    let available = compiled && match kind {
+        BackendKind::Mpd => MpdBackend::connect(&config.mpd).is_ok(),
+        _ => backend::open(kind, config).is_ok(),
+    };
+    json!({ "compiled": compiled, "available": available })
+}
+
+/// Whether the mpv socket of the config answers, false without one.
+fn mpv(config: &Config) -> Value {
+    #[cfg(unix)]
+    let available = config.mpv.socket.is_some() && backend::MpvIpc::connect(&config.mpv).is_some();
+    #[cfg(not(unix))]
+    let available = { let _ = config; false };
+    json!({ "compiled": cfg!(unix), "available": available })
+}
+
+/// Whether `pactl` can reach the sound server, which `pause-on-unplug` watches through.
+fn pactl() -> bool {
+    Command::new("pactl").arg("info").output().is_ok_and(|o| o.status.success())
+}
+
+fn names<T: ValueEnum>() -> Vec<String> {
+    T::value_variants().iter().filter_map(|v| Some(v.to_possible_value()?.get_name().to_owned())).collect()
+}
+
+/// What this build supports and what it can reach on this machine, for wrapper scripts.
+fn describe(config: &Config) -> Value {
+    let linux_desktop = cfg!(all(unix, not(target_os = "macos")));
+    let backends: Map<String, Value> = BackendKind::value_variants().iter()
+        .filter(|k| **k != BackendKind::Auto)
+        .filter_map(|k| Some((k.to_possible_value()?.get_name().to_owned(), backend(config, *k))))
+        .chain([("mpv-ipc".to_owned(), mpv(config))])
+        .collect();
+    let mut storages = vec!["sqlite", "jsonl"];
+    if cfg!(feature = "postgres") {
+        storages.push("postgres");
+    }
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "features": { "postgres": cfg!(feature = "postgres") },
+        "backends": backends,
+        "subsystems": {
+            "dbus-service": linux_desktop,
+            "pause-on-lock": linux_desktop,
+            "notifications": linux_desktop,
+            "pause-on-unplug": pactl(),
+            "serve": true,
+            "mqtt": true,
+            "scrobble": true,
+            "history": true,
+            "spotify": true,
+        },
+        "history_storages": storages,
+        "outputs": names::<Output>(),
+        "compat": names::<Compat>(),
+    })
+}
+
+pub fn run(config: &Config, as_json: bool) -> bool {
+    let capabilities = describe(config);
+    if as_json {
+        println!("{}", capabilities);
+        return true
+    }
+    println!("playing-rs {} on {}", capabilities["version"].as_str().unwrap_or_default(), capabilities["os"].as_str().unwrap_or_default());
+    let yes_no = |v: &Value| if v.as_bool().unwrap_or_default() { "yes" } else { "no" };
+    println!("backends:");
+    for (name, b) in capabilities["backends"].as_object().into_iter().flatten() {
+        println!("  {:10} compiled: {:3}  available: {}", name, yes_no(&b["compiled"]), yes_no(&b["available"]));
+    }
+    println!("subsystems:");
+    for (name, s) in capabilities["subsystems"].as_object().into_iter().flatten() {
+        println!("  {:16} {}", name, yes_no(s));
+    }
+    let list = |v: &Value| v.as_array().into_iter().flatten().filter_map(Value::as_str).collect::<Vec<_>>().join(", ");
+    println!("history storages: {}", list(&capabilities["history_storages"]));
+    println!("outputs: {}", list(&capabilities["outputs"]));
+    println!("compat: {}", list(&capabilities["compat"]));
+    true
+}
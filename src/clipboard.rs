@@ -0,0 +1,49 @@
+use std::{io::{ErrorKind, Write}, process::{Command, Stdio}};
+use clap::ValueEnum;
+
+use crate::PlayingError;
+
+/// What `copy` puts on the clipboard.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Field {
+    Url,
+    Title,
+    /// The status line, without the icon
+    Formatted,
+}
+
+/// The commands that take the clipboard's contents on stdin, the first one installed wins.
+fn tools() -> Vec<&'static [&'static str]> {
+    if cfg!(windows) {
+        vec![&["clip"]]
+    } else if cfg!(target_os = "macos") {
+        vec![&["pbcopy"]]
+    } else {
+        let mut tools: Vec<&'static [&'static str]> = vec![&["xclip", "-selection", "clipboard"], &["xsel", "--clipboard", "--input"]];
+        // xclip and xsel would go to Xwayland's clipboard, which not every app reads
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.insert(0, &["wl-copy"]);
+        }
+        tools
+    }
+}
+
+/// Puts `text` on the clipboard of the desktop.
+pub fn copy(text: &str) -> Result<(), PlayingError> {
+    for tool in tools() {
+        let mut child = match Command::new(tool[0]).args(&tool[1..]).stdin(Stdio::piped()).stdout(Stdio::null()).spawn() {
+            Ok(c) => c,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(PlayingError::unsupported(format!("{} failed with {}", tool[0], status)))
+        }
+        return Ok(())
+    }
+    Err(PlayingError::unsupported("no clipboard tool found, install wl-clipboard, xclip or xsel"))
+}
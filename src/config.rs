@@ -0,0 +1,203 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, time::Duration};
+use serde::Deserialize;
+
+use crate::{access::AccessRules, analytics::AnalyticsConfig, download::DownloadConfig, favorites::{FavoriteRule, SpotifyConfig, SubsonicConfig}, format::{self, DurationSpec}, recent, selection::{SelectionConfig, SelectionPolicy}, Guards, Mode, backend::{MpdConfig, MpvConfig}, history::HistoryConfig, hooks::HooksConfig, input::InputRule, lock::LockConfig, sink::SinkConfig, mqtt::MqttConfig, preset::{PresetRule, SeekTarget}, scrobble::{LastfmConfig, ListenBrainzConfig}, skip::SkipRule, sponsorblock::SponsorBlockConfig, status::StatusConfig, PlayingError};
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the config was loaded from, if from a file.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// Player picked with `--player`, replacing the ranking; `last-active` picks the player that
+    /// last changed playback state.
+    #[serde(skip)]
+    pub player: Option<String>,
+    /// Conditions of the `--if-*` flags, which players have to meet for operations to act on them.
+    #[serde(skip)]
+    pub guards: Guards,
+    /// Group picked with `--group`, which the ranking was narrowed down to.
+    #[serde(skip)]
+    pub group: Option<String>,
+    /// Fixture given with `--fixture`, whose last line replaces the players of the backends.
+    #[serde(skip)]
+    pub fixture: Option<PathBuf>,
+    /// Player identities in order of preference, replacing the built-in ranking.
+    pub ranking: Option<Vec<String>>,
+    /// Whether operations apply to the first player of the ranking or to all of them.
+    pub mode: Mode,
+    /// Whether single operations go to the first player of the ranking even when another one
+    /// is playing, as `--strict-ranking`; the `static-ranking` policy of `[selection]`.
+    pub strict_ranking: bool,
+    /// How the player is picked among those of the ranking, for all commands or some.
+    pub selection: SelectionConfig,
+    /// Named sets of players for `--group`, e.g. `video = ["mpv", "vlc"]`.
+    pub groups: HashMap<String, Vec<String>>,
+    pub access: AccessRules,
+    pub mpd: MpdConfig,
+    pub mpv: MpvConfig,
+    pub status: StatusConfig,
+    /// Last.fm credentials, needed to scrobble.
+    pub lastfm: Option<LastfmConfig>,
+    /// ListenBrainz token, needed to submit listens there.
+    pub listenbrainz: Option<ListenBrainzConfig>,
+    /// Where `favorite` keeps the favorites of some players or URLs.
+    pub favorites: Vec<FavoriteRule>,
+    /// Server whose starred songs are the favorites of the `subsonic` rules.
+    pub subsonic: Option<SubsonicConfig>,
+    /// How often the Spotify favorites ask the Web API.
+    pub spotify: SpotifyConfig,
+    /// Broker to publish to, needed by the mqtt publisher.
+    pub mqtt: Option<MqttConfig>,
+    /// Named seeks for `op seek-preset`.
+    pub seek_presets: HashMap<String, SeekTarget>,
+    /// Presets of some players or URLs, overriding `seek_presets`.
+    pub seek_preset_rules: Vec<PresetRule>,
+    /// Intros, outros and chapters skipped by the `skip` daemon.
+    pub skip: Vec<SkipRule>,
+    /// Categories of YouTube segments skipped by the `skip` daemon.
+    pub sponsorblock: Option<SponsorBlockConfig>,
+    /// Commands run on playback events by `status --follow`.
+    pub hooks: HooksConfig,
+    /// Where `history record` keeps the tracks played.
+    pub history: HistoryConfig,
+    /// Whether operations are logged for `tune`.
+    pub analytics: AnalyticsConfig,
+    /// How `op previous` behaves.
+    pub previous: PreviousConfig,
+    /// Gamepad and MIDI controls mapped to operations by the `input` daemon.
+    pub input: Vec<InputRule>,
+    /// Players paused while the session is locked by `pause-on-lock`.
+    pub lock: LockConfig,
+    /// Players paused by `pause-on-unplug` when the audio output changes.
+    pub sink: SinkConfig,
+    /// Where `download` saves what plays, and how.
+    pub download: DownloadConfig,
+    /// Browser `url --open` opens the URLs with, e.g. `firefox`, instead of the desktop's default.
+    pub browser: Option<String>,
+    /// Aliases and display names of players, by identity.
+    pub players: HashMap<String, PlayerConfig>,
+}
+
+/// A `[players."<identity>"]` table of the config file, e.g.
+///
+/// ```toml
+/// [players."Mozilla firefox"]
+/// aliases = ["ff"]
+/// name = "Firefox"
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerConfig {
+    /// Other names `--player` takes for the player.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// What `{name}` shows instead of the identity.
+    pub name: Option<String>,
+}
+
+/// The `[previous]` table of the config file.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreviousConfig {
+    /// Always behave as `op previous --smart`.
+    pub smart: bool,
+    /// Position past which a smart previous restarts the track.
+    pub restart_after: DurationSpec,
+}
+
+impl Default for PreviousConfig {
+    fn default() -> Self {
+        PreviousConfig { smart: false, restart_after: DurationSpec(Duration::from_secs(5)) }
+    }
+}
+
+impl Config {
+    /// Default location of the config file: `$XDG_CONFIG_HOME/playing/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("org", "prabo", "playing")
+            .map(|d| d.config_dir().join("config.toml"))
+    }
+
+    pub fn ranking(&self) -> Vec<String> {
+        match self.player.as_deref() {
+            // no player was active yet, so the ranking decides
+            Some(recent::LAST_ACTIVE) => if let Some(player) = recent::last_active() {
+                return vec![player]
+            },
+            Some(player) => return vec![player.to_owned()],
+            None => {}
+        }
+        match self.ranking {
+            Some(ref r) => r.clone(),
+            None => crate::DEFAULT_RANKING.iter().map(|p| p.to_str().to_owned()).collect(),
+        }
+    }
+
+    /// The policy picking the player of the command named `action`, as in `[access]`.
+    pub fn selection_policy(&self, action: &str) -> SelectionPolicy {
+        self.selection.forced
+            .or_else(|| self.selection.actions.get(action).copied())
+            .or(self.selection.policy)
+            .unwrap_or(match self.strict_ranking {
+                true => SelectionPolicy::StaticRanking,
+                false => SelectionPolicy::PlayingFirst,
+            })
+    }
+
+    /// Narrows the ranking down to the players of `group`, in the order of the ranking, then of
+    /// the group for those the ranking misses.
+    pub fn restrict_to_group(&mut self, group: &str) -> Result<(), PlayingError> {
+        let members = self.groups.get(group)
+            .ok_or_else(|| PlayingError::config(format!("no group named `{}`", group)))?;
+        let ranking = self.ranking();
+        let mut restricted: Vec<String> = ranking.iter()
+            .filter(|id| members.iter().any(|m| m.eq_ignore_ascii_case(id)))
+            .cloned()
+            .collect();
+        restricted.extend(members.iter().filter(|m| !ranking.iter().any(|id| id.eq_ignore_ascii_case(m))).cloned());
+        self.ranking = Some(restricted);
+        self.group = Some(group.to_owned());
+        Ok(())
+    }
+
+    /// The identity `name` is an alias of, or `name` itself.
+    pub fn resolve_player(&self, name: &str) -> String {
+        self.players.iter()
+            .find(|(_, p)| p.aliases.iter().any(|a| a == name))
+            .map_or(name, |(identity, _)| identity)
+            .to_owned()
+    }
+
+    /// Every player identity the config names, in the ranking or in a group.
+    pub fn known_players(&self) -> Vec<String> {
+        let mut known = match self.ranking {
+            Some(ref r) => r.clone(),
+            None => crate::DEFAULT_RANKING.iter().map(|p| p.to_str().to_owned()).collect(),
+        };
+        known.extend(self.groups.values().flatten().cloned());
+        known
+    }
+
+    /// Loads the config from `path` or the default location.
+    ///
+    /// A missing file at the default location yields the default config, while an explicitly
+    /// requested file has to exist.
+    pub fn load(path: Option<&Path>) -> Result<Config, PlayingError> {
+        let path = match path {
+            Some(p) => p.to_owned(),
+            None => match Config::default_path() {
+                Some(p) if p.exists() => p,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| PlayingError::config(format!("{}: {}", path.display(), e)))?;
+        let config: Config = toml::from_str(&content)
+            .map_err(|e| PlayingError::config(format!("{}: {}", path.display(), e)))?;
+        for selector in config.ranking.iter().flatten().chain(config.groups.values().flatten()) {
+            format::check_selector(selector).map_err(|e| PlayingError::config(format!("{}: {}", path.display(), e)))?;
+        }
+        Ok(Config { path: Some(path), ..config })
+    }
+}
@@ -0,0 +1,191 @@
+use std::{path::{Path, PathBuf}, process::Command};
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::backend::{MprisBackend, MpvIpc};
+use crate::{backend::{Backend, MediaPlayer, MpdBackend}, config::Config, format, learned, Player};
+
+enum Level {
+    Ok,
+    Warn,
+    Error,
+}
+
+#[derive(Default)]
+struct Report {
+    errors: usize,
+}
+
+impl Report {
+    fn finding(&mut self, level: Level, what: impl AsRef<str>, hint: Option<&str>) {
+        let tag = match level {
+            Level::Ok => "ok",
+            Level::Warn => "warn",
+            Level::Error => {
+                self.errors += 1;
+                "error"
+            }
+        };
+        println!("[{}] {}", tag, what.as_ref());
+        if let Some(hint) = hint {
+            println!("       -> {}", hint);
+        }
+    }
+}
+
+/// Checks the environment the other commands rely on, returning whether everything is usable.
+pub fn run(config_path: Option<&Path>, mpv_socket: Option<PathBuf>) -> bool {
+    let mut report = Report::default();
+
+    let mut config = match Config::load(config_path) {
+        Ok(c) => {
+            match config_path.map(Path::to_owned).or_else(Config::default_path) {
+                Some(p) if p.exists() => report.finding(Level::Ok, format!("config {} is valid", p.display()), None),
+                _ => report.finding(Level::Ok, "no config file, using defaults", None),
+            }
+            c
+        }
+        Err(e) => {
+            report.finding(Level::Error, format!("config is invalid: {}", e.inner), Some("fix the file or move it away to use the defaults"));
+            Config::default()
+        }
+    };
+    if let Some(socket) = mpv_socket {
+        config.mpv.socket = Some(socket);
+    }
+
+    check_native(&mut report, &config);
+    check_learned(&mut report, &config);
+
+    match MpdBackend::connect(&config.mpd) {
+        Ok(_) => report.finding(Level::Ok, "mpd is reachable", None),
+        Err(e) => report.finding(Level::Warn, format!("mpd is not reachable: {}", e.inner), Some("ignore this if you don't use mpd, otherwise check [mpd] in the config or $MPD_HOST")),
+    }
+
+    check_spotify(&mut report);
+    check_glyphs(&mut report);
+
+    report.errors == 0
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn check_native(report: &mut Report, config: &Config) {
+    let mpv = match config.mpv.socket {
+        Some(ref socket) => {
+            let mpv = MpvIpc::connect(&config.mpv);
+            match mpv {
+                Some(_) => report.finding(Level::Ok, format!("mpv ipc socket {} is reachable", socket.display()), None),
+                None => report.finding(Level::Warn, format!("mpv ipc socket {} is not reachable", socket.display()), Some("start mpv with --input-ipc-server pointing at it")),
+            }
+            mpv
+        }
+        None => None,
+    };
+
+    match MprisBackend::new(mpv) {
+        Ok(mpris) => {
+            report.finding(Level::Ok, "session bus is reachable", None);
+            check_players(report, &mpris, &config.ranking());
+        }
+        Err(e) => report.finding(Level::Error, format!("session bus is not reachable: {}", e.inner), Some("make sure DBUS_SESSION_BUS_ADDRESS is set and a session bus is running")),
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn check_native(report: &mut Report, config: &Config) {
+    match crate::backend::native(config) {
+        Ok(native) => {
+            report.finding(Level::Ok, "the system's media sessions are reachable", None);
+            check_players(report, native.as_ref(), &config.ranking());
+        }
+        Err(e) => report.finding(Level::Error, format!("the system's media sessions are not reachable: {}", e.inner), None),
+    }
+}
+
+fn check_players(report: &mut Report, backend: &dyn Backend, ranking: &[String]) {
+    match backend.players() {
+        Ok(players) if players.is_empty() => report.finding(Level::Warn, "no players are running", None),
+        Ok(players) => players.iter().for_each(|p| check_player(report, p.as_ref(), ranking)),
+        Err(e) => report.finding(Level::Error, format!("cannot list players: {}", e.inner), None),
+    }
+}
+
+fn check_player(report: &mut Report, p: &dyn MediaPlayer, ranking: &[String]) {
+    if !ranking.iter().any(|r| format::selects(r, p.identity())) {
+        report.finding(Level::Warn, format!("player `{}` is not in the ranking", p.identity()), Some("it is ignored by every command"));
+        return
+    }
+    let caps = match p.capabilities() {
+        Ok(c) => c,
+        Err(e) => return report.finding(Level::Error, format!("player `{}` doesn't answer: {}", p.identity(), e.inner), None),
+    };
+    let missing: Vec<&str> = [
+        (caps.control, "control"),
+        (caps.play, "play"),
+        (caps.pause, "pause"),
+        (caps.next, "next"),
+        (caps.previous, "previous"),
+        (caps.seek, "seek"),
+    ].into_iter().filter(|(c, _)| !c).map(|(_, n)| n).collect();
+    if missing.is_empty() {
+        report.finding(Level::Ok, format!("player `{}` supports every operation", p.identity()), None);
+    } else {
+        report.finding(Level::Warn, format!("player `{}` can't {}", p.identity(), missing.join(", ")), Some("these operations will be ignored by the player"));
+    }
+}
+
+/// Reports the players seen running before that the config still doesn't name.
+fn check_learned(report: &mut Report, config: &Config) {
+    let known = config.known_players();
+    for (identity, l) in learned::load().iter().filter(|(id, _)| !known.contains(id)) {
+        let seen = match (&l.desktop_entry, &l.bus_name) {
+            (Some(entry), Some(bus)) => format!(" (desktop entry {}, bus name {})", entry, bus),
+            (Some(entry), None) => format!(" (desktop entry {})", entry),
+            (None, Some(bus)) => format!(" (bus name {})", bus),
+            (None, None) => String::new(),
+        };
+        let hint = match learned::near_miss(identity, l.desktop_entry.as_deref(), &known) {
+            Some(k) => format!("replace `{}` with `{}` in `ranking` in the config", k, identity),
+            None => format!("add `{}` to `ranking` in the config to control it", identity),
+        };
+        report.finding(Level::Warn, format!("player `{}` was seen running but is not in the ranking{}", identity, seen), Some(&hint));
+    }
+}
+
+fn check_spotify(report: &mut Report) {
+    let configured = std::env::var_os("RSPOTIFY_CLIENT_ID").is_some() || directories::ProjectDirs::from("org", "prabo", "spotifav")
+        .map(|d| d.config_dir().join("config.toml"))
+        .is_some_and(|p| p.metadata().is_ok_and(|m| m.len() > 0));
+    if !configured {
+        report.finding(Level::Warn, "spotify credentials are not configured", Some("run `playing favorite --always` once and fill in the file it creates"));
+        return
+    }
+    // rspotify caches the token relative to the working directory
+    let token = std::fs::read_to_string(".spotify_token_cache.json").ok()
+        .and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok());
+    match token {
+        Some(t) if t["refresh_token"].is_string() => report.finding(Level::Ok, "spotify token is cached and refreshable", None),
+        Some(_) => report.finding(Level::Warn, "spotify token can't be refreshed", Some("remove .spotify_token_cache.json and log in again")),
+        None => report.finding(Level::Warn, "no spotify token cached in the working directory", Some("run `playing favorite --always` to log in")),
+    }
+}
+
+fn check_glyphs(report: &mut Report) {
+    let icons: String = [Player::Mpv, Player::Vlc, Player::Firefox, Player::Spotify, Player::Chrome, Player::Mpd, Player::Music]
+        .iter()
+        .map(Player::icon)
+        .collect();
+    let mut missing = vec![];
+    for c in icons.chars() {
+        match Command::new("fc-list").arg(format!(":charset={:x}", c as u32)).output() {
+            Ok(out) if out.status.success() && out.stdout.is_empty() => missing.push(c),
+            Ok(_) => {}
+            Err(_) => return report.finding(Level::Warn, "cannot check icon glyphs, fc-list is not available", None),
+        }
+    }
+    if missing.is_empty() {
+        report.finding(Level::Ok, "an installed font covers every player icon", None);
+    } else {
+        let missing: Vec<String> = missing.iter().map(|c| format!("U+{:04X}", *c as u32)).collect();
+        report.finding(Level::Warn, format!("no installed font has the glyphs {}", missing.join(" ")), Some("install a Nerd Font or use `status --no-icon`"));
+    }
+}
@@ -0,0 +1,55 @@
+use std::{io::ErrorKind, path::{Path, PathBuf}, process::Command};
+use serde::Deserialize;
+
+use crate::{backend::MediaPlayer, PlayingError};
+
+/// The `[download]` table of the config file.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DownloadConfig {
+    /// Where the downloads go, the user's download directory by default.
+    pub dir: Option<PathBuf>,
+    /// Name of the downloaded files, as an output template of yt-dlp.
+    pub template: String,
+    /// The yt-dlp executable, e.g. a path outside of `$PATH`.
+    pub command: String,
+    /// Further arguments of yt-dlp, e.g. `["-x", "--audio-format", "mp3"]`.
+    pub args: Vec<String>,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        DownloadConfig {
+            dir: None,
+            template: "%(title)s [%(id)s].%(ext)s".to_owned(),
+            command: "yt-dlp".to_owned(),
+            args: vec![],
+        }
+    }
+}
+
+/// Downloads what `p` plays with yt-dlp into `dir`, as named by `template`, the ones of the
+/// config by default. yt-dlp prints its progress as it goes.
+pub fn run(config: &DownloadConfig, p: &dyn MediaPlayer, dir: Option<&Path>, template: Option<&str>) -> Result<bool, PlayingError> {
+    let Some(url) = p.track()?.url.filter(|u| u.starts_with("https://") || u.starts_with("http://")) else {
+        eprintln!("{} isn't playing anything from the web", p.identity());
+        return Ok(false)
+    };
+    let dir = dir.map(Path::to_owned)
+        .or_else(|| config.dir.clone())
+        .or_else(|| directories::UserDirs::new().and_then(|d| d.download_dir().map(Path::to_owned)))
+        .ok_or_else(|| PlayingError::config("no directory to download to, set `dir` in [download]"))?;
+    eprintln!("downloading {} to {}", url, dir.display());
+    let status = Command::new(&config.command)
+        .arg("--paths").arg(&dir)
+        .arg("--output").arg(template.unwrap_or(&config.template))
+        .args(&config.args)
+        .arg("--")
+        .arg(&url)
+        .status()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => PlayingError::unsupported(format!("{} is not installed", config.command)),
+            _ => e.into(),
+        })?;
+    Ok(status.success())
+}
@@ -0,0 +1,58 @@
+use std::{thread::sleep, time::{Duration, SystemTime, UNIX_EPOCH}};
+use serde_json::json;
+
+use crate::{backend::{self, Backend}, config::Config, follow::{self, Snapshot}, status::StatusOptions, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What changed for one player between two polls.
+fn event(last: Option<&Snapshot>, next: Option<&Snapshot>) -> Option<&'static str> {
+    match (last, next) {
+        (None, Some(_)) => Some("appeared"),
+        (Some(_), None) => Some("vanished"),
+        _ => follow::event(last, next),
+    }
+}
+
+fn print(event: &str, player: &str, snapshot: Option<&Snapshot>, opts: &StatusOptions) {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default();
+    let state = snapshot.map_or_else(Snapshot::stopped, |s| s.json(opts));
+    println!("{}", json!({ "event": event, "time": time, "player": player, "state": state }));
+}
+
+/// Prints a JSON object per line for every event of every player: `appeared`, `vanished`,
+/// `track`, `playing`, `paused`, `stopped` and `seek`, e.g. for `jq` or eww's `deflisten`.
+pub fn run(config: &Config, backends: &[Box<dyn Backend>]) -> Result<bool, PlayingError> {
+    let opts = StatusOptions::published(config)?;
+    let mut last: Vec<Snapshot> = vec![];
+    loop {
+        match backend::players(backends) {
+            Ok(players) => {
+                let mut next = vec![];
+                // players that failed to answer are kept around rather than announced as vanished
+                let mut silent = vec![];
+                for p in &players {
+                    match Snapshot::take(p.as_ref()) {
+                        Ok(s) => next.push(s),
+                        Err(_) => silent.push(p.identity()),
+                    }
+                }
+                for n in &next {
+                    if let Some(e) = event(last.iter().find(|l| l.player == n.player), Some(n)) {
+                        print(e, &n.player, Some(n), &opts);
+                    }
+                }
+                for l in last.iter().filter(|l| !next.iter().any(|n| n.player == l.player) && !silent.contains(&l.player.as_str())) {
+                    if let Some(e) = event(Some(l), None) {
+                        print(e, &l.player, None, &opts);
+                    }
+                }
+                next.extend(last.into_iter().filter(|l| silent.contains(&l.player.as_str())));
+                last = next;
+            }
+            Err(e) => eprintln!("cannot list the players: {}", e.inner),
+        }
+        crate::timing::tick();
+        sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashMap, path::PathBuf, time::{Duration, SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+
+use crate::format::DurationSpec;
+
+/// The `[spotify]` table of the config file, about the Web API calls of the favorites.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpotifyConfig {
+    /// How long whether a track is saved is trusted, e.g. by `{favorite}` and `favorite --check`.
+    pub likes_ttl: DurationSpec,
+    /// Least time between two calls asking whether a track is saved; meanwhile what was last
+    /// learned of it is used, however old.
+    pub min_interval: DurationSpec,
+}
+
+impl Default for SpotifyConfig {
+    fn default() -> Self {
+        SpotifyConfig { likes_ttl: DurationSpec(Duration::from_secs(600)), min_interval: DurationSpec(Duration::from_secs(5)) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Like {
+    saved: bool,
+    at: u64,
+}
+
+/// Whether the recently seen Spotify tracks are saved, by id, kept on disk for the bars that
+/// run `status` every few seconds.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Likes {
+    tracks: HashMap<String, Like>,
+    /// Unix time of the last call asking the API.
+    asked_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("spotify-likes.json")
+}
+
+impl Likes {
+    pub fn load() -> Likes {
+        path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default()
+    }
+
+    /// Saves the likes learned within `ttl`, forgetting the older ones; failing to only costs
+    /// calls later.
+    fn save(&mut self, ttl: Duration) {
+        let Some(path) = path() else { return };
+        let oldest = now().saturating_sub(ttl.as_secs());
+        self.tracks.retain(|_, l| l.at >= oldest);
+        if let Err(e) = crate::store::write_atomic(&path, serde_json::to_string(self).unwrap_or_default().as_bytes()) {
+            eprintln!("cannot keep the liked tracks in {}: {}", path.display(), e);
+        }
+    }
+
+    /// Whether the track `id` is saved, if learned within `ttl`.
+    pub fn fresh(&self, id: &str, ttl: Duration) -> Option<bool> {
+        self.tracks.get(id).filter(|l| l.at + ttl.as_secs() > now()).map(|l| l.saved)
+    }
+
+    /// Whether the track `id` is saved, however long ago it was learned.
+    pub fn last(&self, id: &str) -> Option<bool> {
+        self.tracks.get(id).map(|l| l.saved)
+    }
+
+    /// Whether the API was asked less than `interval` ago.
+    pub fn limited(&self, interval: Duration) -> bool {
+        self.asked_at + interval.as_secs() > now()
+    }
+
+    /// Notes that the API is being asked.
+    pub fn ask(&mut self, ttl: Duration) {
+        self.asked_at = now();
+        self.save(ttl);
+    }
+
+    /// Keeps whether the track `id` is saved, as told by the API or changed by a toggle.
+    pub fn learn(&mut self, id: &str, saved: bool, ttl: Duration) {
+        self.tracks.insert(id.to_owned(), Like { saved, at: now() });
+        self.save(ttl);
+    }
+}
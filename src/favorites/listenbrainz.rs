@@ -0,0 +1,96 @@
+use std::time::Duration;
+use serde_json::{json, Value};
+
+use super::{FavoriteProvider, Pending};
+use crate::{backend::Track, scrobble::ListenBrainzConfig, PlayingError};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The loved recordings of a ListenBrainz user, the one of the token of `[listenbrainz]`.
+pub struct ListenBrainz<'a> {
+    config: &'a ListenBrainzConfig,
+}
+
+fn lb_error(e: ureq::Error) -> PlayingError {
+    let message = match e {
+        ureq::Error::Status(code, r) => {
+            let body = r.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<Value>(&body).ok()
+                .and_then(|v| v["error"].as_str().map(str::to_owned))
+                .unwrap_or(body);
+            format!("{} (http {})", message, code)
+        }
+        e => e.to_string(),
+    };
+    PlayingError::favorite(format!("listenbrainz: {}", message))
+}
+
+impl<'a> ListenBrainz<'a> {
+    pub fn new(config: &'a ListenBrainzConfig) -> Self {
+        ListenBrainz { config }
+    }
+
+    fn get(&self, path: &str, params: &[(&str, &str)]) -> Result<Value, PlayingError> {
+        let mut request = ureq::get(&format!("{}{}", self.config.api_url(), path))
+            .timeout(TIMEOUT)
+            .set("Authorization", &format!("Token {}", self.config.token));
+        for (k, v) in params {
+            request = request.query(k, v);
+        }
+        Ok(request.call().map_err(lb_error)?.into_json()?)
+    }
+
+    fn user(&self) -> Result<String, PlayingError> {
+        let token = self.get("/1/validate-token", &[])?;
+        token["user_name"].as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| PlayingError::config("the token of [listenbrainz] is not valid"))
+    }
+
+    /// The MusicBrainz id of `track`, looked up by its title and artist.
+    fn recording(&self, track: &Track) -> Result<String, PlayingError> {
+        let (Some(title), Some(artist)) = (&track.title, track.artist()) else {
+            return Err(PlayingError::favorite("listenbrainz needs the title and artist of the track"))
+        };
+        let found = self.get("/1/metadata/lookup/", &[("artist_name", artist), ("recording_name", title)])?;
+        found["recording_mbid"].as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| PlayingError::favorite(format!("`{} - {}` is not on musicbrainz", artist, title)))
+    }
+
+    fn loved(&self, mbid: &str) -> Result<bool, PlayingError> {
+        let feedback = self.get(&format!("/1/feedback/user/{}/get-feedback-for-recordings", self.user()?), &[("recording_mbids", mbid)])?;
+        Ok(feedback["feedback"].as_array().into_iter().flatten().any(|f| f["score"] == 1))
+    }
+
+    fn feedback(&self, mbid: &str, loved: bool) -> Result<(), PlayingError> {
+        // a score of 0 takes the feedback back
+        ureq::post(&format!("{}/1/feedback/recording-feedback", self.config.api_url()))
+            .timeout(TIMEOUT)
+            .set("Authorization", &format!("Token {}", self.config.token))
+            .send_json(json!({ "recording_mbid": mbid, "score": if loved { 1 } else { 0 } }))
+            .map_err(lb_error)?;
+        Ok(())
+    }
+
+    fn love(&self, track: &Track) -> Result<bool, PlayingError> {
+        let mbid = self.recording(track)?;
+        let loved = self.loved(&mbid)?;
+        self.feedback(&mbid, !loved)?;
+        Ok(!loved)
+    }
+}
+
+impl FavoriteProvider for ListenBrainz<'_> {
+    fn contains<'a>(&'a self, _: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(std::future::ready(self.recording(track).and_then(|mbid| self.loved(&mbid))))
+    }
+
+    fn toggle<'a>(&'a self, _: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(std::future::ready(self.love(track)))
+    }
+
+    fn set<'a>(&'a self, _: &'a str, track: &'a Track, saved: bool) -> Pending<'a, ()> {
+        Box::pin(std::future::ready(self.recording(track).and_then(|mbid| self.feedback(&mbid, saved))))
+    }
+}
@@ -0,0 +1,114 @@
+use std::{path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+
+use super::{FavoriteProvider, Pending};
+use crate::{backend::Track, PlayingError};
+
+/// A track favorited on a player other than Spotify, which has no favorites of its own.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Favorite {
+    pub player: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub url: Option<String>,
+    pub length: Option<f64>,
+    pub added_at: u64,
+}
+
+impl Favorite {
+    /// Whether both are the same track: the same URL, or the same title and artist when either
+    /// has none, as for a radio stream.
+    fn same(&self, other: &Favorite) -> bool {
+        match (&self.url, &other.url) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.title.is_some() && self.title == other.title && self.artist == other.artist,
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("favorites.json")
+}
+
+/// The favorites, none before the first one; a file that can't be read fails rather than
+/// reading as none, which the next toggle would save over.
+pub fn load() -> Result<Vec<Favorite>, PlayingError> {
+    let Some(path) = path() else { return Ok(vec![]) };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_str(&contents)
+        .map_err(|e| PlayingError::favorite(format!("{}: {}, see `playing db check`", path.display(), e)))
+}
+
+fn save(favorites: &[Favorite]) -> Result<(), PlayingError> {
+    let Some(path) = path() else {
+        return Err(PlayingError::config("no data directory to keep the favorites in"))
+    };
+    crate::store::write_atomic(&path, serde_json::to_string(favorites).unwrap_or_default().as_bytes())?;
+    Ok(())
+}
+
+/// The track `player` plays as a favorite.
+fn of(player: &str, track: &Track) -> Result<Favorite, PlayingError> {
+    if track.title.is_none() && track.url.is_none() {
+        return Err(PlayingError::unsupported(format!("{} tells nothing about what it plays", player)))
+    }
+    Ok(Favorite {
+        player: player.to_owned(),
+        artist: track.artist().cloned(),
+        length: track.plausible_length().map(|l| l.as_secs_f64()),
+        title: track.title.clone(),
+        url: track.url.clone(),
+        added_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+    })
+}
+
+/// The favorites kept in the data dir, for the players that have none of their own.
+pub struct Local;
+
+impl Local {
+    fn contains(player: &str, track: &Track) -> Result<bool, PlayingError> {
+        let track = of(player, track)?;
+        Ok(load()?.iter().any(|f| f.same(&track)))
+    }
+
+    fn toggle(player: &str, track: &Track) -> Result<bool, PlayingError> {
+        let track = of(player, track)?;
+        let mut favorites = load()?;
+        let before = favorites.len();
+        favorites.retain(|f| !f.same(&track));
+        let added = favorites.len() == before;
+        if added {
+            favorites.push(track);
+        }
+        save(&favorites)?;
+        Ok(added)
+    }
+
+    fn set(player: &str, track: &Track, saved: bool) -> Result<(), PlayingError> {
+        let track = of(player, track)?;
+        let mut favorites = load()?;
+        favorites.retain(|f| !f.same(&track));
+        if saved {
+            favorites.push(track);
+        }
+        save(&favorites)
+    }
+}
+
+impl FavoriteProvider for Local {
+    fn contains<'a>(&'a self, player: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(std::future::ready(Local::contains(player, track)))
+    }
+
+    fn toggle<'a>(&'a self, player: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(std::future::ready(Local::toggle(player, track)))
+    }
+
+    fn set<'a>(&'a self, player: &'a str, track: &'a Track, saved: bool) -> Pending<'a, ()> {
+        Box::pin(std::future::ready(Local::set(player, track, saved)))
+    }
+}
@@ -0,0 +1,301 @@
+use std::{future::Future, path::PathBuf, pin::Pin, time::{Duration, SystemTime, UNIX_EPOCH}};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::{MediaPlayer, Track}, config::Config, format, notify::Notifier, PlayingError};
+
+mod likes;
+mod listenbrainz;
+mod local;
+mod queue;
+mod spotify;
+mod subsonic;
+mod undo;
+
+pub use likes::SpotifyConfig;
+pub use subsonic::SubsonicConfig;
+use listenbrainz::ListenBrainz;
+use local::{Favorite, Local};
+use spotify::Spotify;
+use subsonic::Subsonic;
+
+/// Icon of the notifications of `favorite --notify`, a heart in most icon themes.
+const HEART: &str = "emblem-favorite";
+/// How long `{favorite}` trusts what it learned of a track, for the status line not to ask the
+/// provider on every poll.
+const KNOWN_FOR: Duration = Duration::from_secs(60);
+
+/// What `{favorite}` last learned, kept on disk as the status line runs anew on every poll of
+/// most bars.
+#[derive(Serialize, Deserialize)]
+struct Known {
+    /// The player and the URL or title of its track.
+    key: String,
+    /// Whether it's a favorite, none when the provider couldn't tell.
+    favorite: Option<bool>,
+    /// Unix time it was learned.
+    at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn known_path() -> Option<PathBuf> {
+    crate::store::path("favorite-known.json")
+}
+
+fn known_key(player: &str, track: &Track) -> String {
+    format!("{}\n{}", player, track.url.clone().or_else(|| track.title.clone()).unwrap_or_default())
+}
+
+/// Whether the track `player` plays is a favorite, if learned less than `KNOWN_FOR` ago.
+fn known(player: &str, track: &Track) -> Option<Option<bool>> {
+    let known: Known = known_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())?;
+    (known.key == known_key(player, track) && known.at + KNOWN_FOR.as_secs() > now()).then_some(known.favorite)
+}
+
+/// Keeps whether the track `player` plays is a favorite, as asked or changed by a toggle; failing
+/// to only costs calls to the provider later.
+fn learn(player: &str, track: &Track, favorite: Option<bool>) {
+    let Some(path) = known_path() else { return };
+    let known = Known { key: known_key(player, track), favorite, at: now() };
+    if let Err(e) = crate::store::write_atomic(&path, serde_json::to_string(&known).unwrap_or_default().as_bytes()) {
+        eprintln!("cannot keep whether the track is a favorite in {}: {}", path.display(), e);
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FavoriteCommand {
+    /// Lists the favorites kept locally, oldest first
+    List,
+    /// Prints the local favorites that have a URL as a playlist, e.g. for mpv or VLC
+    Export {
+        #[arg(long, required = true)]
+        m3u: bool,
+    },
+}
+
+/// The answer of a provider, boxed as the Spotify API is async.
+pub type Pending<'a, T> = Pin<Box<dyn Future<Output = Result<T, PlayingError>> + 'a>>;
+
+/// Where the favorites of some players are kept, e.g. the saved tracks of a Spotify account.
+pub trait FavoriteProvider {
+    /// Whether the track `player` plays is a favorite.
+    fn contains<'a>(&'a self, player: &'a str, track: &'a Track) -> Pending<'a, bool>;
+
+    /// Adds the track `player` plays to the favorites, or removes it if it's one already,
+    /// returning whether it was added.
+    fn toggle<'a>(&'a self, player: &'a str, track: &'a Track) -> Pending<'a, bool>;
+
+    /// Adds `track`, which `player` played, to the favorites or removes it, as when undoing a
+    /// toggle.
+    fn set<'a>(&'a self, player: &'a str, track: &'a Track, saved: bool) -> Pending<'a, ()>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// The saved tracks of the account of spotifav.
+    Spotify,
+    /// A file in the data dir.
+    Local,
+    /// The starred songs of the `[subsonic]` server.
+    Subsonic,
+    /// The loved recordings of the `[listenbrainz]` user.
+    Listenbrainz,
+}
+
+/// A `[[favorites]]` rule of the config file, picking where the favorites of some players or
+/// URLs go; the first rule that applies wins, e.g.
+///
+/// ```toml
+/// [[favorites]]
+/// url = "https://music.example.com/*"
+/// provider = "subsonic"
+/// ```
+///
+/// Without one, Spotify's go to its account and the others' to the local favorites.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FavoriteRule {
+    /// Identity of the player, e.g. `mpv`.
+    player: Option<String>,
+    /// Pattern of the URL, where `*` matches anything.
+    url: Option<String>,
+    provider: ProviderKind,
+}
+
+impl FavoriteRule {
+    fn applies(&self, player: &str, url: Option<&str>) -> bool {
+        self.player.as_ref().is_none_or(|p| p == player)
+            && self.url.as_ref().is_none_or(|pattern| url.is_some_and(|u| format::glob(pattern, u)))
+    }
+}
+
+fn is_spotify(player: &str) -> bool {
+    player.eq_ignore_ascii_case("Spotify")
+}
+
+/// Where the favorites of the track `player` plays go.
+fn kind(config: &Config, player: &str, track: &Track) -> ProviderKind {
+    config.favorites.iter()
+        .find(|r| r.applies(player, track.url.as_deref()))
+        .map(|r| r.provider)
+        .unwrap_or(if is_spotify(player) { ProviderKind::Spotify } else { ProviderKind::Local })
+}
+
+fn provider<'a>(config: &'a Config, kind: ProviderKind, poll: bool) -> Result<Box<dyn FavoriteProvider + 'a>, PlayingError> {
+    Ok(match kind {
+        ProviderKind::Spotify => Box::new(Spotify { config: &config.spotify, poll }),
+        ProviderKind::Local => Box::new(Local),
+        ProviderKind::Subsonic => match config.subsonic {
+            Some(ref subsonic) => Box::new(Subsonic::new(subsonic)),
+            None => return Err(PlayingError::config("the subsonic favorites need a [subsonic] table in the config")),
+        },
+        ProviderKind::Listenbrainz => match config.listenbrainz {
+            Some(ref listenbrainz) => Box::new(ListenBrainz::new(listenbrainz)),
+            None => return Err(PlayingError::config("the listenbrainz favorites need a [listenbrainz] table in the config")),
+        },
+    })
+}
+
+/// What `favorite` did: whether it succeeded, what it printed and the track it was about.
+struct Outcome {
+    ok: bool,
+    message: &'static str,
+    track: Option<Track>,
+}
+
+async fn favorite_current(config: &Config, players: &[Box<dyn MediaPlayer>], always: bool, poll: bool, check: bool) -> Result<Outcome, PlayingError> {
+    let policy = config.selection_policy("favorite");
+    let ranking = crate::selection::ranking(config, policy, players)?;
+    let (player, track) = match crate::selected_player(players, &ranking, policy)? {
+        _ if always => ("Spotify", Track::default()),
+        Some(p) => (p.identity(), p.track()?),
+        None => {
+            eprintln!("nothing is playing");
+            return Ok(Outcome { ok: false, message: "nothing is playing", track: None })
+        }
+    };
+    let kind = if always { ProviderKind::Spotify } else { kind(config, player, &track) };
+    let provider = provider(config, kind, poll)?;
+    let (ok, message) = if check {
+        let saved = provider.contains(player, &track).await?;
+        (saved, if saved { "saved" } else { "not saved" })
+    } else {
+        let added = provider.toggle(player, &track).await?;
+        undo::record(kind, player, &track, added);
+        learn(player, &track, Some(added));
+        (true, if added { "added song to favorites" } else { "removed song from favorites" })
+    };
+    println!("{}", message);
+    Ok(Outcome { ok, message, track: Some(track) })
+}
+
+/// Puts the track of the last toggle back as it was, wherever it plays now.
+async fn undo_last(config: &Config) -> Result<Outcome, PlayingError> {
+    let Some(last) = undo::last() else {
+        eprintln!("there's no toggle to undo");
+        return Ok(Outcome { ok: false, message: "nothing to undo", track: None })
+    };
+    let track = last.track();
+    provider(config, last.provider, false)?.set(&last.player, &track, !last.added).await?;
+    undo::forget()?;
+    learn(&last.player, &track, Some(!last.added));
+    let message = if last.added { "removed song from favorites" } else { "added song back to favorites" };
+    println!("{}", message);
+    Ok(Outcome { ok: true, message, track: Some(track) })
+}
+
+/// Toggles the track of the current player in its favorites, or only tells whether it's one
+/// with `check`; `always` goes to Spotify even when it's not running, as on another device.
+/// With `undo` the last toggle is reversed instead, e.g. after a keybinding fired twice.
+///
+/// With `notify` the outcome, failures included, also shows as a desktop notification, for
+/// keybindings whose output nobody sees.
+pub async fn favorite(config: &Config, players: &[Box<dyn MediaPlayer>], always: bool, poll: bool, check: bool, undo: bool, notify: bool) -> Result<bool, PlayingError> {
+    let outcome = if undo { undo_last(config).await } else { favorite_current(config, players, always, poll, check).await };
+    if notify {
+        let (summary, body) = match outcome {
+            Ok(ref o) => {
+                let track = o.track.as_ref().and_then(|t| {
+                    let title = t.title.as_deref()?;
+                    Some(match t.artist() {
+                        Some(artist) => format!("{} — {}", artist, title),
+                        None => title.to_owned(),
+                    })
+                });
+                (o.message.to_owned(), track.unwrap_or_default())
+            }
+            Err(ref e) => ("cannot change the favorites".to_owned(), e.inner.to_string()),
+        };
+        if let Err(e) = Notifier::new().and_then(|mut n| n.send(&summary, &body, HEART, &[])) {
+            eprintln!("cannot send a notification: {}", e.inner);
+        }
+    }
+    outcome.map(|o| o.ok)
+}
+
+/// Makes the changes of the Spotify favorites queued while its API was unreachable, for daemons
+/// not to wait for the next `favorite`.
+pub async fn flush_queue() -> Result<(), PlayingError> {
+    if queue::load().is_empty() {
+        return Ok(())
+    }
+    let cli = spotifav::get_client().await.map_err(PlayingError::from_spotifav)?;
+    queue::flush(&cli).await
+}
+
+/// Waits for `future` of a provider from sync code, such as the status line.
+fn block_on<T>(future: Pending<'_, T>) -> Result<T, PlayingError> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => tokio::runtime::Builder::new_current_thread().enable_all().build()?.block_on(future),
+    }
+}
+
+/// Whether the track `player` plays is a favorite, for `{favorite}`; none when the provider
+/// can't tell. Asks again only once the track changes or after a minute.
+pub fn is_favorite(config: &Config, player: &str, track: &Track) -> Option<bool> {
+    if let Some(favorite) = known(player, track) {
+        return favorite
+    }
+    let favorite = provider(config, kind(config, player, track), false)
+        .and_then(|p| block_on(p.contains(player, track)))
+        .ok();
+    learn(player, track, favorite);
+    favorite
+}
+
+/// What's shown of a favorite: `artist - title`, or its URL without a title.
+fn name(f: &Favorite) -> String {
+    match (&f.title, &f.url) {
+        (Some(title), _) => format!("{} - {}", f.artist.as_deref().unwrap_or("Unknown"), title),
+        (None, url) => url.clone().unwrap_or_default(),
+    }
+}
+
+pub fn run(command: &FavoriteCommand, as_json: bool) -> Result<bool, PlayingError> {
+    let favorites = local::load()?;
+    match command {
+        FavoriteCommand::List if as_json => println!("{}", serde_json::to_string(&favorites).unwrap_or_default()),
+        FavoriteCommand::List => for f in &favorites {
+            let added = chrono::DateTime::from_timestamp(f.added_at as i64, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            println!("{}  {} ({})", added, name(f), f.player);
+        },
+        FavoriteCommand::Export { .. } => {
+            println!("#EXTM3U");
+            for f in &favorites {
+                let Some(ref url) = f.url else { continue };
+                println!("#EXTINF:{},{}", f.length.map_or(-1, |l| l.round() as i64), name(f));
+                println!("{}", url);
+            }
+        }
+    }
+    Ok(!favorites.is_empty())
+}
@@ -0,0 +1,103 @@
+use std::{path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+use rspotify::{model::TrackId, prelude::OAuthClient, AuthCodeSpotify, ClientError};
+use serde::{Deserialize, Serialize};
+
+use super::likes::Likes;
+use crate::{backend::Track, spotify::spotify_error, PlayingError, PlayingErrorKind};
+
+/// A change of the Spotify favorites made while the API was unreachable.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Queued {
+    /// Id of the track on Spotify.
+    pub track: String,
+    /// Whether the track is to end up saved or removed.
+    pub saved: bool,
+    pub queued_at: u64,
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("favorite-queue.json")
+}
+
+pub fn load() -> Vec<Queued> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save(queue: &[Queued]) -> Result<(), PlayingError> {
+    let Some(path) = path() else {
+        return Err(PlayingError::config("no data directory to queue the favorites in"))
+    };
+    crate::store::write_atomic(&path, serde_json::to_string(queue).unwrap_or_default().as_bytes())?;
+    Ok(())
+}
+
+/// Whether `e` means that the API couldn't be reached at all, rather than that it refused.
+pub fn is_offline(e: &PlayingError) -> bool {
+    matches!(e.kind, PlayingErrorKind::Spotifav) && matches!(
+        e.inner.downcast_ref::<ClientError>(),
+        Some(ClientError::Http(h)) if matches!(**h, rspotify::http::HttpError::Client(_))
+    )
+}
+
+/// The Spotify id of the track in the URL Spotify gives its tracks, e.g.
+/// `https://open.spotify.com/track/<id>`.
+pub fn id_in_url(url: &str) -> Option<&str> {
+    let id = url.strip_prefix("https://open.spotify.com/track/")?;
+    Some(id.split(['?', '/']).next().unwrap_or(id)).filter(|id| !id.is_empty())
+}
+
+/// Queues toggling `track` for when the API is back, returning whether it's to be saved.
+///
+/// Whether it's saved now is what a change of it queued already makes it, else what the likes
+/// last learned of it, however old; a track neither tells about can't be toggled offline.
+pub fn push(track: &Track) -> Result<bool, PlayingError> {
+    let Some(id) = track.url.as_deref().and_then(id_in_url) else {
+        return Err(PlayingError::favorite("spotify is unreachable and the track has no id to queue it by"))
+    };
+    let queued = load().into_iter().find(|q| q.track == id).map(|q| q.saved);
+    let Some(current) = queued.or_else(|| Likes::load().last(id)) else {
+        return Err(PlayingError::favorite("spotify is unreachable and whether the track is saved isn't known yet"))
+    };
+    let saved = !current;
+    enqueue(id, saved)?;
+    Ok(saved)
+}
+
+/// Queues saving or removing the track `id` for when the API is back, replacing what was
+/// queued of it.
+pub fn enqueue(id: &str, saved: bool) -> Result<(), PlayingError> {
+    let mut queue = load();
+    queue.retain(|q| q.track != id);
+    queue.push(Queued {
+        track: id.to_owned(),
+        saved,
+        queued_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+    });
+    save(&queue)
+}
+
+/// Makes the queued changes, oldest first, keeping them while the API is unreachable.
+pub async fn flush(cli: &AuthCodeSpotify) -> Result<(), PlayingError> {
+    let mut queue = load();
+    if queue.is_empty() {
+        return Ok(())
+    }
+    while let Some(q) = queue.first() {
+        let result = match TrackId::from_id(q.track.clone()) {
+            Ok(id) if q.saved => cli.current_user_saved_tracks_add([id]).await.map_err(spotify_error),
+            Ok(id) => cli.current_user_saved_tracks_delete([id]).await.map_err(spotify_error),
+            Err(e) => Err(PlayingError::favorite(e.to_string())),
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if is_offline(&e) => break,
+            // it would fail every time
+            Err(e) => eprintln!("dropping the queued favorite {}: {}", q.track, e.inner),
+        }
+        queue.remove(0);
+    }
+    save(&queue)
+}
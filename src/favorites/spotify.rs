@@ -0,0 +1,114 @@
+use rspotify::{model::TrackId, prelude::OAuthClient, AuthCodeSpotify};
+
+use super::{likes::{Likes, SpotifyConfig}, queue, FavoriteProvider, Pending};
+use crate::{backend::Track, spotify::spotify_error, PlayingError};
+
+/// The saved tracks of the Spotify account, whatever Spotify plays on any device.
+pub struct Spotify<'a> {
+    pub config: &'a SpotifyConfig,
+    /// Wait for Spotify to report the new track first, as right after skipping.
+    pub poll: bool,
+}
+
+/// The id of the track the local Spotify plays, none for the other players and devices.
+fn local_id(track: &Track) -> Option<&str> {
+    track.url.as_deref().and_then(queue::id_in_url)
+}
+
+impl Spotify<'_> {
+    /// Keeps whether the track is saved for `contains`, after a change of it.
+    fn learn(&self, track: &Track, saved: bool) {
+        if let Some(id) = local_id(track) {
+            let ttl = self.config.likes_ttl.0;
+            Likes::load().learn(id, saved, ttl);
+        }
+    }
+
+    /// Whether the track is saved, asking the API only when the cache has nothing recent and the
+    /// last call was long enough ago; calls for tracks of other devices are limited too.
+    async fn saved(&self, track: &Track) -> Result<bool, PlayingError> {
+        let (ttl, interval) = (self.config.likes_ttl.0, self.config.min_interval.0);
+        let mut likes = Likes::load();
+        let id = local_id(track);
+        if let Some(saved) = id.and_then(|id| likes.fresh(id, ttl)) {
+            return Ok(saved)
+        }
+        if likes.limited(interval) {
+            return id.and_then(|id| likes.last(id))
+                .ok_or_else(|| PlayingError::favorite("spotify was asked moments ago, try again later"))
+        }
+        likes.ask(ttl);
+        let cli = self.client().await?;
+        let Some(id) = id else {
+            return crate::spotify::is_saved(&cli).await?
+                .ok_or_else(|| PlayingError::favorite("spotify is not playing a track"))
+        };
+        let track_id = TrackId::from_id(id).map_err(|e| PlayingError::favorite(e.to_string()))?;
+        let saved = cli.current_user_saved_tracks_contains([track_id]).await.map_err(spotify_error)?
+            .first()
+            .copied()
+            .unwrap_or_default();
+        likes.learn(id, saved, ttl);
+        Ok(saved)
+    }
+
+    /// The client, once the changes queued while the API was unreachable are made.
+    async fn client(&self) -> Result<AuthCodeSpotify, PlayingError> {
+        let cli = spotifav::get_client().await.map_err(PlayingError::from_spotifav)?;
+        queue::flush(&cli).await?;
+        if self.poll {
+            spotifav::poll(&cli).await.map_err(PlayingError::from_spotifav)?;
+        }
+        Ok(cli)
+    }
+}
+
+impl FavoriteProvider for Spotify<'_> {
+    fn contains<'a>(&'a self, _: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(self.saved(track))
+    }
+
+    /// Queues the toggle of the track of the local player while the API is unreachable.
+    fn toggle<'a>(&'a self, _: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(async move {
+            let toggled = match self.client().await {
+                Ok(cli) => spotifav::do_toggle(&cli).await.map_err(PlayingError::from_spotifav),
+                Err(e) => Err(e),
+            };
+            let saved = match toggled {
+                Err(e) if queue::is_offline(&e) => {
+                    let saved = queue::push(track)?;
+                    eprintln!("spotify is unreachable, the change is queued until it's back");
+                    saved
+                }
+                toggled => toggled?,
+            };
+            self.learn(track, saved);
+            Ok(saved)
+        })
+    }
+
+    /// Only knows the tracks played by the local Spotify, whose URL has their id.
+    fn set<'a>(&'a self, _: &'a str, track: &'a Track, saved: bool) -> Pending<'a, ()> {
+        Box::pin(async move {
+            let Some(id) = track.url.as_deref().and_then(queue::id_in_url) else {
+                return Err(PlayingError::favorite("the track has no spotify id"))
+            };
+            let track_id = TrackId::from_id(id).map_err(|e| PlayingError::favorite(e.to_string()))?;
+            let result = match self.client().await {
+                Ok(cli) if saved => cli.current_user_saved_tracks_add([track_id]).await.map_err(spotify_error),
+                Ok(cli) => cli.current_user_saved_tracks_delete([track_id]).await.map_err(spotify_error),
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if queue::is_offline(&e) => {
+                    eprintln!("spotify is unreachable, the change is queued until it's back");
+                    queue::enqueue(id, saved)?;
+                }
+                result => result?,
+            }
+            self.learn(track, saved);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{FavoriteProvider, Pending};
+use crate::{backend::Track, PlayingError};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Version of the API spoken, the one of Subsonic 6.1 which Navidrome and others implement.
+const API_VERSION: &str = "1.16.1";
+
+/// The `[subsonic]` table of the config file, the server whose starred songs are the favorites.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SubsonicConfig {
+    /// Root of the server, e.g. `https://music.example.com`.
+    url: String,
+    user: String,
+    password: String,
+}
+
+/// The starred songs of a Subsonic server, e.g. Navidrome.
+pub struct Subsonic<'a> {
+    config: &'a SubsonicConfig,
+}
+
+/// The `id` parameter of a `stream` or `download` URL of the API, which players of the server's
+/// songs are given.
+fn id_in_url(url: &str) -> Option<&str> {
+    let (path, query) = url.split_once('?')?;
+    if !path.contains("/rest/") {
+        return None
+    }
+    query.split('&').find_map(|p| p.strip_prefix("id="))
+}
+
+impl<'a> Subsonic<'a> {
+    pub fn new(config: &'a SubsonicConfig) -> Self {
+        Subsonic { config }
+    }
+
+    /// Calls `method` with `params`, returning the `subsonic-response` object.
+    fn call(&self, method: &str, params: &[(&str, &str)]) -> Result<Value, PlayingError> {
+        // the password is only sent salted and hashed
+        let salt = format!("{:x}", SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default());
+        let token = format!("{:x}", md5::compute(format!("{}{}", self.config.password, salt)));
+        let mut request = ureq::get(&format!("{}/rest/{}", self.config.url.trim_end_matches('/'), method))
+            .timeout(TIMEOUT)
+            .query("u", &self.config.user)
+            .query("t", &token)
+            .query("s", &salt)
+            .query("v", API_VERSION)
+            .query("c", "playing.rs")
+            .query("f", "json");
+        for (k, v) in params {
+            request = request.query(k, v);
+        }
+        let body: Value = request.call()
+            .map_err(|e| PlayingError::favorite(format!("subsonic: {}", e)))?
+            .into_json()?;
+        let response = &body["subsonic-response"];
+        if response["status"] != "ok" {
+            let message = response["error"]["message"].as_str().unwrap_or("unexpected response");
+            return Err(PlayingError::favorite(format!("subsonic: {}", message)))
+        }
+        Ok(response.clone())
+    }
+
+    /// The id of `track` on the server: the one in its URL, or else the best result of searching
+    /// its title and artist.
+    fn song(&self, track: &Track) -> Result<String, PlayingError> {
+        if let Some(id) = track.url.as_deref().and_then(id_in_url) {
+            return Ok(id.to_owned())
+        }
+        let Some(ref title) = track.title else {
+            return Err(PlayingError::favorite("the track has no title to look it up on the subsonic server"))
+        };
+        let artist = track.artist();
+        let query = match artist {
+            Some(a) => format!("{} {}", a, title),
+            None => title.clone(),
+        };
+        let found = self.call("search3", &[("query", &query), ("songCount", "10"), ("albumCount", "0"), ("artistCount", "0")])?;
+        let songs = found["searchResult3"]["song"].as_array().cloned().unwrap_or_default();
+        let is = |s: &Value, key: &str, value: &str| s[key].as_str().is_some_and(|v| v.eq_ignore_ascii_case(value));
+        songs.iter()
+            .find(|s| is(s, "title", title) && artist.is_none_or(|a| is(s, "artist", a)))
+            .or_else(|| songs.iter().find(|s| is(s, "title", title)))
+            .and_then(|s| s["id"].as_str())
+            .map(str::to_owned)
+            .ok_or_else(|| PlayingError::favorite(format!("`{}` is not on the subsonic server", query)))
+    }
+
+    fn starred(&self, id: &str) -> Result<bool, PlayingError> {
+        let song = self.call("getSong", &[("id", id)])?;
+        Ok(song["song"].get("starred").is_some())
+    }
+
+    fn star(&self, track: &Track) -> Result<bool, PlayingError> {
+        let id = self.song(track)?;
+        let starred = self.starred(&id)?;
+        self.call(if starred { "unstar" } else { "star" }, &[("id", &id)])?;
+        Ok(!starred)
+    }
+
+    fn set(&self, track: &Track, starred: bool) -> Result<(), PlayingError> {
+        let id = self.song(track)?;
+        self.call(if starred { "star" } else { "unstar" }, &[("id", &id)])?;
+        Ok(())
+    }
+}
+
+impl FavoriteProvider for Subsonic<'_> {
+    fn contains<'a>(&'a self, _: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(std::future::ready(self.song(track).and_then(|id| self.starred(&id))))
+    }
+
+    fn toggle<'a>(&'a self, _: &'a str, track: &'a Track) -> Pending<'a, bool> {
+        Box::pin(std::future::ready(self.star(track)))
+    }
+
+    fn set<'a>(&'a self, _: &'a str, track: &'a Track, saved: bool) -> Pending<'a, ()> {
+        Box::pin(std::future::ready(Subsonic::set(self, track, saved)))
+    }
+}
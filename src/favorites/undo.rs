@@ -0,0 +1,70 @@
+use std::{path::PathBuf, time::Duration};
+use serde::{Deserialize, Serialize};
+
+use super::ProviderKind;
+use crate::{backend::Track, PlayingError};
+
+/// The last toggle of a favorite, kept for `favorite --undo`.
+#[derive(Serialize, Deserialize)]
+pub struct Toggle {
+    pub provider: ProviderKind,
+    pub player: String,
+    pub title: Option<String>,
+    pub album_artists: Vec<String>,
+    pub artists: Vec<String>,
+    pub url: Option<String>,
+    pub length: Option<f64>,
+    /// Whether the toggle added the track, rather than removed it.
+    pub added: bool,
+}
+
+impl Toggle {
+    /// The track toggled, as much of it as the providers look it up by.
+    pub fn track(&self) -> Track {
+        Track {
+            title: self.title.clone(),
+            album_artists: self.album_artists.clone(),
+            artists: self.artists.clone(),
+            url: self.url.clone(),
+            // read back from the data dir, where it can be anything
+            length: self.length.and_then(|l| Duration::try_from_secs_f64(l).ok()),
+            ..Track::default()
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("favorite-last.json")
+}
+
+pub fn last() -> Option<Toggle> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+}
+
+/// Keeps the toggle of `track` for undoing it; failing to only loses the undo.
+pub fn record(provider: ProviderKind, player: &str, track: &Track, added: bool) {
+    let Some(path) = path() else { return };
+    let toggle = Toggle {
+        provider,
+        player: player.to_owned(),
+        title: track.title.clone(),
+        album_artists: track.album_artists.clone(),
+        artists: track.artists.clone(),
+        url: track.url.clone(),
+        length: track.length.map(|l| l.as_secs_f64()),
+        added,
+    };
+    if let Err(e) = crate::store::write_atomic(&path, serde_json::to_string(&toggle).unwrap_or_default().as_bytes()) {
+        eprintln!("cannot keep the toggle to undo it: {}", e);
+    }
+}
+
+/// Forgets the last toggle once undone, so that undoing twice doesn't toggle it back.
+pub fn forget() -> Result<(), PlayingError> {
+    match path() {
+        Some(path) if path.exists() => Ok(std::fs::remove_file(path)?),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,117 @@
+use std::{fs::File, io::{BufRead, BufReader, Write}, path::Path, thread::sleep, time::{Duration, Instant}};
+use serde_json::json;
+
+use crate::{backend::{self, Backend, Frame, Recorded, ReplayBackend, Status}, config::Config, follow::{self, Snapshot}, scrobble, status::{self, StatusOptions}, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Writes the state of every player to `path` as a line of JSON every half second, until
+/// interrupted, skipping the polls where nothing but the positions moved on.
+pub fn record(backends: &[Box<dyn Backend>], path: &Path) -> Result<bool, PlayingError> {
+    let mut file = File::create(path)?;
+    let start = Instant::now();
+    let mut last: Option<Vec<Recorded>> = None;
+    loop {
+        match backend::players(backends).and_then(|players| players.iter().map(|p| Recorded::take(p.as_ref())).collect::<Result<Vec<_>, _>>()) {
+            Ok(players) => {
+                let moved_on = |last: &Vec<Recorded>| last.len() == players.len() && last.iter().zip(&players)
+                    .all(|(l, p)| Recorded { position: p.position, ..l.clone() } == *p);
+                if !last.as_ref().is_some_and(moved_on) {
+                    let frame = Frame { t: start.elapsed().as_secs_f64(), players };
+                    writeln!(file, "{}", serde_json::to_string(&frame).unwrap_or_default())?;
+                    last = Some(frame.players);
+                }
+            }
+            Err(e) => eprintln!("cannot read the players: {}", e.inner),
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// The track being listened to in a replay and for how long it was played, as the scrobbler
+/// counts it.
+struct Listening {
+    snapshot: Snapshot,
+    played: Duration,
+    scrobbled: bool,
+}
+
+/// Feeds the frames of the fixture at `path` through what the daemons make of the players,
+/// printing a line of JSON for every frame that changed something: the `event`, the status
+/// `line`, the `hook` run and the track a `scrobble` would submit.
+///
+/// Time is taken from the fixture rather than the clock, so that replays always print the same.
+pub fn replay(config: &Config, path: &Path, opts: &StatusOptions) -> Result<bool, PlayingError> {
+    let replay = ReplayBackend::default();
+    let backends: Vec<Box<dyn Backend>> = vec![Box::new(replay.clone())];
+    let ranking = config.ranking();
+    let start = Instant::now();
+    let mut last: Option<Snapshot> = None;
+    let mut last_line: Option<String> = None;
+    let mut listening: Option<Listening> = None;
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue
+        }
+        let frame: Frame = serde_json::from_str(&line)
+            .map_err(|e| PlayingError::config(format!("{}:{}: {}", path.display(), n + 1, e)))?;
+        replay.set(frame.players);
+        let at = start + Duration::from_secs_f64(frame.t.max(0.0));
+
+        let mut next = follow::current(&backends, &ranking)?;
+        if let Some(ref mut s) = next {
+            s.at = at;
+        }
+        let event = follow::event(last.as_ref(), next.as_ref());
+        let players = backend::players(&backends)?;
+        let line = match crate::active_player(&players, &ranking)? {
+            Some(p) => status::render(p, opts, (event == Some("seek")).then_some("seek"))?,
+            None => opts.idle("No media"),
+        };
+        let hook = event.and_then(|e| config.hooks.matching(e, next.as_ref())).map(|(name, _)| name);
+
+        // frames are only recorded on changes, so a track playing in one is taken to have played
+        // until the next one
+        let mut scrobble = None;
+        if let Some(ref mut l) = listening {
+            if l.snapshot.status == Status::Playing {
+                l.played += at.saturating_duration_since(l.snapshot.at);
+            }
+            if !l.scrobbled && scrobble::is_due(l.snapshot.track.plausible_length(), l.played) {
+                l.scrobbled = true;
+                scrobble = Some(json!({
+                    "artist": l.snapshot.track.artist(),
+                    "title": l.snapshot.track.title,
+                    "played": l.played.as_secs_f64(),
+                }));
+            }
+        }
+        listening = match (listening.take(), next.as_ref()) {
+            (Some(l), Some(n)) if l.snapshot.same_track(n) => Some(Listening { snapshot: n.clone(), ..l }),
+            (_, Some(n)) => Some(Listening { snapshot: n.clone(), played: Duration::ZERO, scrobbled: false }),
+            (_, None) => None,
+        };
+
+        let changed = last_line.as_ref() != Some(&line);
+        if event.is_some() || changed || scrobble.is_some() {
+            let mut out = json!({ "t": frame.t });
+            if let Some(e) = event {
+                out["event"] = json!(e);
+            }
+            if changed || event == Some("seek") {
+                out["line"] = json!(line);
+            }
+            if let Some(h) = hook {
+                out["hook"] = json!(h);
+            }
+            if let Some(s) = scrobble {
+                out["scrobble"] = s;
+            }
+            println!("{}", out);
+        }
+        last_line = Some(line);
+        last = next;
+    }
+    Ok(true)
+}
@@ -0,0 +1,310 @@
+use std::{fs::{self, File}, io::{self, Write}, path::PathBuf, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use serde_json::{json, Value};
+
+use crate::{backend::{self, Backend, MediaPlayer, Status, Track}, hooks::HooksConfig, notify::Notifier, status::{self, Classes, StatusOptions}, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Distance from the expected position past which the player must have been seeked.
+const SEEK_TOLERANCE: Duration = Duration::from_millis(1500);
+
+/// What a player was doing at a given time, to tell what changed since.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub player: String,
+    pub track: Track,
+    pub position: Option<Duration>,
+    pub status: Status,
+    pub rate: f64,
+    pub playlist: Option<(usize, usize)>,
+    pub at: Instant,
+}
+
+impl Snapshot {
+    pub fn take(p: &dyn MediaPlayer) -> Result<Snapshot, PlayingError> {
+        let _span = crate::timing::span("metadata read");
+        Ok(Snapshot {
+            player: p.identity().to_owned(),
+            track: p.track()?,
+            position: p.position()?,
+            status: p.status()?,
+            rate: p.rate()?,
+            playlist: p.playlist()?,
+            at: Instant::now(),
+        })
+    }
+
+    /// The snapshot as published by the mqtt and http servers, the fields of the status line as
+    /// `opts` renders them, see [`StatusOptions::published`].
+    pub fn json(&self, opts: &StatusOptions) -> Value {
+        let reading = status::Reading {
+            identity: &self.player,
+            track: &self.track,
+            status: Some(self.status),
+            position: self.position,
+            rate: self.rate,
+            playlist: self.playlist,
+            favorite: crate::favorites::is_favorite(opts.config, &self.player, &self.track),
+        };
+        let mut state = status::state(&reading, opts);
+        state["updated_at"] = json!(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default());
+        state
+    }
+
+    /// What [`Snapshot::json`] says without any player.
+    pub fn stopped() -> Value {
+        json!({ "status": "stopped" })
+    }
+
+    /// Whether `next` is the same player on the same track.
+    pub fn same_track(&self, next: &Snapshot) -> bool {
+        self.player == next.player && self.track.title == next.track.title && self.track.url == next.track.url
+    }
+
+    /// Whether the position in `next` is too far from where playback would have taken `self`.
+    pub fn seeked_to(&self, next: &Snapshot) -> bool {
+        if !self.same_track(next) {
+            return false
+        }
+        let (Some(before), Some(after)) = (self.position, next.position) else { return false };
+        let expected = if self.status == Status::Playing {
+            before + next.at.duration_since(self.at).mul_f64(self.rate.max(0.0))
+        } else {
+            before
+        };
+        after.abs_diff(expected) > SEEK_TOLERANCE
+    }
+}
+
+/// What changed between two snapshots, if anything worth an event.
+pub fn event(last: Option<&Snapshot>, next: Option<&Snapshot>) -> Option<&'static str> {
+    match (last, next) {
+        (None, None) => None,
+        (None, Some(_)) | (Some(_), None) => Some("player"),
+        (Some(l), Some(n)) if !l.same_track(n) => Some("track"),
+        (Some(l), Some(n)) if l.status != n.status => Some(n.status.name()),
+        (Some(l), Some(n)) if l.seeked_to(n) => Some("seek"),
+        _ => None,
+    }
+}
+
+/// A snapshot of the current player, playing or else paused.
+pub fn current(backends: &[Box<dyn Backend>], ranking: &[String]) -> Result<Option<Snapshot>, PlayingError> {
+    let players = backend::players(backends)?;
+    crate::current_player(&players, ranking)?.map(Snapshot::take).transpose()
+}
+
+/// Files rewritten instead of printing, for e.g. the text and image sources of OBS.
+pub struct Files {
+    line: Sink,
+    /// Gets the path (or URL, when not local) of the cover art.
+    art: Option<Sink>,
+}
+
+/// A file given the latest line, or a named pipe given every line, e.g. for bars reading a FIFO.
+struct Sink {
+    path: PathBuf,
+    fifo: bool,
+    /// Write end of the pipe, open while a reader is attached.
+    pipe: Option<File>,
+}
+
+impl Sink {
+    fn new(path: PathBuf) -> Self {
+        #[cfg(unix)]
+        let fifo = {
+            use std::os::unix::fs::FileTypeExt;
+            fs::metadata(&path).is_ok_and(|m| m.file_type().is_fifo())
+        };
+        #[cfg(not(unix))]
+        let fifo = false;
+        Sink { path, fifo, pipe: None }
+    }
+
+    fn write(&mut self, contents: &str) {
+        let result = if self.fifo { self.send(contents) } else { crate::store::write_atomic(&self.path, contents.as_bytes()) };
+        if let Err(e) = result {
+            eprintln!("cannot write {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Writes a line to the pipe without ever blocking: lines are dropped while no reader is
+    /// attached or while it lags behind, and the pipe is opened again when a reader comes back.
+    #[cfg(unix)]
+    fn send(&mut self, contents: &str) -> io::Result<()> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let pipe = match self.pipe {
+            Some(ref mut pipe) => pipe,
+            None => match fs::OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(&self.path) {
+                Ok(pipe) => self.pipe.insert(pipe),
+                // nobody reads it yet
+                Err(e) if e.raw_os_error() == Some(libc::ENXIO) => return Ok(()),
+                Err(e) => return Err(e),
+            },
+        };
+        match pipe.write_all(format!("{}\n", contents).as_bytes()) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                self.pipe = None;
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            result => result,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn send(&mut self, _: &str) -> io::Result<()> {
+        unreachable!("named pipes are only detected on unix")
+    }
+
+    /// Gives `contents` to a reader attached to the pipe since the last write, which would
+    /// otherwise wait for the next change.
+    fn reattach(&mut self, contents: &str) {
+        if self.fifo && self.pipe.is_none() {
+            self.write(contents);
+        }
+    }
+}
+
+/// The local path of the art at `url`, or the url itself when it's remote.
+pub fn art_path(url: &str) -> String {
+    let Some(path) = url.strip_prefix("file://") else { return url.to_owned() };
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn art(snapshot: Option<&Snapshot>) -> String {
+    snapshot.and_then(|s| s.track.art_url.as_deref()).map(art_path).unwrap_or_default()
+}
+
+impl Files {
+    pub fn new(line: PathBuf, art: Option<PathBuf>) -> Self {
+        Files { line: Sink::new(line), art: art.map(Sink::new) }
+    }
+
+    fn write(&mut self, line: &str, snapshot: Option<&Snapshot>) {
+        self.line.write(line);
+        if let Some(ref mut sink) = self.art {
+            sink.write(&art(snapshot));
+        }
+    }
+
+    fn reattach(&mut self, line: &str, snapshot: Option<&Snapshot>) {
+        self.line.reattach(line);
+        if let Some(ref mut sink) = self.art {
+            sink.reattach(&art(snapshot));
+        }
+    }
+}
+
+/// Prints the status line of the active player whenever it changes, or writes it to `files`.
+/// Seeks, by this tool or anything else, print the line right away with `{event}` set to `seek`:
+/// the `Seeked` signals wake the loop up, and the seeks of players without them are told by a
+/// jump of the position on the next poll.
+///
+/// The `hooks` follow the current player, paused or not, rather than the active one. The
+/// `notifier` shows every track the active player starts.
+pub fn run(backends: &[Box<dyn Backend>], ranking: &[String], opts: &StatusOptions, quiet: bool, mut files: Option<Files>, hooks: &HooksConfig, mut notifier: Option<Notifier>) -> Result<bool, PlayingError> {
+    let mut last_line: Option<(String, Classes)> = None;
+    let mut offset = 0;
+    let mut last: Option<Snapshot> = None;
+    let mut hooked: Option<Snapshot> = None;
+    loop {
+        if !hooks.is_empty() {
+            match current(backends, ranking) {
+                Ok(next) => {
+                    if let Some(e) = event(hooked.as_ref(), next.as_ref()) {
+                        hooks.run(e, next.as_ref());
+                    }
+                    hooked = next;
+                }
+                Err(e) => eprintln!("cannot read the current player: {}", e.inner),
+            }
+        }
+        let refreshed = crate::refresh::take();
+        let signaled = crate::seeked::take();
+        match tick(backends, ranking, opts, quiet, last.as_ref(), signaled) {
+            Ok((line, classes, snapshot, seeked)) => {
+                if refreshed || seeked || last_line.as_ref().is_none_or(|(l, c)| *l != line || *c != classes) {
+                    offset = 0;
+                    let frame = opts.frame(&line, offset, classes);
+                    match files {
+                        Some(ref mut files) => files.write(&frame, snapshot.as_ref()),
+                        None => println!("{}", frame),
+                    }
+                    last_line = Some((line, classes));
+                } else if let Some(ref mut files) = files {
+                    files.reattach(&opts.frame(&line, offset, classes), snapshot.as_ref());
+                }
+                if let (Some(n), Some(s), Some("player" | "track")) = (&mut notifier, &snapshot, event(last.as_ref(), snapshot.as_ref())) {
+                    if let Err(e) = n.track(s, &[]) {
+                        eprintln!("cannot send a notification: {}", e.inner);
+                    }
+                }
+                last = snapshot;
+            }
+            // players come and go between listing and querying them
+            Err(e) => eprintln!("cannot read the active player: {}", e.inner),
+        }
+        crate::timing::tick();
+        match (opts.scroll, &last_line) {
+            (Some(step), Some((line, classes))) if opts.overflows(line) => {
+                scroll(opts, step, line, *classes, &mut offset, &mut files);
+            }
+            _ => {
+                crate::refresh::wait(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Moves the marquee of `line` by a character every `step` until the next poll of the players,
+/// or until a refresh is asked for.
+fn scroll(opts: &StatusOptions, step: Duration, line: &str, classes: Classes, offset: &mut usize, files: &mut Option<Files>) {
+    let poll = Instant::now() + POLL_INTERVAL;
+    let mut at = Instant::now();
+    loop {
+        at += step;
+        if at > poll {
+            crate::refresh::wait(poll.saturating_duration_since(Instant::now()));
+            return
+        }
+        if crate::refresh::wait(at.saturating_duration_since(Instant::now())) {
+            return
+        }
+        *offset += 1;
+        let frame = opts.frame(line, *offset, classes);
+        match files {
+            Some(ref mut files) => files.line.write(&frame),
+            None => println!("{}", frame),
+        }
+    }
+}
+
+/// The line of the active player, uncut, its classes, and whether it was seeked: as `signaled` by
+/// the players or the operations, or else as told by a jump of the position.
+fn tick(backends: &[Box<dyn Backend>], ranking: &[String], opts: &StatusOptions, quiet: bool, last: Option<&Snapshot>, signaled: bool) -> Result<(String, Classes, Option<Snapshot>, bool), PlayingError> {
+    let players = backend::players(backends)?;
+    let p = if opts.include_paused { crate::current_player(&players, ranking)? } else { crate::active_player(&players, ranking)? };
+    let Some(p) = p else {
+        return Ok((if quiet { "" } else { "No media" }.to_owned(), Classes::default(), None, false))
+    };
+    let snapshot = Snapshot::take(p)?;
+    let seeked = signaled || last.is_some_and(|l| l.seeked_to(&snapshot));
+    let (line, classes) = status::line(p, opts, seeked.then_some("seek"))?;
+    Ok((line, classes, Some(snapshot), seeked))
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use serde::Deserialize;
+
+pub use playing_format::{duration, remaining_time, Template};
+
+/// Parses a duration written as `90`, `85s`, `1m30s`, `2h` or `1:30`/`1:02:03`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let secs = if s.contains(':') {
+        let mut secs = 0.0;
+        for part in s.split(':') {
+            secs = secs * 60.0 + part.parse::<f64>().ok().filter(|p| *p >= 0.0)?;
+        }
+        secs
+    } else {
+        let mut secs = 0.0;
+        let mut number = String::new();
+        for c in s.chars() {
+            let unit = match c {
+                'h' => 3600.0,
+                'm' => 60.0,
+                's' => 1.0,
+                c => {
+                    number.push(c);
+                    continue
+                }
+            };
+            secs += number.parse::<f64>().ok().filter(|n| *n >= 0.0)? * unit;
+            number.clear();
+        }
+        if !number.is_empty() {
+            secs += number.parse::<f64>().ok().filter(|n| *n >= 0.0)?;
+        }
+        secs
+    };
+    // e.g. `1e20s`, longer than a duration holds
+    (!s.is_empty()).then(|| Duration::try_from_secs_f64(secs).ok()).flatten()
+}
+
+/// A duration in the config file, written as [`parse_duration`] accepts it.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+#[serde(try_from = "String")]
+pub struct DurationSpec(pub Duration);
+
+impl TryFrom<String> for DurationSpec {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        parse_duration(&s).map(DurationSpec).ok_or_else(|| format!("invalid duration `{}`, expected e.g. `90s`, `1m30s` or `1:30`", s))
+    }
+}
+
+/// The regular expressions of the selectors, compiled once as the config is checked rather than
+/// for every player of every ranking entry.
+static SELECTORS: Mutex<Option<HashMap<String, Result<regex::Regex, String>>>> = Mutex::new(None);
+
+/// The regular expression of a player selector written as `/.../`.
+fn selector_regex(selector: &str) -> Option<&str> {
+    selector.strip_prefix('/')?.strip_suffix('/')
+}
+
+/// The regular expression `re` of a selector, compiled or what's wrong with it.
+fn compiled(re: &str) -> Result<regex::Regex, String> {
+    let mut selectors = SELECTORS.lock().unwrap_or_else(|e| e.into_inner());
+    // clones of a regex share what was compiled
+    selectors.get_or_insert_with(HashMap::new)
+        .entry(re.to_owned())
+        .or_insert_with(|| regex::Regex::new(re).map_err(|e| e.to_string()))
+        .clone()
+}
+
+/// Whether the player selector `selector`, of the ranking, a group or `--player`, picks the
+/// player `identity`: selectors are identities, globs such as `Brave*`, or regular expressions
+/// between slashes such as `/(?i)^(chromium|brave)$/`.
+pub fn selects(selector: &str, identity: &str) -> bool {
+    match selector_regex(selector) {
+        Some(re) => compiled(re).is_ok_and(|re| re.is_match(identity)),
+        None => glob(selector, identity),
+    }
+}
+
+/// Tells what's wrong with the regular expression of `selector`, if it has one.
+pub fn check_selector(selector: &str) -> Result<(), String> {
+    match selector_regex(selector).map(compiled) {
+        Some(Err(e)) => Err(format!("invalid player selector `{}`: {}", selector, e)),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `s` matches `pattern`, where `*` stands for any run of characters.
+pub fn glob(pattern: &str, s: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = s.strip_prefix(first) else { return false };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else { return rest.is_empty() };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
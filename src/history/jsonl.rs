@@ -0,0 +1,91 @@
+use std::{fs::{self, OpenOptions}, io::Write, path::{Path, PathBuf}, time::Duration};
+use serde::{Deserialize, Serialize};
+
+use super::{Play, Stat, Storage, Top};
+use crate::PlayingError;
+
+/// A play as a line of the file, in seconds.
+#[derive(Serialize, Deserialize)]
+struct Line {
+    player: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    url: Option<String>,
+    length: Option<f64>,
+    listened: f64,
+    started_at: u64,
+}
+
+fn seconds(s: f64) -> Duration {
+    Duration::try_from_secs_f64(s).unwrap_or_default()
+}
+
+impl From<Line> for Play {
+    fn from(l: Line) -> Play {
+        Play {
+            player: l.player,
+            title: l.title,
+            artist: l.artist,
+            album: l.album,
+            url: l.url,
+            length: l.length.map(seconds),
+            listened: seconds(l.listened),
+            started_at: l.started_at,
+        }
+    }
+}
+
+/// The plays as lines of JSON appended to a file, easy to sync, grep and feed to other tools.
+pub struct JsonlStorage {
+    path: PathBuf,
+}
+
+impl JsonlStorage {
+    pub fn open(path: &Path) -> Result<JsonlStorage, PlayingError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(JsonlStorage { path: path.to_owned() })
+    }
+
+    /// Every play, in the order they were recorded; lines that can't be read are left out.
+    fn plays(&self) -> Result<Vec<Play>, PlayingError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(contents.lines().filter_map(|l| serde_json::from_str::<Line>(l).ok()).map(Play::from).collect())
+    }
+}
+
+impl Storage for JsonlStorage {
+    fn record(&self, play: &Play) -> Result<(), PlayingError> {
+        let line = Line {
+            player: play.player.clone(),
+            title: play.title.clone(),
+            artist: play.artist.clone(),
+            album: play.album.clone(),
+            url: play.url.clone(),
+            length: play.length.map(|l| l.as_secs_f64()),
+            listened: play.listened.as_secs_f64(),
+            started_at: play.started_at,
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&line).unwrap_or_default())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn recent(&self, limit: usize) -> Result<Vec<Play>, PlayingError> {
+        let mut plays = self.plays()?;
+        plays.sort_by_key(|p| std::cmp::Reverse(p.started_at));
+        plays.truncate(limit);
+        Ok(plays)
+    }
+
+    fn stats(&self, top: Top, since: u64, limit: usize) -> Result<Vec<Stat>, PlayingError> {
+        Ok(super::stats_of(self.plays()?, top, since, limit))
+    }
+}
@@ -0,0 +1,332 @@
+use std::{collections::HashMap, path::PathBuf, thread::sleep, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use clap::{Subcommand, ValueEnum};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{backend::{self, BackendKind}, config::Config, follow::{self, Snapshot}, format::{self, DurationSpec}, PlayingError};
+
+mod jsonl;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod sqlite;
+
+pub use sqlite::{db_error, SqliteStorage};
+use jsonl::JsonlStorage;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The `[history]` table of the config file.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Where the plays are kept.
+    pub storage: StorageKind,
+    /// Database or file of the storage, defaults to `history.db` or `history.jsonl` in the data dir.
+    pub path: Option<PathBuf>,
+    /// Connection string of the `postgres` storage, e.g. `host=db.lan user=playing dbname=playing`.
+    pub url: Option<String>,
+    /// Name the plays of this machine are recorded under in the `postgres` storage, defaults to
+    /// the host name.
+    pub host: Option<String>,
+    /// Tracks listened to for less than this, e.g. skipped ones, aren't recorded.
+    pub min_listened: DurationSpec,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig { storage: StorageKind::Sqlite, path: None, url: None, host: None, min_listened: DurationSpec(Duration::from_secs(10)) }
+    }
+}
+
+/// The storages `[history]` can keep the plays in.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    Sqlite,
+    /// A line of JSON per play.
+    Jsonl,
+    /// A PostgreSQL server, shared by the machines recording to it; needs the `postgres` feature.
+    Postgres,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// Keeps running and records every track played to the history database
+    Record,
+    /// Lists the last tracks played, the default
+    Recent {
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+    /// Sums up the listening time and plays of the top artists, tracks or players
+    Stats {
+        /// Only count plays since this date or that long ago, e.g. 2024-01-01, 30d or 12h
+        #[arg(long, value_parser = parse_since)]
+        since: Option<u64>,
+        #[arg(value_enum, long, default_value = "artists")]
+        top: Top,
+        #[arg(short = 'n', long, default_value = "10")]
+        limit: usize,
+    },
+}
+
+/// What `history stats` ranks.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Top {
+    Artists,
+    Tracks,
+    Players,
+}
+
+impl Top {
+    /// Columns the plays are grouped by.
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Top::Artists => &["artist"],
+            Top::Tracks => &["artist", "title"],
+            Top::Players => &["player"],
+        }
+    }
+}
+
+/// Listening time and plays of an artist, track or player.
+pub struct Stat {
+    /// Values of the columns of [`Top::columns`].
+    pub key: Vec<Option<String>>,
+    pub plays: u64,
+    pub listened: Duration,
+}
+
+/// Parses `--since` into a unix time: a local date, or a time ago in days (`30d`) or as a duration.
+fn parse_since(s: &str) -> Result<u64, String> {
+    let ago = if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+            .map(|t| t.timestamp().max(0) as u64)
+            .ok_or_else(|| format!("`{}` is not a valid local date", s))
+    } else if let Some(days) = s.strip_suffix('d').and_then(|d| d.parse::<u64>().ok()) {
+        Duration::from_secs(days * 86400)
+    } else {
+        format::parse_duration(s).ok_or_else(|| format!("invalid time `{}`, expected e.g. `2024-01-01`, `30d` or `12h`", s))?
+    };
+    Ok(now().saturating_sub(ago.as_secs()))
+}
+
+/// Where the plays are kept.
+pub trait Storage {
+    fn record(&self, play: &Play) -> Result<(), PlayingError>;
+    /// The last `limit` plays, most recent first.
+    fn recent(&self, limit: usize) -> Result<Vec<Play>, PlayingError>;
+    /// The `limit` artists, tracks or players listened to the longest since `since`.
+    fn stats(&self, top: Top, since: u64, limit: usize) -> Result<Vec<Stat>, PlayingError>;
+}
+
+/// The file of the storage of `config`, none for the ones on a server.
+pub fn path(config: &HistoryConfig) -> Result<Option<PathBuf>, PlayingError> {
+    let name = match config.storage {
+        StorageKind::Sqlite => "history.db",
+        StorageKind::Jsonl => "history.jsonl",
+        StorageKind::Postgres => return Ok(None),
+    };
+    config.path.clone()
+        .or_else(|| crate::store::path(name))
+        .map(Some)
+        .ok_or_else(|| PlayingError::config("no data dir to keep the history in, set [history] path"))
+}
+
+/// The name of this machine, as the `postgres` storage records it.
+#[cfg(feature = "postgres")]
+fn host_name() -> String {
+    std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")).ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|h| h.trim().to_owned()))
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Opens the storage of `config`.
+pub fn open(config: &HistoryConfig) -> Result<Box<dyn Storage>, PlayingError> {
+    match config.storage {
+        StorageKind::Sqlite => Ok(Box::new(SqliteStorage::open(&path(config)?.unwrap_or_default())?)),
+        StorageKind::Jsonl => Ok(Box::new(JsonlStorage::open(&path(config)?.unwrap_or_default())?)),
+        #[cfg(feature = "postgres")]
+        StorageKind::Postgres => {
+            let url = config.url.as_deref().ok_or_else(|| PlayingError::config("the postgres storage needs a url in [history]"))?;
+            let host = config.host.clone().unwrap_or_else(host_name);
+            Ok(Box::new(postgres::PostgresStorage::connect(url, host)?))
+        }
+        #[cfg(not(feature = "postgres"))]
+        StorageKind::Postgres => Err(PlayingError::unsupported("the postgres storage needs playing built with the postgres feature")),
+    }
+}
+
+/// The stats of `plays`, for the storages that can't sum them up themselves.
+fn stats_of(plays: Vec<Play>, top: Top, since: u64, limit: usize) -> Vec<Stat> {
+    let mut sums: HashMap<Vec<Option<String>>, (u64, Duration)> = HashMap::new();
+    for p in plays.into_iter().filter(|p| p.started_at >= since) {
+        let key = top.columns().iter().map(|c| match *c {
+            "artist" => p.artist.clone(),
+            "title" => p.title.clone(),
+            _ => Some(p.player.clone()),
+        }).collect();
+        let sum = sums.entry(key).or_default();
+        sum.0 += 1;
+        sum.1 += p.listened;
+    }
+    let mut stats: Vec<Stat> = sums.into_iter().map(|(key, (plays, listened))| Stat { key, plays, listened }).collect();
+    stats.sort_by_key(|s| std::cmp::Reverse(s.listened));
+    stats.truncate(limit);
+    stats
+}
+
+/// A track as recorded once it's done playing.
+pub struct Play {
+    pub player: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub url: Option<String>,
+    pub length: Option<Duration>,
+    pub listened: Duration,
+    /// Unix time the track started playing at.
+    pub started_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// The track being listened to and for how long it has been played.
+struct Listening {
+    snapshot: Snapshot,
+    listened: Duration,
+    started_at: u64,
+}
+
+impl Listening {
+    fn play(self) -> Play {
+        let track = self.snapshot.track;
+        Play {
+            player: self.snapshot.player,
+            artist: track.artist().cloned(),
+            title: track.title,
+            album: track.album,
+            url: track.url,
+            length: track.length,
+            listened: self.listened,
+            started_at: self.started_at,
+        }
+    }
+}
+
+/// Records every track the current player plays, once it moves on to another one.
+fn record(config: &Config, kind: BackendKind, history: &dyn Storage) -> Result<bool, PlayingError> {
+    crate::shutdown::install();
+    let backends = backend::open(kind, config)?;
+    let ranking = config.ranking();
+    let DurationSpec(min_listened) = config.history.min_listened;
+    let mut current: Option<Listening> = None;
+    let mut last_tick = Instant::now();
+    loop {
+        let elapsed = last_tick.elapsed();
+        last_tick = Instant::now();
+        match follow::current(&backends, &ranking) {
+            Ok(next) => {
+                let same = matches!((&current, &next), (Some(c), Some(n)) if c.snapshot.same_track(n));
+                match (current.take(), next) {
+                    (Some(mut c), Some(n)) if same => {
+                        // only the time between two ticks that both saw it playing counts
+                        if c.snapshot.status == crate::Status::Playing && n.status == crate::Status::Playing {
+                            c.listened += elapsed;
+                        }
+                        c.snapshot = n;
+                        current = Some(c);
+                    }
+                    (done, next) => {
+                        if let Some(done) = done.filter(|d| d.listened >= min_listened) {
+                            if let Err(e) = history.record(&done.play()) {
+                                eprintln!("cannot record a play: {}", e.inner);
+                            }
+                        }
+                        current = next.map(|n| Listening {
+                            started_at: now().saturating_sub(n.position.unwrap_or_default().as_secs()),
+                            snapshot: n,
+                            listened: Duration::ZERO,
+                        });
+                    }
+                }
+            }
+            Err(e) => eprintln!("cannot read the current player: {}", e.inner),
+        }
+        if crate::shutdown::requested() {
+            // the play in progress would be lost otherwise
+            if let Some(c) = current.filter(|c| c.listened >= min_listened) {
+                history.record(&c.play())?;
+            }
+            return Ok(true)
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+fn recent(history: &dyn Storage, limit: usize, as_json: bool) -> Result<bool, PlayingError> {
+    let plays = history.recent(limit)?;
+    if as_json {
+        let plays: Vec<_> = plays.iter().map(|p| json!({
+            "player": p.player,
+            "title": p.title,
+            "artist": p.artist,
+            "album": p.album,
+            "url": p.url,
+            "length": p.length.map(|l| l.as_secs_f64()),
+            "listened": p.listened.as_secs_f64(),
+            "started_at": p.started_at,
+        })).collect();
+        println!("{}", json!(plays));
+        return Ok(true)
+    }
+    for p in &plays {
+        let started = chrono::DateTime::from_timestamp(p.started_at as i64, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!("{}  {} - {} ({}, {})",
+            started,
+            p.artist.as_deref().unwrap_or("Unknown"),
+            p.title.as_deref().unwrap_or("Unknown"),
+            format::duration(p.listened),
+            p.player);
+    }
+    Ok(!plays.is_empty())
+}
+
+fn stats(history: &dyn Storage, top: Top, since: u64, limit: usize, as_json: bool) -> Result<bool, PlayingError> {
+    let stats = history.stats(top, since, limit)?;
+    if as_json {
+        let stats: Vec<_> = stats.iter().map(|s| {
+            let mut stat = json!({ "plays": s.plays, "listened": s.listened.as_secs_f64() });
+            for (column, value) in top.columns().iter().zip(&s.key) {
+                stat[*column] = json!(value);
+            }
+            stat
+        }).collect();
+        println!("{}", json!(stats));
+        return Ok(true)
+    }
+    let names: Vec<String> = stats.iter()
+        .map(|s| s.key.iter().map(|k| k.as_deref().unwrap_or("Unknown")).collect::<Vec<_>>().join(" - "))
+        .collect();
+    let times: Vec<String> = stats.iter().map(|s| format::duration(s.listened)).collect();
+    let width = times.iter().map(String::len).max().unwrap_or_default();
+    for (i, (s, (name, time))) in stats.iter().zip(names.iter().zip(&times)).enumerate() {
+        println!("{:>3}. {:>width$}  {:>5} plays  {}", i + 1, time, s.plays, name);
+    }
+    Ok(!stats.is_empty())
+}
+
+pub fn run(config: &Config, kind: BackendKind, command: Option<HistoryCommand>, as_json: bool) -> Result<bool, PlayingError> {
+    let history = open(&config.history)?;
+    match command.unwrap_or(HistoryCommand::Recent { limit: 20 }) {
+        HistoryCommand::Record => record(config, kind, history.as_ref()),
+        HistoryCommand::Recent { limit } => recent(history.as_ref(), limit, as_json),
+        HistoryCommand::Stats { since, top, limit } => stats(history.as_ref(), top, since.unwrap_or_default(), limit, as_json),
+    }
+}
@@ -0,0 +1,86 @@
+use std::{cell::RefCell, time::Duration};
+use postgres::{Client, NoTls, Row};
+
+use super::{Play, Stat, Storage, Top};
+use crate::PlayingError;
+
+fn db_error(e: postgres::Error) -> PlayingError {
+    PlayingError::from(Box::new(e) as Box<dyn std::error::Error>)
+}
+
+fn seconds(s: f64) -> Duration {
+    Duration::try_from_secs_f64(s).unwrap_or_default()
+}
+
+fn play(row: &Row) -> Play {
+    Play {
+        player: row.get(0),
+        title: row.get(1),
+        artist: row.get(2),
+        album: row.get(3),
+        url: row.get(4),
+        length: row.get::<_, Option<f64>>(5).map(seconds),
+        listened: seconds(row.get(6)),
+        started_at: row.get::<_, i64>(7).max(0) as u64,
+    }
+}
+
+/// The plays on a PostgreSQL server, which several machines can record to, each under its host name.
+pub struct PostgresStorage {
+    client: RefCell<Client>,
+    host: String,
+}
+
+impl PostgresStorage {
+    pub fn connect(url: &str, host: String) -> Result<PostgresStorage, PlayingError> {
+        let mut client = Client::connect(url, NoTls).map_err(db_error)?;
+        client.batch_execute(
+            "CREATE TABLE IF NOT EXISTS plays (
+                id BIGSERIAL PRIMARY KEY,
+                host TEXT NOT NULL,
+                player TEXT NOT NULL,
+                title TEXT,
+                artist TEXT,
+                album TEXT,
+                url TEXT,
+                length DOUBLE PRECISION,
+                listened DOUBLE PRECISION NOT NULL,
+                started_at BIGINT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS plays_started_at ON plays (started_at);"
+        ).map_err(db_error)?;
+        Ok(PostgresStorage { client: RefCell::new(client), host })
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn record(&self, play: &Play) -> Result<(), PlayingError> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO plays (host, player, title, artist, album, url, length, listened, started_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            &[&self.host, &play.player, &play.title, &play.artist, &play.album, &play.url, &play.length.map(|l| l.as_secs_f64()), &play.listened.as_secs_f64(), &(play.started_at as i64)],
+        ).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn recent(&self, limit: usize) -> Result<Vec<Play>, PlayingError> {
+        let rows = self.client.borrow_mut().query(
+            "SELECT player, title, artist, album, url, length, listened, started_at FROM plays ORDER BY started_at DESC LIMIT $1",
+            &[&(limit as i64)],
+        ).map_err(db_error)?;
+        Ok(rows.iter().map(play).collect())
+    }
+
+    fn stats(&self, top: Top, since: u64, limit: usize) -> Result<Vec<Stat>, PlayingError> {
+        let columns = top.columns().join(", ");
+        let rows = self.client.borrow_mut().query(
+            &format!("SELECT {columns}, COUNT(*), SUM(listened) FROM plays WHERE started_at >= $1 GROUP BY {columns} ORDER BY SUM(listened) DESC LIMIT $2"),
+            &[&(since as i64), &(limit as i64)],
+        ).map_err(db_error)?;
+        let n = top.columns().len();
+        Ok(rows.iter().map(|row| Stat {
+            key: (0..n).map(|i| row.get(i)).collect(),
+            plays: row.get::<_, i64>(n).max(0) as u64,
+            listened: seconds(row.get(n + 1)),
+        }).collect())
+    }
+}
@@ -0,0 +1,88 @@
+use std::{path::Path, time::Duration};
+use rusqlite::{params, Connection, Row};
+
+use super::{Play, Stat, Storage, Top};
+use crate::PlayingError;
+
+pub fn db_error(e: rusqlite::Error) -> PlayingError {
+    PlayingError::from(Box::new(e) as Box<dyn std::error::Error>)
+}
+
+impl Play {
+    /// The play of a row of `player, title, artist, album, url, length, listened, started_at`.
+    pub fn from_row(row: &Row) -> rusqlite::Result<Play> {
+        Ok(Play {
+            player: row.get(0)?,
+            title: row.get(1)?,
+            artist: row.get(2)?,
+            album: row.get(3)?,
+            url: row.get(4)?,
+            length: row.get::<_, Option<f64>>(5)?.map(|l| Duration::from_secs_f64(l.max(0.0))),
+            listened: Duration::from_secs_f64(row.get::<_, f64>(6)?.max(0.0)),
+            started_at: row.get(7)?,
+        })
+    }
+}
+
+/// The plays in an SQLite database, the default storage.
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<SqliteStorage, PlayingError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(db_error)?;
+        // the write-ahead log keeps the database whole through crashes and power losses
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;").map_err(db_error)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS plays (
+                id INTEGER PRIMARY KEY,
+                player TEXT NOT NULL,
+                title TEXT,
+                artist TEXT,
+                album TEXT,
+                url TEXT,
+                length REAL,
+                listened REAL NOT NULL,
+                started_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS plays_started_at ON plays (started_at);"
+        ).map_err(db_error)?;
+        Ok(SqliteStorage { conn })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn record(&self, play: &Play) -> Result<(), PlayingError> {
+        self.conn.execute(
+            "INSERT INTO plays (player, title, artist, album, url, length, listened, started_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![play.player, play.title, play.artist, play.album, play.url, play.length.map(|l| l.as_secs_f64()), play.listened.as_secs_f64(), play.started_at],
+        ).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn recent(&self, limit: usize) -> Result<Vec<Play>, PlayingError> {
+        let mut statement = self.conn.prepare(
+            "SELECT player, title, artist, album, url, length, listened, started_at FROM plays ORDER BY started_at DESC LIMIT ?1"
+        ).map_err(db_error)?;
+        let plays = statement.query_map([limit as i64], Play::from_row).map_err(db_error)?;
+        plays.collect::<Result<_, _>>().map_err(db_error)
+    }
+
+    fn stats(&self, top: Top, since: u64, limit: usize) -> Result<Vec<Stat>, PlayingError> {
+        let columns = top.columns().join(", ");
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {columns}, COUNT(*), SUM(listened) FROM plays WHERE started_at >= ?1 GROUP BY {columns} ORDER BY SUM(listened) DESC LIMIT ?2"
+        )).map_err(db_error)?;
+        let n = top.columns().len();
+        let stats = statement.query_map(params![since, limit as i64], |row| Ok(Stat {
+            key: (0..n).map(|i| row.get(i)).collect::<Result<_, _>>()?,
+            plays: row.get(n)?,
+            listened: Duration::from_secs_f64(row.get::<_, f64>(n + 1)?.max(0.0)),
+        })).map_err(db_error)?;
+        stats.collect::<Result<_, _>>().map_err(db_error)
+    }
+}
@@ -0,0 +1,82 @@
+use std::{process::{Command, Stdio}, thread};
+use serde::Deserialize;
+
+use crate::follow::Snapshot;
+
+/// Commands run on playback events, from the `[hooks]` table of the config file:
+///
+/// ```toml
+/// [hooks]
+/// on_track_change = "notify-send \"$PLAYING_TITLE\" \"$PLAYING_ARTIST\""
+/// on_pause = "~/bin/lights dim"
+/// ```
+///
+/// They run through the shell with the track in `PLAYING_TITLE`, `PLAYING_ARTIST`,
+/// `PLAYING_ALBUM`, `PLAYING_URL`, `PLAYING_ART_URL`, `PLAYING_LENGTH` (seconds), as well as
+/// `PLAYING_PLAYER`, `PLAYING_STATUS` and `PLAYING_EVENT`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    on_track_change: Option<String>,
+    on_play: Option<String>,
+    on_pause: Option<String>,
+    on_stop: Option<String>,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.on_track_change.is_none() && self.on_play.is_none() && self.on_pause.is_none() && self.on_stop.is_none()
+    }
+
+    /// The name and command of the hook of `event`, as told by [`crate::follow::event`].
+    pub fn matching(&self, event: &str, snapshot: Option<&Snapshot>) -> Option<(&'static str, &str)> {
+        let (name, hook) = match (event, snapshot) {
+            ("track" | "player", Some(_)) => ("on_track_change", &self.on_track_change),
+            ("player", None) | ("stopped", _) => ("on_stop", &self.on_stop),
+            ("playing", _) => ("on_play", &self.on_play),
+            ("paused", _) => ("on_pause", &self.on_pause),
+            _ => return None,
+        };
+        Some((name, hook.as_deref()?))
+    }
+
+    /// Runs the hook of `event` without waiting for it.
+    pub fn run(&self, event: &str, snapshot: Option<&Snapshot>) {
+        let Some((_, hook)) = self.matching(event, snapshot) else { return };
+
+        #[cfg(windows)]
+        let mut command = {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(hook);
+            c
+        };
+        #[cfg(not(windows))]
+        let mut command = {
+            let mut c = Command::new("sh");
+            c.arg("-c").arg(hook);
+            c
+        };
+        command.stdin(Stdio::null()).env("PLAYING_EVENT", event);
+        if let Some(s) = snapshot {
+            let track = &s.track;
+            command.envs([
+                ("PLAYING_PLAYER", s.player.clone()),
+                ("PLAYING_STATUS", s.status.name().to_owned()),
+                ("PLAYING_TITLE", track.title.clone().unwrap_or_default()),
+                ("PLAYING_ARTIST", track.artist().cloned().unwrap_or_default()),
+                ("PLAYING_ALBUM", track.album.clone().unwrap_or_default()),
+                ("PLAYING_URL", track.url.clone().unwrap_or_default()),
+                ("PLAYING_ART_URL", track.art_url.clone().unwrap_or_default()),
+                ("PLAYING_LENGTH", track.plausible_length().map(|l| l.as_secs().to_string()).unwrap_or_default()),
+            ]);
+        } else {
+            command.env("PLAYING_STATUS", "stopped");
+        }
+        match command.spawn() {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+            }
+            Err(e) => eprintln!("cannot run the {} hook: {}", event, e),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{path::{Path, PathBuf}, sync::mpsc::{self, Sender}, time::Duration};
+use serde::Deserialize;
+
+use crate::{access::Source, backend::{self, BackendKind}, config::Config, PlayingError};
+
+/// Devices that can't be opened are tried again this often, e.g. until the gamepad is plugged in.
+const REOPEN_DELAY: Duration = Duration::from_secs(5);
+
+/// A control of a gamepad or MIDI controller mapped to a command, from the `[[input]]` tables of
+/// the config file:
+///
+/// ```toml
+/// [[input]]
+/// device = "/dev/input/by-id/usb-8BitDo_Pro_2-event-joystick"
+/// button = 304  # BTN_SOUTH
+/// command = "toggle"
+///
+/// [[input]]
+/// device = "/dev/snd/midiC1D0"
+/// cc = 7
+/// volume = true
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct InputRule {
+    /// evdev device (`/dev/input/...`) or raw MIDI device (`/dev/snd/midiC<card>D<device>`).
+    device: PathBuf,
+    /// Key code of a button, as `evtest` shows it.
+    button: Option<u16>,
+    /// MIDI note of a pad or key.
+    note: Option<u8>,
+    /// MIDI control change number of a knob, fader or button.
+    cc: Option<u8>,
+    /// MIDI channel, 1 to 16, any when unset.
+    channel: Option<u8>,
+    /// Operation run on press, e.g. `toggle` or `forward 10`.
+    command: Option<String>,
+    /// Set the current player's volume from the control's value instead.
+    #[serde(default)]
+    volume: bool,
+}
+
+/// Something a device sent.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Input {
+    Button(u16),
+    Note { channel: u8, note: u8 },
+    Control { channel: u8, cc: u8, value: u8 },
+}
+
+impl InputRule {
+    /// What the rule does with `input`: a command, or a volume from 0 to 1.
+    fn applies(&self, input: Input) -> Option<Result<&str, f64>> {
+        let channel_matches = |c: u8| self.channel.is_none_or(|ch| ch == c + 1);
+        let value = match input {
+            Input::Button(b) if self.button == Some(b) => None,
+            Input::Note { channel, note } if self.note == Some(note) && channel_matches(channel) => None,
+            Input::Control { channel, cc, value } if self.cc == Some(cc) && channel_matches(channel) => Some(value),
+            _ => return None,
+        };
+        match (self.volume, value, self.command.as_deref()) {
+            (true, Some(v), _) => Some(Err(f64::from(v) / 127.0)),
+            // buttons sending control changes send 0 on release
+            (false, Some(0), _) => None,
+            (false, _, Some(command)) => Some(Ok(command)),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the key presses of an evdev device.
+#[cfg(target_os = "linux")]
+fn read_evdev(file: &mut std::fs::File, device: &Path, tx: &Sender<(PathBuf, Input)>) -> std::io::Result<()> {
+    use std::io::Read;
+    const EV_KEY: u16 = 1;
+    let mut event = [0u8; std::mem::size_of::<libc::input_event>()];
+    loop {
+        file.read_exact(&mut event)?;
+        // SAFETY: input_event is plain data, and the kernel writes whole events
+        let e: libc::input_event = unsafe { std::ptr::read_unaligned(event.as_ptr().cast()) };
+        // 1 is a press, 0 a release and 2 a repeat
+        if e.type_ == EV_KEY && e.value == 1 && tx.send((device.to_owned(), Input::Button(e.code))).is_err() {
+            return Ok(())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_evdev(_: &mut std::fs::File, _: &Path, _: &Sender<(PathBuf, Input)>) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "evdev devices are only available on linux"))
+}
+
+/// Reads the notes and control changes of a raw MIDI device.
+fn read_midi(file: &mut std::fs::File, device: &Path, tx: &Sender<(PathBuf, Input)>) -> std::io::Result<()> {
+    use std::io::Read;
+    let mut status = 0u8;
+    let mut data = vec![];
+    let mut byte = [0u8];
+    loop {
+        file.read_exact(&mut byte)?;
+        match byte[0] {
+            // real-time messages may come in between the bytes of any other
+            0xf8..=0xff => continue,
+            b if b & 0x80 != 0 => {
+                status = b;
+                data.clear();
+                continue
+            }
+            b => data.push(b),
+        }
+        if data.len() < 2 {
+            continue
+        }
+        // later messages of the same kind may leave out the status byte
+        let (kind, channel) = (status & 0xf0, status & 0x0f);
+        let input = match kind {
+            0x90 if data[1] > 0 => Some(Input::Note { channel, note: data[0] }),
+            0xb0 => Some(Input::Control { channel, cc: data[0], value: data[1] }),
+            _ => None,
+        };
+        data.clear();
+        if let Some(input) = input {
+            if tx.send((device.to_owned(), input)).is_err() {
+                return Ok(())
+            }
+        }
+    }
+}
+
+/// Reads `device` until the daemon stops, reopening it whenever it goes away.
+fn watch(device: PathBuf, tx: Sender<(PathBuf, Input)>) {
+    let midi = device.file_name().is_some_and(|n| n.to_string_lossy().starts_with("midi"));
+    let mut reported = false;
+    loop {
+        let result = std::fs::File::open(&device).and_then(|mut file| {
+            eprintln!("reading {}", device.display());
+            reported = false;
+            if midi { read_midi(&mut file, &device, &tx) } else { read_evdev(&mut file, &device, &tx) }
+        });
+        match result {
+            Ok(_) => return,
+            Err(e) if !reported => {
+                eprintln!("cannot read {}: {}, retrying until it's there", device.display(), e);
+                reported = true;
+            }
+            Err(_) => {}
+        }
+        std::thread::sleep(REOPEN_DELAY);
+    }
+}
+
+/// Runs the commands the `[[input]]` rules map the buttons, pads and knobs of the devices to.
+pub async fn run(config: &Config, kind: BackendKind) -> Result<bool, PlayingError> {
+    if config.input.is_empty() {
+        return Err(PlayingError::config("input mapping needs [[input]] rules in the config"))
+    }
+    let (tx, inputs) = mpsc::channel();
+    let mut devices: Vec<&PathBuf> = config.input.iter().map(|r| &r.device).collect();
+    devices.sort();
+    devices.dedup();
+    for device in devices {
+        let (device, tx) = (device.clone(), tx.clone());
+        std::thread::spawn(move || watch(device, tx));
+    }
+    drop(tx);
+
+    let backends = backend::open(kind, config)?;
+    let ranking = config.ranking();
+    for (device, input) in inputs {
+        let Some(action) = config.input.iter().filter(|r| r.device == device).find_map(|r| r.applies(input)) else { continue };
+        let result = match action {
+            // boxed, as dispatching is what started this loop
+            Ok(command) => match crate::Remote::parse_line(command) {
+                Ok(action) => Box::pin(crate::dispatch(config, kind, Source::Input, action)).await.map(|_| ()),
+                Err(e) => Err(e),
+            },
+            Err(volume) => config.access.check_names(Source::Input, &["volume"], false)
+                .and_then(|_| backend::players(&backends))
+                .and_then(|players| match crate::current_player(&players, &ranking)? {
+                    Some(p) => p.set_volume(volume),
+                    None => Ok(()),
+                }),
+        };
+        if let Err(e) = result {
+            eprintln!("input {:?} of {} failed: {}: {}", input, device.display(), e.kind, e.inner);
+        }
+    }
+    Err(PlayingError::config("no input device could be read"))
+}
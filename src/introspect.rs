@@ -0,0 +1,45 @@
+use clap::{Arg, ArgAction, Command, ValueEnum};
+use serde_json::{json, Value};
+
+/// How `--dump-cli` prints the commands.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum DumpFormat {
+    Json,
+}
+
+fn arg(a: &Arg) -> Value {
+    let takes_value = !matches!(a.get_action(), ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count | ArgAction::Help | ArgAction::Version);
+    // flags parse `true` and `false`, which nobody passes them
+    let possible_values = if takes_value { a.get_possible_values() } else { vec![] };
+    json!({
+        "id": a.get_id().as_str(),
+        "long": a.get_long(),
+        "short": a.get_short().map(String::from),
+        "help": a.get_help().map(ToString::to_string),
+        "positional": a.is_positional(),
+        "required": a.is_required_set(),
+        "global": a.is_global_set(),
+        "takes_value": takes_value,
+        "multiple": matches!(a.get_action(), ArgAction::Append),
+        "default": a.get_default_values().iter().map(|v| v.to_string_lossy()).collect::<Vec<_>>(),
+        "possible_values": possible_values.iter().filter(|v| !v.is_hide_set()).map(|v| v.get_name()).collect::<Vec<_>>(),
+    })
+}
+
+/// `command` and its subcommands, leaving out the hidden ones which other commands start.
+fn command(command: &Command) -> Value {
+    json!({
+        "name": command.get_name(),
+        "about": command.get_about().map(ToString::to_string),
+        "args": command.get_arguments().filter(|a| !a.is_hide_set()).map(arg).collect::<Vec<_>>(),
+        "subcommands": command.get_subcommands().filter(|c| !c.is_hide_set()).map(self::command).collect::<Vec<_>>(),
+    })
+}
+
+/// Prints the whole tree of commands and their arguments, for GUIs and launcher plugins to
+/// build their interface from.
+pub fn dump(cli: &Command, format: DumpFormat) {
+    match format {
+        DumpFormat::Json => println!("{}", command(cli)),
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::BTreeMap, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::MediaPlayer, config::Config, format};
+
+/// A player seen running that no ranking or group names, so no command acts on it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Learned {
+    pub desktop_entry: Option<String>,
+    pub bus_name: Option<String>,
+    /// Unix time it was first seen at.
+    pub first_seen: u64,
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("learned-players.json")
+}
+
+/// The learned players, by identity.
+pub fn load() -> BTreeMap<String, Learned> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save(learned: &BTreeMap<String, Learned>) {
+    let Some(path) = path() else { return };
+    if let Err(e) = crate::store::write_atomic(&path, serde_json::to_string_pretty(learned).unwrap_or_default().as_bytes()) {
+        eprintln!("cannot save the learned players to {}: {}", path.display(), e);
+    }
+}
+
+/// The entry of the ranking that was likely meant for `identity`, differing in case, spacing or
+/// by being its desktop entry, e.g. `firefox` for `Mozilla Firefox`.
+pub fn near_miss<'a>(identity: &str, desktop_entry: Option<&str>, known: &'a [String]) -> Option<&'a str> {
+    let squash = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>();
+    let words: Vec<String> = identity.split_whitespace().map(squash).collect();
+    let desktop_entry = desktop_entry.map(|d| squash(d.rsplit('.').next().unwrap_or(d)));
+    known.iter().map(String::as_str).find(|k| {
+        let k = squash(k);
+        !k.is_empty() && (k == squash(identity) || desktop_entry.as_ref() == Some(&k) || words.contains(&k))
+    })
+}
+
+/// Records the players of `players` that nothing in the config names, telling about each one
+/// the first time it shows up.
+pub fn observe(players: &[Box<dyn MediaPlayer>], config: &Config) {
+    // a narrowed down ranking leaves out players that are known
+    if config.player.is_some() || config.group.is_some() {
+        return
+    }
+    let known = config.known_players();
+    let unknown: Vec<&Box<dyn MediaPlayer>> = players.iter().filter(|p| !known.iter().any(|k| format::selects(k, p.identity()))).collect();
+    if unknown.is_empty() {
+        return
+    }
+    let mut learned = load();
+    let before = learned.clone();
+    for p in unknown {
+        let desktop_entry = p.desktop_entry();
+        let bus_name = p.bus_name().map(str::to_owned);
+        match learned.get_mut(p.identity()) {
+            Some(l) => {
+                l.desktop_entry = desktop_entry;
+                l.bus_name = bus_name;
+            }
+            None => {
+                match near_miss(p.identity(), desktop_entry.as_deref(), &known) {
+                    Some(k) => eprintln!("player `{}` is not in the ranking, which has `{}`: identities have to match exactly", p.identity(), k),
+                    None => eprintln!("player `{}` is not in the ranking, add it to `ranking` in the config to control it", p.identity()),
+                }
+                let first_seen = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+                learned.insert(p.identity().to_owned(), Learned { desktop_entry, bus_name, first_seen });
+            }
+        }
+    }
+    if learned != before {
+        save(&learned);
+    }
+}
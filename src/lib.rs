@@ -0,0 +1,1438 @@
+//! playing.rs as a library: the command line the `playing-rs` binary runs, and [`api`] for
+//! programs linking it instead, e.g. through the C API of the `playing-ffi` crate.
+
+use std::{fmt::{Debug, Display}, net::SocketAddr, path::PathBuf, process::exit, time::Duration};
+#[cfg(all(unix, not(target_os = "macos")))]
+use mpris::DBusError;
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+
+mod access;
+mod analytics;
+pub mod api;
+mod arbiter;
+mod backend;
+mod capabilities;
+mod clipboard;
+mod config;
+mod doctor;
+mod download;
+mod events;
+mod favorites;
+mod follow;
+mod fixture;
+mod format;
+mod history;
+mod hooks;
+mod input;
+mod introspect;
+mod learned;
+mod lock;
+mod migrate;
+mod plan;
+mod mqtt;
+mod notify;
+mod preset;
+mod recent;
+mod refresh;
+mod repeat;
+mod scrobble;
+mod seeked;
+mod selection;
+mod serve;
+mod sink;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod service;
+mod shutdown;
+mod skip;
+mod sponsorblock;
+mod spotify;
+mod status;
+mod stdin;
+mod store;
+mod timer;
+mod timing;
+mod wake;
+
+use access::Source;
+use backend::{BackendKind, LoopMode, MediaPlayer, Status};
+use config::Config;
+use format::DurationSpec;
+use preset::SeekTarget;
+use selection::SelectionPolicy;
+use status::StatusOptions;
+
+#[derive(Debug)]
+enum PlayingErrorKind {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    DBus,
+    IO,
+    Spotifav,
+    Config,
+    Denied,
+    Mpd,
+    #[cfg(unix)]
+    Mpv,
+    Unsupported,
+    #[cfg(windows)]
+    Smtc,
+    #[cfg(target_os = "macos")]
+    Osascript,
+    Scrobble,
+    Mqtt,
+    BadCommand,
+    Favorite,
+}
+
+impl Display for PlayingErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+pub struct PlayingError {
+    kind: PlayingErrorKind,
+    inner: Box<dyn std::error::Error>,
+    code: i32,
+}
+
+impl Display for PlayingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.inner)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl From<DBusError> for PlayingError {
+    fn from(value: DBusError) -> Self {
+        PlayingError { kind: PlayingErrorKind::DBus, code: 2, inner: Box::new(value) }
+    }
+}
+
+impl From<std::io::Error> for PlayingError {
+    fn from(value: std::io::Error) -> Self {
+        PlayingError { kind: PlayingErrorKind::IO, code: 3, inner: Box::new(value) }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for PlayingError {
+    fn from(value: Box<dyn std::error::Error>) -> Self {
+        PlayingError { kind: PlayingErrorKind::IO, code: 4, inner: value }
+    }
+}
+
+impl PlayingError {
+    /// The code the binary exits with on this error.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    fn from_spotifav(e: Box<dyn std::error::Error>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Spotifav, code: 5, inner: e }
+    }
+
+    fn config(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Config, code: 6, inner: e.into() }
+    }
+
+    fn denied(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Denied, code: 7, inner: e.into() }
+    }
+
+    fn mpd(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Mpd, code: 9, inner: e.into() }
+    }
+
+    #[cfg(unix)]
+    fn mpv(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Mpv, code: 10, inner: e.into() }
+    }
+
+    fn unsupported(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Unsupported, code: 11, inner: e.into() }
+    }
+
+    #[cfg(windows)]
+    fn smtc(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Smtc, code: 12, inner: e.into() }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn osascript(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Osascript, code: 13, inner: e.into() }
+    }
+
+    fn scrobble(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Scrobble, code: 14, inner: e.into() }
+    }
+
+    fn mqtt(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Mqtt, code: 15, inner: e.into() }
+    }
+
+    fn bad_command(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::BadCommand, code: 16, inner: e.into() }
+    }
+
+    fn favorite(e: impl Into<Box<dyn std::error::Error>>) -> Self {
+        PlayingError { kind: PlayingErrorKind::Favorite, code: 17, inner: e.into() }
+    }
+}
+
+/// Runs the command line of the arguments of the process, exiting with its code.
+pub async fn cli() {
+    let cmd = match Cmd::try_parse() {
+        Ok(cmd) => cmd,
+        // --dump-cli is the only thing that goes without a command
+        Err(e) if e.kind() == clap::error::ErrorKind::MissingSubcommand => {
+            let matches = Cmd::command().subcommand_required(false).get_matches();
+            match matches.get_one::<introspect::DumpFormat>("dump_cli") {
+                Some(format) => {
+                    introspect::dump(&Cmd::command(), *format);
+                    return
+                }
+                None => e.exit(),
+            }
+        }
+        Err(e) => e.exit(),
+    };
+    if let Some(format) = cmd.dump_cli {
+        introspect::dump(&Cmd::command(), format);
+        return
+    }
+    if cmd.trace_timing {
+        timing::enable();
+    }
+
+    let result = run(cmd).await;
+    timing::report();
+    match result {
+        Ok(code) => exit(code),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            exit(e.code);
+        }
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,PartialOrd,Ord,ValueEnum,Deserialize,Default,Debug)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    #[default]
+    Single,
+    Multiple,
+}
+
+#[derive(Subcommand, Debug)]
+enum Operation {
+    Toggle,
+    Play {
+        /// Pause every other player that is playing
+        #[arg(long)]
+        exclusive: bool,
+    },
+    Pause,
+    Next,
+    Previous {
+        /// Restart the track instead, once it has played for a while; see `[previous]` in the config
+        #[arg(long)]
+        smart: bool,
+    },
+    Rewind {
+        #[arg(default_value = "1", value_parser = seconds_arg)]
+        seconds: f32,
+    },
+    Forward {
+        #[arg(default_value = "1", value_parser = seconds_arg)]
+        seconds: f32,
+    },
+    SeekRelative {
+        #[arg(value_parser = offset_arg)]
+        seconds: f32,
+    },
+    Seek {
+        #[arg(value_parser = seconds_arg)]
+        seconds: f32,
+    },
+    /// Moves by `offset` chapters (mpv IPC only)
+    Chapter {
+        #[arg(default_value = "1", allow_hyphen_values = true)]
+        offset: i64,
+    },
+    /// Steps a single frame (mpv IPC only)
+    FrameStep {
+        #[arg(action = ArgAction::SetTrue, long)]
+        back: bool,
+    },
+    /// Jumps to the 1-based playlist position (mpv IPC only)
+    PlaylistJump {
+        position: usize,
+    },
+    /// Sets the loop's A point, then the B point, then clears it (mpv IPC only)
+    AbLoop,
+    /// Seeks as the preset `name` of the config says, e.g. `intro-skip = "85s"`
+    SeekPreset {
+        name: String,
+    },
+    /// Rewinds by `seconds` and resumes playback if paused
+    Replay {
+        #[arg(default_value = "10", value_parser = seconds_arg)]
+        seconds: f32,
+    },
+    /// Loops the current track, going back to the previous loop mode after `times` more plays
+    RepeatThis {
+        #[arg(default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        times: u32,
+    },
+    /// Pauses the player once the current track is over, or keeps it playing if that was asked
+    /// already
+    StopAfterCurrent {
+        /// Wait for the track to end instead of watching it in the background
+        #[arg(long)]
+        wait: bool,
+    },
+}
+
+impl Operation {
+    /// Whether this operation moves the position within the track.
+    fn seeks(&self) -> bool {
+        matches!(self, Operation::Rewind { .. } | Operation::Forward { .. } | Operation::SeekRelative { .. } | Operation::Seek { .. } | Operation::Replay { .. } | Operation::SeekPreset { .. })
+    }
+
+    /// Names this operation answers to in access rules, most specific first.
+    fn permission_names(&self) -> &'static [&'static str] {
+        match self {
+            Operation::Toggle => &["toggle"],
+            Operation::Play { .. } => &["play"],
+            Operation::Pause => &["pause"],
+            Operation::Next => &["next"],
+            Operation::Previous { .. } => &["previous"],
+            Operation::Rewind { .. } => &["rewind", "seek"],
+            Operation::Forward { .. } => &["forward", "seek"],
+            Operation::SeekRelative { .. } => &["seek-relative", "seek"],
+            Operation::Seek { .. } => &["seek"],
+            Operation::Chapter { .. } => &["chapter", "seek"],
+            Operation::FrameStep { .. } => &["frame-step", "seek"],
+            Operation::PlaylistJump { .. } => &["playlist-jump"],
+            Operation::AbLoop => &["ab-loop"],
+            Operation::SeekPreset { .. } => &["seek-preset", "seek"],
+            Operation::Replay { .. } => &["replay", "rewind", "seek"],
+            Operation::RepeatThis { .. } => &["repeat-this", "loop"],
+            Operation::StopAfterCurrent { .. } => &["stop-after-current", "pause"],
+        }
+    }
+}
+
+/// Conditions a player has to meet for operations to act on it.
+#[derive(Args, Default, Debug)]
+struct Guards {
+    /// Only act on a player that is playing
+    #[arg(long, global = true, conflicts_with = "if_paused")]
+    if_playing: bool,
+    /// Only act on a player that is paused
+    #[arg(long, global = true)]
+    if_paused: bool,
+    /// Only act on the players this selects as `--player` would, e.g. `Spotify` or `Brave*`
+    #[arg(long, global = true)]
+    if_player: Option<String>,
+}
+
+impl Guards {
+    fn is_set(&self) -> bool {
+        self.if_playing || self.if_paused || self.if_player.is_some()
+    }
+
+    fn allow(&self, p: &dyn MediaPlayer) -> Result<bool, PlayingError> {
+        if self.if_player.as_ref().is_some_and(|id| !picks(id, p)) {
+            return Ok(false)
+        }
+        Ok(match (self.if_playing, self.if_paused) {
+            (false, false) => true,
+            (playing, _) => p.status()? == if playing { Status::Playing } else { Status::Paused },
+        })
+    }
+}
+
+// parsed once per run, the options of `status` can take the room they need
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand,Debug)]
+enum Action {
+    #[command(subcommand, alias = "op")]
+    Operation(Operation),
+    Player,
+    /// Lists the running players, numbered for `--player`, with their bus names
+    Players,
+    #[command(after_help = status::QUIET_HELP)]
+    Status { 
+        #[arg(action = ArgAction::SetTrue, long)]
+        no_icon: bool,
+        #[arg(default_value = "1", long)]
+        spaces_after_icon: usize,
+        /// Print nothing, only exit with the code of the playback state; with --follow, print an
+        /// empty line instead of "No media"
+        #[arg(action = ArgAction::SetTrue, short)]
+        quiet: bool,
+        /// As -q, but print the playback state: playing, paused, stopped or none
+        #[arg(long, conflicts_with = "follow")]
+        quiet_verbose: bool,
+        /// Format of the line, e.g. "{artist} - {title}"; placeholders: icon, title, album,
+        /// artist (the album artist, else the track's), album_artist, track_artist, player, name, status, url, position, length, remaining, live, uptime, event,
+        /// track_number, playlist_position, playlist_length, ending, state_icon, favorite
+        #[arg(long)]
+        format: Option<String>,
+        /// Format of the line while a livestream plays
+        #[arg(long)]
+        live_format: Option<String>,
+        /// Keep running and print the line whenever it changes, right away after seeks, running
+        /// the hooks of the config
+        #[arg(action = ArgAction::SetTrue, short, long)]
+        follow: bool,
+        /// How to wrap the line for the bar showing it
+        #[arg(value_enum, long, default_value = "plain")]
+        output: status::Output,
+        /// Time left from which the track is ending: `{ending}` is set, the waybar class is
+        /// `ending` and polybar colors the line
+        #[arg(long, value_parser = duration_arg)]
+        ending_within: Option<Duration>,
+        /// Rewrite this file with the line instead of printing it, e.g. for OBS text sources;
+        /// a named pipe gets every line instead, dropped while nothing reads it
+        #[arg(long, requires = "follow")]
+        write_to: Option<PathBuf>,
+        /// Rewrite this file with the path of the cover art
+        #[arg(long, requires = "write_to")]
+        art_to: Option<PathBuf>,
+        /// Also show a desktop notification of every track played
+        #[arg(long, requires = "follow")]
+        notify: bool,
+        /// Listen on this socket for `refresh`, which polls the players and prints the line again
+        /// as SIGUSR1 does, e.g. after the bar restarted
+        #[arg(long, requires = "follow")]
+        control_socket: Option<PathBuf>,
+        /// Columns the line is cut to, wide characters such as CJK counting twice
+        #[arg(long)]
+        max_length: Option<usize>,
+        /// Columns the title is cut to, before the line is
+        #[arg(long)]
+        max_title: Option<usize>,
+        /// Columns the album is cut to, before the line is
+        #[arg(long)]
+        max_album: Option<usize>,
+        /// Columns the artist is cut to, before the line is
+        #[arg(long)]
+        max_artist: Option<usize>,
+        /// What goes between the artists of a track, ", " by default
+        #[arg(long)]
+        artist_separator: Option<String>,
+        /// Artists shown before the others are only counted, as in "A, B & 2 more"
+        #[arg(long)]
+        max_artists: Option<usize>,
+        /// What ends the line and the fields that were cut, "..." by default
+        #[arg(long)]
+        ellipsis: Option<String>,
+        /// Show the first paused player of the ranking when none is playing, with a pause icon
+        /// and the waybar class `paused`
+        #[arg(long)]
+        include_paused: bool,
+        /// Print a styled card of several lines, with a progress bar, for terminals
+        #[arg(long, conflicts_with_all = ["follow", "quiet", "format", "output", "escape"])]
+        pretty: bool,
+        /// Print nothing when the line is the one this same command printed last, for bars that
+        /// redraw whatever they read
+        #[arg(long, conflicts_with_all = ["follow", "quiet"])]
+        changed_only: bool,
+        /// Markup to escape the line for, so that e.g. an `&` in a title doesn't break the bar's
+        #[arg(value_enum, long)]
+        escape: Option<status::Escape>,
+        /// Scroll lines wider than the maximum length by a character every STEP instead of
+        /// cutting them, e.g. for bar modules of a fixed width
+        #[arg(long, requires = "follow", value_name = "STEP", value_parser = duration_arg, num_args = 0..=1, default_missing_value = "0.3s")]
+        scroll: Option<Duration>,
+    },
+    Favorite {
+        #[command(subcommand)]
+        command: Option<favorites::FavoriteCommand>,
+        #[arg(default_value = "false", short, long)]
+        poll: bool,
+        #[arg(long)]
+        always: bool,
+        /// Only tell whether the track is saved already, exiting with 1 when it's not
+        #[arg(long)]
+        check: bool,
+        /// Reverse the last toggle, wherever its track plays now
+        #[arg(long, conflicts_with_all = ["check", "always", "poll"])]
+        undo: bool,
+        /// Also show the outcome as a desktop notification
+        #[arg(long)]
+        notify: bool,
+    },
+    /// Adds the track Spotify plays to one of the user's playlists
+    Playlist {
+        #[command(subcommand)]
+        command: spotify::PlaylistCommand,
+    },
+    /// Lists the Spotify Connect devices, moves playback between them or queues tracks
+    Spotify {
+        #[command(subcommand)]
+        command: spotify::SpotifyCommand,
+    },
+    Url {
+        /// Draw the URL as a QR code, e.g. to go on listening on the phone
+        #[arg(long)]
+        qr: bool,
+        /// Open the URL in the browser instead, `browser` of the config or the desktop's default
+        #[arg(long, conflicts_with = "qr")]
+        open: bool,
+    },
+    /// Copies the URL, the title or the status line of the current track to the clipboard
+    Copy {
+        #[arg(value_enum)]
+        what: clipboard::Field,
+        /// Format of the status line copied as `formatted`, e.g. "{artist} - {title}"
+        #[arg(long)]
+        format: Option<String>,
+    },
+    /// Downloads what the current player plays from the web with yt-dlp, as set in `[download]`
+    Download {
+        /// Directory to download to, replacing the one of the config
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Output template of yt-dlp, e.g. "%(uploader)s - %(title)s.%(ext)s"
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Pauses every player that is playing, ranked or not
+    PauseAll,
+    /// Keeps pausing the other players when one starts playing, resuming them once it stops
+    AutoPause,
+    /// Keeps pausing the players of `[sink]` in the config when the audio output changes, e.g. when
+    /// headphones are unplugged
+    PauseOnUnplug,
+    /// Records the state of every player to a fixture file for `replay`, until interrupted
+    Record {
+        file: PathBuf,
+    },
+    /// Prints what the status line, hooks and scrobbler make of a fixture written by `record`,
+    /// without any player
+    Replay {
+        file: PathBuf,
+    },
+    /// Notifies of every track played, with buttons to skip it or add it to the Spotify favorites
+    Notifications,
+    /// Shows a desktop notification of the current track, replacing the last one
+    Notify,
+    /// Keeps pausing the players of `[lock]` in the config while the session is locked
+    PauseOnLock,
+    /// Runs the operations that gamepad buttons and MIDI pads and knobs are mapped to by the
+    /// `[[input]]` rules of the config
+    Input,
+    /// Runs the commands read from stdin, one per line (e.g. `toggle`, `seek 30` or
+    /// `status --format "{title}"`), sharing one connection to the players
+    Stdin,
+    /// Diagnoses the session bus, players, config and Spotify setup
+    Doctor,
+    /// Tells which backends, subsystems and output formats this build supports and can reach
+    Capabilities,
+    /// Prints a config equivalent to another tool's setup
+    Migrate {
+        #[arg(value_enum, long)]
+        from: migrate::MigrateFrom,
+        /// Files with invocations of the tool, defaults to the usual WM and bar configs
+        files: Vec<PathBuf>,
+    },
+    /// Sends what the active player plays to Last.fm and ListenBrainz, retrying failed scrobbles later
+    Scrobble {
+        /// Keep running and scrobble every track played for half its length or four minutes
+        #[arg(action = ArgAction::SetTrue, long)]
+        daemon: bool,
+    },
+    /// Publishes the current player's state and events to an MQTT broker, see `[mqtt]` in the config
+    Mqtt,
+    /// Keeps skipping the intros, outros and chapters that the `[[skip]]` rules of the config name,
+    /// and YouTube's sponsor segments with `[sponsorblock]`
+    Skip,
+    /// Serves the players' state and operations over HTTP, e.g. `POST /toggle` or `GET /status`,
+    /// and a remote page at `/ui`
+    Serve {
+        /// Address to listen on, use 0.0.0.0:<port> to reach it from the LAN
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        http: SocketAddr,
+    },
+    /// Serves org.playing.Manager on the session bus, with Toggle, Next, Status, ... methods
+    Dbus,
+    /// Prints every event of every player as a line of JSON, e.g. for jq or eww's deflisten
+    Events,
+    /// Lists the tracks played recently or sums up listening stats, from the plays `history record`
+    /// keeps in the `[history]` database
+    History {
+        #[command(subcommand)]
+        command: Option<history::HistoryCommand>,
+    },
+    /// Checks the history database and the other files kept in the data dir, or repairs them
+    Db {
+        #[command(subcommand)]
+        command: Option<store::DbCommand>,
+    },
+    /// Starts playing at the given time, raising the volume gradually
+    Wake {
+        /// Local time to wake up at, e.g. 07:00
+        #[arg(long, value_parser = wake::parse_time)]
+        at: chrono::NaiveTime,
+        /// File, URL or playlist to play, instead of resuming what the player had
+        #[arg(long)]
+        playlist: Option<String>,
+        /// Time the volume takes to get from silence to its previous level
+        #[arg(long, default_value = "1m", value_parser = duration_arg)]
+        fade: Duration,
+        /// Identity of the player, defaults to the first running one of the ranking
+        #[arg(long)]
+        player: Option<String>,
+    },
+    /// Pauses the playing player after some time or once its track ends, e.g. to fall asleep to a
+    /// podcast
+    SleepTimer {
+        /// Time to pause after, e.g. 30m
+        #[arg(value_parser = duration_arg, required_unless_present_any = ["end_of_track", "cancel", "status"], conflicts_with_all = ["end_of_track", "cancel", "status"])]
+        after: Option<Duration>,
+        /// Pause once the current track ends instead
+        #[arg(long, conflicts_with_all = ["cancel", "status"])]
+        end_of_track: bool,
+        /// Stop the player instead of pausing it
+        #[arg(long, conflicts_with_all = ["cancel", "status"])]
+        stop: bool,
+        /// Cancel the running timer
+        #[arg(long, conflicts_with = "status")]
+        cancel: bool,
+        /// Tell what the running timer will do and when
+        #[arg(long)]
+        status: bool,
+    },
+    /// Suggests a better ranking from how often its picks were overridden, see `[analytics]`
+    Tune,
+    /// Puts the loop mode back once `op repeat-this` is done, started by it
+    #[command(hide = true)]
+    RepeatWatch {
+        player: String,
+        times: u32,
+        #[arg(value_enum)]
+        restore: LoopMode,
+    },
+    /// Pauses the player once the timer of `sleep-timer` is up, started by it
+    #[command(hide = true)]
+    SleepTimerWatch {
+        identity: String,
+        /// Unix time to pause at, else at the end of the track
+        #[arg(long)]
+        until: Option<u64>,
+        #[arg(long)]
+        stop: bool,
+    },
+}
+
+impl Action {
+    fn permission_names(&self) -> &'static [&'static str] {
+        match self {
+            Action::Operation(op) => op.permission_names(),
+            Action::Player => &["player"],
+            Action::Players => &["players", "player"],
+            Action::Status { .. } => &["status"],
+            Action::Favorite { .. } => &["favorite"],
+            Action::Playlist { .. } => &["playlist"],
+            Action::Spotify { command: spotify::SpotifyCommand::Devices } => &["spotify-devices", "player"],
+            Action::Spotify { command: spotify::SpotifyCommand::Transfer { .. } } => &["spotify-transfer"],
+            Action::Spotify { command: spotify::SpotifyCommand::Queue { .. } | spotify::SpotifyCommand::Radio { .. } } => &["spotify-queue"],
+            Action::Url { .. } => &["url"],
+            Action::Copy { .. } => &["copy", "status"],
+            Action::Download { .. } => &["download", "status"],
+            Action::PauseAll => &["pause-all", "pause"],
+            Action::AutoPause => &["auto-pause", "pause", "play"],
+            Action::Input => &["input"],
+            Action::Stdin => &["stdin"],
+            Action::PauseOnLock => &["pause-on-lock", "pause"],
+            Action::Notifications => &["notifications", "status"],
+            Action::Notify => &["notify", "status"],
+            Action::Record { .. } => &["record", "status"],
+            Action::Replay { .. } => &["replay"],
+            Action::PauseOnUnplug => &["pause-on-unplug", "pause"],
+            Action::Doctor => &["doctor"],
+            Action::Capabilities => &["capabilities"],
+            Action::Migrate { .. } => &["migrate"],
+            Action::Scrobble { .. } => &["scrobble"],
+            Action::Mqtt => &["mqtt"],
+            Action::Skip => &["skip", "seek"],
+            Action::Serve { .. } => &["serve"],
+            Action::RepeatWatch { .. } => &["repeat-this", "loop"],
+            Action::Wake { .. } => &["wake", "play"],
+            Action::Events => &["events", "status"],
+            Action::Dbus => &["dbus"],
+            Action::History { .. } => &["history"],
+            Action::Db { .. } => &["db"],
+            Action::Tune => &["tune"],
+            Action::SleepTimer { .. } | Action::SleepTimerWatch { .. } => &["sleep-timer", "pause"],
+        }
+    }
+
+    /// Whether the action only reads player state.
+    fn is_read_only(&self) -> bool {
+        matches!(self, Action::Player | Action::Players | Action::Status { .. } | Action::Url { .. } | Action::Copy { .. } | Action::Doctor | Action::Capabilities | Action::Migrate { .. } | Action::Scrobble { .. } | Action::Mqtt | Action::Serve { .. } | Action::Events | Action::Dbus | Action::History { .. } | Action::Tune | Action::Notify | Action::Record { .. } | Action::Replay { .. } | Action::Db { command: None | Some(store::DbCommand::Check) } | Action::Favorite { check: true, .. } | Action::Favorite { command: Some(_), .. } | Action::Spotify { command: spotify::SpotifyCommand::Devices })
+    }
+}
+
+#[derive(Parser,Debug)]
+#[command(
+    name = "playing.rs",
+    about = "Manage your running multimedia players using mpris (or mpd, windows' media sessions, macos' Music and Spotify)",
+    version = env!("CARGO_PKG_VERSION"),
+    author = "topongo"
+)]
+struct Cmd {
+    /// Run operations on the first player of the ranking only, or on every one; defaults to the
+    /// config's `mode`, else single
+    #[arg(value_enum,short,long)]
+    mode: Option<Mode>,
+    /// Run single operations on the first player of the ranking even when one below it is the
+    /// one playing, as `--selection static-ranking`
+    #[arg(long, conflicts_with = "selection")]
+    strict_ranking: bool,
+    /// How the player is picked among those of the ranking, over `[selection]` of the config
+    #[arg(value_enum, long, global = true)]
+    selection: Option<SelectionPolicy>,
+    /// Only consider the players of this group of the config, e.g. `video`
+    #[arg(long, global = true)]
+    group: Option<String>,
+    /// Only consider this player, or `last-active` for the one that last changed playback state;
+    /// also a pattern of identities, a full bus name or a number listed by `players`
+    #[arg(long, global = true)]
+    player: Option<String>,
+    #[command(flatten)]
+    guards: Guards,
+    /// Print which players an operation would act on and why, without running it
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Print JSON instead of text: the plan of --dry-run, the plays of `history`, the tracks of
+    /// `favorite list`, the report of `capabilities`
+    #[arg(long, global = true)]
+    json: bool,
+    /// Path of the config file, defaults to $XDG_CONFIG_HOME/playing/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[arg(value_enum, long, global = true, default_value = "auto")]
+    backend: BackendKind,
+    /// IPC socket of mpv (`--input-ipc-server`), enabling its extended controls
+    #[arg(long, global = true)]
+    mpv_socket: Option<PathBuf>,
+    /// Print how long each stage took (connecting, listing the players, matching the ranking,
+    /// reading metadata, rendering), and their percentiles every minute in the daemons
+    #[arg(long, global = true)]
+    trace_timing: bool,
+    /// Read the players from the last line of a fixture written by `record` instead of the
+    /// backends, e.g. to see what a state prints
+    #[arg(long, global = true)]
+    fixture: Option<PathBuf>,
+    /// Print what this version printed where the default output changed since, e.g. `0.1`
+    #[arg(value_enum, long, global = true)]
+    compat: Option<status::Compat>,
+    /// Icons of the players, for terminals without a patched font (nerd by default)
+    #[arg(value_enum, long, global = true)]
+    icons: Option<status::IconSet>,
+    /// Print every command and argument, e.g. as JSON for GUIs to build their interface from
+    #[arg(value_enum, long, hide = true, exclusive = true)]
+    dump_cli: Option<introspect::DumpFormat>,
+    #[command(subcommand)]
+    action: Action,
+}
+
+/// Seconds of the seeking operations, which remote sources send too: `nan`, `inf` or `1e30` parse
+/// as numbers, but aren't any time within a track.
+fn offset_arg(s: &str) -> Result<f32, String> {
+    s.parse::<f32>().ok()
+        .filter(|seconds| offset(*seconds).is_ok())
+        .ok_or_else(|| format!("invalid number of seconds `{}`", s))
+}
+
+fn seconds_arg(s: &str) -> Result<f32, String> {
+    match offset_arg(s)? {
+        seconds if seconds < 0.0 => Err(format!("invalid number of seconds `{}`, expected a positive one", s)),
+        seconds => Ok(seconds),
+    }
+}
+
+fn duration_arg(s: &str) -> Result<Duration, String> {
+    format::parse_duration(s).ok_or_else(|| format!("invalid duration `{}`, expected e.g. `90s`, `5m` or `1:30`", s))
+}
+
+/// `text` as a QR code of half blocks, two modules per line, light on the dark terminal.
+fn qr_code(text: &str) -> Result<String, PlayingError> {
+    use qrcode::{render::unicode::Dense1x2, QrCode};
+
+    let code = QrCode::new(text).map_err(|e| PlayingError::unsupported(format!("cannot draw a QR code: {}", e)))?;
+    // scanners expect dark modules on light, which the foreground color is on dark terminals
+    Ok(code.render::<Dense1x2>().dark_color(Dense1x2::Light).light_color(Dense1x2::Dark).build())
+}
+
+/// Opens `url` with `browser`, or with the desktop's default handler.
+fn open_url(browser: Option<&str>, url: &str) -> Result<(), PlayingError> {
+    let mut command = match browser {
+        Some(b) => std::process::Command::new(b),
+        None if cfg!(windows) => {
+            // the empty title keeps `start` from taking the URL for one
+            let mut c = std::process::Command::new("cmd");
+            c.args(["/C", "start", ""]);
+            c
+        }
+        None if cfg!(target_os = "macos") => std::process::Command::new("open"),
+        None => std::process::Command::new("xdg-open"),
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command.arg(url).status().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => PlayingError::unsupported(format!("{} is not installed", program)),
+        _ => e.into(),
+    })?;
+    if !status.success() {
+        return Err(PlayingError::unsupported(format!("{} failed with {}", program, status)))
+    }
+    Ok(())
+}
+
+/// A command sent by a remote source, e.g. `toggle` or `seek 30`.
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true)]
+struct Remote {
+    #[command(subcommand)]
+    operation: Operation,
+}
+
+impl Remote {
+    fn parse_line(line: &str) -> Result<Action, PlayingError> {
+        match Remote::try_parse_from(line.split_whitespace()) {
+            Ok(r) => Ok(Action::Operation(r.operation)),
+            Err(e) => Err(PlayingError::bad_command(e.to_string().lines().next().unwrap_or_default().to_owned())),
+        }
+    }
+}
+
+/// A command read by `stdin`: an operation, as remote sources send them, or any other command.
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true)]
+struct Streamed {
+    #[command(subcommand)]
+    action: Action,
+}
+
+impl Streamed {
+    /// Parses `line`, split as a shell would, e.g. `status --format "{artist} - {title}"`.
+    fn parse_line(line: &str) -> Result<Action, PlayingError> {
+        let Some(words) = shlex::split(line) else {
+            return Err(PlayingError::bad_command("unbalanced quotes"))
+        };
+        let parsed = match words.first() {
+            Some(w) if Operation::has_subcommand(w) => Remote::try_parse_from(&words).map(|r| Action::Operation(r.operation)),
+            _ => Streamed::try_parse_from(&words).map(|s| s.action),
+        };
+        parsed.map_err(|e| PlayingError::bad_command(e.to_string().lines().next().unwrap_or_default().to_owned()))
+    }
+}
+
+#[derive(PartialEq,Eq,PartialOrd,Ord,Debug)]
+enum Player {
+    Mpv,
+    Vlc,
+    Firefox,
+    Spotify,
+    Chrome,
+    Mpd,
+    Music,
+    Custom(&'static str)
+}
+use Player::*;
+
+impl Player {
+    /// The entry of the player in the built-in ranking.
+    fn to_str(&self) -> &'static str {
+        match self {
+            Mpv => "mpv",
+            Vlc => "vlc",
+            Firefox => "Mozilla firefox",
+            Spotify => "Spotify",
+            // Chromium and the browsers built on it name themselves
+            Chrome => "/^(chrome|[Cc]hromium|Brave.*)$/",
+            Mpd => "mpd",
+            Music => "Music",
+            Custom(s) => s,
+        }
+    }
+
+    fn parse(s: &str) -> Option<Player> {
+        match s {
+            "mpv" => Some(Mpv),
+            "vlc" => Some(Vlc),
+            "Mozilla firefox" => Some(Firefox),
+            "Spotify" => Some(Spotify),
+            s if format::selects(Chrome.to_str(), s) => Some(Chrome),
+            "mpd" => Some(Mpd),
+            "Music" => Some(Music),
+            // c => { println!("{}", c); None },
+            _ => None,
+        }
+    }
+
+    /// Icon of the player in `set`.
+    fn icon_in(&self, set: status::IconSet) -> &'static str {
+        use status::IconSet;
+        match (set, self) {
+            (IconSet::Nerd, _) => self.icon(),
+            (IconSet::None, _) | (_, Custom(_)) => "",
+            (IconSet::Emoji, Mpv) => "🎬",
+            (IconSet::Emoji, Vlc) => "📼",
+            (IconSet::Emoji, Firefox) => "🦊",
+            (IconSet::Emoji, Spotify) => "🎧",
+            (IconSet::Emoji, Chrome) => "🌐",
+            (IconSet::Emoji, Mpd) => "🎵",
+            (IconSet::Emoji, Music) => "🎶",
+            (IconSet::Ascii, Mpv) => "[mpv]",
+            (IconSet::Ascii, Vlc) => "[vlc]",
+            (IconSet::Ascii, Firefox) => "[ff]",
+            (IconSet::Ascii, Spotify) => "[sp]",
+            (IconSet::Ascii, Chrome) => "[ch]",
+            (IconSet::Ascii, Mpd) => "[mpd]",
+            (IconSet::Ascii, Music) => "[mu]",
+        }
+    }
+
+    /// Icon of the player among the Nerd Fonts glyphs.
+    fn icon(&self) -> &'static str {
+        match self {
+            Mpv => "",
+            Vlc => "󰕼",
+            Firefox => "",
+            Spotify => "",
+            Chrome => "",
+            Mpd => "󰝚",
+            Music => "",
+            Custom(_) => "",
+        }
+    }
+}
+
+/// Players in order of preference, unless the config says otherwise.
+const DEFAULT_RANKING: [Player; 7] = [Custom("mpv"), Vlc, Firefox, Spotify, Chrome, Mpd, Music];
+
+/// The exit code of the commands that either succeed or don't.
+fn exit_code(success: bool) -> i32 {
+    if success { 0 } else { 1 }
+}
+
+async fn run(cmd: Cmd) -> Result<i32, PlayingError> {
+    //eprintln!("{:?}", cmd);
+    if let Action::Doctor = cmd.action {
+        // the doctor reports a broken config instead of failing on it
+        return Ok(exit_code(doctor::run(cmd.config.as_deref(), cmd.mpv_socket)))
+    }
+    if let Action::Migrate { from, files } = cmd.action {
+        return Ok(exit_code(migrate::run(from, files)))
+    }
+    let mut config = Config::load(cmd.config.as_deref())?;
+    if let Some(socket) = cmd.mpv_socket {
+        config.mpv.socket = Some(socket);
+    }
+    if let Some(mode) = cmd.mode {
+        config.mode = mode;
+    }
+    if cmd.strict_ranking {
+        config.selection.forced = Some(SelectionPolicy::StaticRanking);
+    }
+    if cmd.selection.is_some() {
+        config.selection.forced = cmd.selection;
+    }
+    if let Some(ref group) = cmd.group {
+        config.restrict_to_group(group)?;
+    }
+    config.player = cmd.player.map(|p| config.resolve_player(&p));
+    if let Some(ref player) = config.player {
+        format::check_selector(player).map_err(PlayingError::config)?;
+    }
+    config.fixture = cmd.fixture;
+    if let Some(n) = config.player.as_deref().and_then(|p| p.parse::<usize>().ok()) {
+        // numbers only mean something among the players running now, unlike bus names
+        let backends = backend::open(cmd.backend, &config)?;
+        let players = backend::players(&backends)?;
+        let p = n.checked_sub(1).and_then(|i| numbered(&players).get(i).copied())
+            .ok_or_else(|| PlayingError::config(format!("there's no player number {}, see `players`", n)))?;
+        config.player = Some(p.bus_name().unwrap_or(p.identity()).to_owned());
+    }
+    if cmd.compat.is_some() {
+        config.status.compat = cmd.compat;
+    }
+    if cmd.icons.is_some() {
+        config.status.icons = cmd.icons;
+    }
+    config.guards = cmd.guards;
+    // an alias or a pattern, as for `--player`
+    if let Some(player) = config.guards.if_player.take() {
+        let player = config.resolve_player(&player);
+        format::check_selector(&player).map_err(PlayingError::config)?;
+        config.guards.if_player = Some(player);
+    }
+    if cmd.dry_run {
+        return plan::run(&config, cmd.backend, &cmd.action, cmd.json).map(exit_code)
+    }
+    if let Action::Capabilities = cmd.action {
+        config.access.check(Source::Cli, &cmd.action)?;
+        return Ok(exit_code(capabilities::run(&config, cmd.json)))
+    }
+    if let Action::Favorite { command: Some(ref command), .. } = cmd.action {
+        config.access.check(Source::Cli, &cmd.action)?;
+        return favorites::run(command, cmd.json).map(exit_code)
+    }
+    if let Action::History { command } = cmd.action {
+        config.access.check(Source::Cli, &Action::History { command: None })?;
+        return history::run(&config, cmd.backend, command, cmd.json).map(exit_code)
+    }
+    if let Action::Status { quiet, quiet_verbose, follow: false, .. } = cmd.action {
+        if quiet || quiet_verbose {
+            config.access.check(Source::Cli, &cmd.action)?;
+            return status::quiet(&config, cmd.backend, quiet_verbose)
+        }
+    }
+    dispatch(&config, cmd.backend, Source::Cli, cmd.action).await.map(exit_code)
+}
+
+/// Entry point shared by every command source, enforcing the access rules before acting.
+async fn dispatch(config: &Config, backend: BackendKind, source: Source, action: Action) -> Result<bool, PlayingError> {
+    config.access.check(source, &action)?;
+
+    if let Action::Wake { at, ref playlist, fade, ref player } = action {
+        // connections opened now could be long closed at wake up time
+        return wake::run(config, backend, at, playlist.as_deref(), fade, player.as_deref())
+    }
+    if let Action::Tune = action {
+        return Ok(analytics::tune(config))
+    }
+    if let Action::Db { command } = action {
+        return store::run(config, command)
+    }
+    if let Action::SleepTimer { cancel: true, .. } = action {
+        return timer::cancel()
+    }
+    if let Action::SleepTimer { status: true, .. } = action {
+        return timer::status()
+    }
+    if let Action::SleepTimerWatch { ref identity, until, stop } = action {
+        // opens the backends itself, as late as it can
+        return timer::watch(config, backend, identity, until, stop)
+    }
+    if let Action::Status { quiet, quiet_verbose, follow: false, .. } = action {
+        if quiet || quiet_verbose {
+            // the other sources only tell success, which is whether something plays
+            return status::quiet(config, backend, quiet_verbose).map(|code| code == status::EXIT_PLAYING)
+        }
+    }
+    if let Action::Replay { ref file } = action {
+        let opts = StatusOptions::new(None, None, config, false, 1)?;
+        return fixture::replay(config, file, &opts)
+    }
+
+    if let Action::Input = action {
+        // the commands open the backends again, the players may be long gone by the first press
+        return input::run(config, backend).await
+    }
+    if let Action::Stdin = action {
+        return stdin::run(config, backend).await
+    }
+    if let Action::PauseOnLock = action {
+        return lock::run(config, backend)
+    }
+    if let Action::PauseOnUnplug = action {
+        return sink::run(config, backend)
+    }
+    if let Action::Notifications = action {
+        return notify::run(config, backend).await
+    }
+
+    let backends = backend::open(backend, config)?;
+    let players = backend::players(&backends)?;
+    // a fixture's players aren't running, there's nothing to learn from them
+    if config.fixture.is_none() {
+        recent::observe(&players);
+        learned::observe(&players, config);
+    }
+
+    if let Action::Favorite { command: None, always, poll, check, undo, notify } = action {
+        return favorites::favorite(config, &players, always, poll, check, undo, notify).await
+    }
+
+    if let Action::Playlist { command } = action {
+        let cli = spotifav::get_client().await.map_err(PlayingError::from_spotifav)?;
+        return spotify::run_playlist(&cli, command).await
+    }
+    if let Action::Spotify { command } = action {
+        let cli = spotifav::get_client().await.map_err(PlayingError::from_spotifav)?;
+        return spotify::run(&cli, command).await
+    }
+
+    let policy = config.selection_policy(action.permission_names()[0]);
+    if let Action::Notify = action {
+        let Some(p) = pick_player(config, &players, policy)? else { return Ok(false) };
+        notify::Notifier::new()?.track(&follow::Snapshot::take(p)?, &[])?;
+        return Ok(true)
+    }
+
+    if let Action::Copy { what, ref format } = action {
+        let Some(p) = pick_player(config, &players, policy)? else { return Ok(false) };
+        let text = match what {
+            clipboard::Field::Url => p.track()?.url,
+            clipboard::Field::Title => p.track()?.title,
+            // icons are glyphs of the bar's font, which chats don't have
+            clipboard::Field::Formatted => Some(status::render(p, &StatusOptions::new(format.as_deref(), None, config, true, 0)?, None)?),
+        };
+        let Some(text) = text else {
+            eprintln!("{} doesn't tell the {} of its track", p.identity(), format!("{:?}", what).to_lowercase());
+            return Ok(false)
+        };
+        clipboard::copy(&text)?;
+        println!("{}", text);
+        return Ok(true)
+    }
+
+    if let Action::Download { ref dir, ref template } = action {
+        let Some(p) = pick_player(config, &players, policy)? else { return Ok(false) };
+        return download::run(&config.download, p, dir.as_deref(), template.as_deref())
+    }
+
+    if let Action::Players = action {
+        for (i, p) in numbered(&players).iter().enumerate() {
+            println!("{}  {}  {}  {}", i + 1, p.identity(), p.status()?.name(), p.bus_name().unwrap_or("-"));
+        }
+        return Ok(!players.is_empty())
+    }
+
+    if let Action::PauseAll = action {
+        for p in &players {
+            // one player failing shouldn't keep the others playing
+            match p.status() {
+                Ok(Status::Playing) => if let Err(e) = p.pause() {
+                    eprintln!("cannot pause {}: {}", p.identity(), e.inner);
+                },
+                Ok(_) => {}
+                Err(e) => eprintln!("cannot read the status of {}: {}", p.identity(), e.inner),
+            }
+        }
+        return Ok(true)
+    }
+
+    if let Action::Scrobble { daemon } = action {
+        return scrobble::run(config, &backends, daemon)
+    }
+    if let Action::Mqtt = action {
+        return mqtt::run(config, backend, &backends).await
+    }
+    if let Action::Record { ref file } = action {
+        return fixture::record(&backends, file)
+    }
+    if let Action::AutoPause = action {
+        return arbiter::run(&backends)
+    }
+    if let Action::Skip = action {
+        return skip::run(config, &backends)
+    }
+    if let Action::Serve { http } = action {
+        return serve::run(config, backend, &backends, http).await
+    }
+    if let Action::Dbus = action {
+        #[cfg(all(unix, not(target_os = "macos")))]
+        return service::run(config, backend).await;
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        return Err(PlayingError::unsupported("the d-bus service is only available with mpris"));
+    }
+    if let Action::Events = action {
+        return events::run(config, &backends)
+    }
+    if let Action::RepeatWatch { ref player, times, restore } = action {
+        return repeat::watch(&backends, player, times, restore)
+    }
+
+    let ranking = selection::ranking(config, policy, &players)?;
+    if let Action::SleepTimer { after, stop, .. } = action {
+        let Some(p) = active_player(&players, &ranking)? else {
+            eprintln!("nothing is playing");
+            return Ok(false)
+        };
+        return timer::start(p, after, stop, config)
+    }
+    let status_opts = match action {
+        Action::Status { no_icon, spaces_after_icon, ref format, ref live_format, output, ending_within, max_length, scroll, max_title, max_album, max_artist, ref ellipsis, escape, include_paused, ref artist_separator, max_artists, pretty, changed_only, .. } => {
+            let mut opts = StatusOptions::new(format.as_deref(), live_format.as_deref(), config, no_icon, spaces_after_icon)?;
+            opts.output = output;
+            opts.ending_within = ending_within.or(opts.ending_within);
+            opts.max_length = max_length.unwrap_or(opts.max_length);
+            opts.scroll = scroll;
+            opts.max_title = max_title.or(opts.max_title);
+            opts.max_album = max_album.or(opts.max_album);
+            opts.max_artist = max_artist.or(opts.max_artist);
+            opts.ellipsis = ellipsis.clone().unwrap_or(opts.ellipsis);
+            opts.escape = escape.unwrap_or(opts.escape);
+            opts.include_paused = include_paused;
+            opts.pretty = pretty;
+            opts.changed_only = changed_only;
+            opts.artist_separator = artist_separator.clone().unwrap_or(opts.artist_separator);
+            opts.max_artists = max_artists.or(opts.max_artists);
+            Some(opts)
+        }
+        _ => None,
+    };
+
+    if let (Action::Status { follow: true, quiet, write_to, art_to, notify, control_socket, .. }, Some(ref opts)) = (&action, &status_opts) {
+        let files = write_to.clone().map(|line| follow::Files::new(line, art_to.clone()));
+        let notifier = notify.then(notify::Notifier::new).transpose()?;
+        refresh::install();
+        seeked::listen();
+        if let Some(socket) = control_socket {
+            refresh::listen(socket)?;
+        }
+        return follow::run(&backends, &ranking, opts, *quiet, files, &config.hooks, notifier)
+    }
+
+    if let Action::Operation(ref op) = action {
+        let targets = operation_targets(&players, &ranking, config, policy)?;
+        for p in &targets {
+            run_operation(*p, op, config)?;
+            recent::touch(p.identity());
+            if op.seeks() {
+                // for `status --follow` to show the new position without waiting for its poll
+                seeked::announce(p.identity());
+            }
+        }
+        analytics::record(config, op.permission_names()[0], &targets);
+        if let (Operation::Play { exclusive: true }, false) = (op, targets.is_empty()) {
+            for p in players.iter().filter(|p| !targets.iter().any(|t| std::ptr::addr_eq(*t, p.as_ref()))) {
+                if p.status()? == Status::Playing {
+                    p.pause()?
+                }
+            }
+        }
+        // scripts can tell that the guards held everything back
+        return Ok(!targets.is_empty() || !config.guards.is_set())
+    }
+
+    if let (Action::Status { include_paused: true, .. }, Some(ref opts)) = (&action, &status_opts) {
+        if let Some(p) = selected_player(&players, &ranking, policy)? {
+            opts.print(&status::render(p, opts, None)?);
+            return Ok(true)
+        }
+    }
+
+    let span = timing::span("identity match");
+    'ranking: for id in ranking {
+        // println!("Checking for {}", id);
+        for p in &players {
+            // println!("\tFound {}", p.identity());
+            if picks(&id, p.as_ref()) {
+                match action {
+                    Action::Status { .. } => {
+                        // println!("status: {:?}", p.status()?);
+                        if p.status()? == Status::Playing {
+                            if let Some(ref opts) = status_opts {
+                                opts.print(&status::render(p.as_ref(), opts, None)?);
+                            }
+                            return Ok(true)
+                        }
+                        // the first player picked is the one meant, even paused
+                        if policy != SelectionPolicy::PlayingFirst {
+                            break 'ranking
+                        }
+                    }
+                    Action::Operation(_) | Action::Favorite { .. } | Action::Doctor | Action::Capabilities | Action::Migrate { .. } | Action::Scrobble { .. } | Action::Mqtt | Action::Skip | Action::Serve { .. } | Action::RepeatWatch { .. } | Action::Wake { .. } | Action::Events | Action::Dbus | Action::History { .. } | Action::Tune | Action::PauseAll | Action::AutoPause | Action::Input | Action::Stdin | Action::PauseOnLock | Action::Notifications | Action::Notify | Action::PauseOnUnplug | Action::Record { .. } | Action::Replay { .. } | Action::SleepTimer { .. } | Action::SleepTimerWatch { .. } | Action::Db { .. } | Action::Playlist { .. } | Action::Spotify { .. } | Action::Copy { .. } | Action::Download { .. } | Action::Players => {}
+                    Action::Url { qr, open } => {
+                        if Player::parse(p.identity()).is_some() {
+                            let url = p.track()?.url.unwrap_or_default();
+                            if open {
+                                if url.is_empty() {
+                                    eprintln!("{} has no URL to open", p.identity());
+                                    return Ok(false)
+                                }
+                                open_url(config.browser.as_deref(), &url)?;
+                            } else if qr {
+                                println!("{}", qr_code(&url)?);
+                            } else {
+                                print!("{}", url);
+                            }
+                        }
+                    }
+                    Action::Player => {
+                        println!("{}", p.identity());
+                    }
+                }
+            }
+        }
+    }
+
+    drop(span);
+
+    if let Action::Status { .. } = action {
+        match status_opts {
+            Some(ref opts) => opts.print(&opts.idle("No media")),
+            None => println!("No media"),
+        }
+    }
+
+    Ok(true)
+}
+
+/// The players an operation acts on: those of the ranking that meet the guards, only the one
+/// `policy` picks in single mode.
+fn operation_targets<'a>(players: &'a [Box<dyn MediaPlayer>], ranking: &[String], config: &Config, policy: SelectionPolicy) -> Result<Vec<&'a dyn MediaPlayer>, PlayingError> {
+    let _span = timing::span("identity match");
+    let mut targets: Vec<&dyn MediaPlayer> = vec![];
+    for id in ranking {
+        for p in players {
+            // patterns of the ranking can pick a player twice
+            if targets.iter().any(|t| std::ptr::addr_eq(*t, p.as_ref())) {
+                continue
+            }
+            if picks(id, p.as_ref()) && config.guards.allow(p.as_ref())? {
+                targets.push(p.as_ref());
+                if config.mode == Mode::Single && policy != SelectionPolicy::PlayingFirst {
+                    return Ok(targets)
+                }
+            }
+        }
+    }
+    if config.mode == Mode::Single {
+        // a player that's playing is the one meant, rather than a paused one ranked above it
+        for p in &targets {
+            if p.status()? == Status::Playing {
+                return Ok(vec![*p])
+            }
+        }
+        targets.truncate(1);
+    }
+    Ok(targets)
+}
+
+/// Whether the ranking entry or `--player` selector `selector` picks `p`, by its identity or by
+/// its full bus name, e.g. `org.mpris.MediaPlayer2.mpv.instance1234` among several mpv.
+fn picks(selector: &str, p: &dyn MediaPlayer) -> bool {
+    p.bus_name() == Some(selector) || format::selects(selector, p.identity())
+}
+
+/// The players in the order `players` numbers them, which holds while they run: by bus name,
+/// then by identity for the ones without.
+fn numbered(players: &[Box<dyn MediaPlayer>]) -> Vec<&dyn MediaPlayer> {
+    let mut numbered: Vec<&dyn MediaPlayer> = players.iter().map(AsRef::as_ref).collect();
+    numbered.sort_by(|a, b| (a.bus_name(), a.identity()).cmp(&(b.bus_name(), b.identity())));
+    numbered
+}
+
+/// The first player of the ranking that is playing.
+fn active_player<'a>(players: &'a [Box<dyn MediaPlayer>], ranking: &[String]) -> Result<Option<&'a dyn MediaPlayer>, PlayingError> {
+    let _span = timing::span("identity match");
+    for id in ranking {
+        for p in players {
+            if picks(id, p.as_ref()) && p.status()? == Status::Playing {
+                return Ok(Some(p.as_ref()))
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The player of the ranking that `policy` picks for a command acting on a single one: the first
+/// running, or the one playing before with `playing-first`.
+fn selected_player<'a>(players: &'a [Box<dyn MediaPlayer>], ranking: &[String], policy: SelectionPolicy) -> Result<Option<&'a dyn MediaPlayer>, PlayingError> {
+    if policy == SelectionPolicy::PlayingFirst {
+        return current_player(players, ranking)
+    }
+    Ok(ranking.iter().find_map(|id| players.iter().find(|p| picks(id, p.as_ref()))).map(AsRef::as_ref))
+}
+
+/// The player that `policy` picks among `players` for a command acting on a single one, such as
+/// `notify` or `copy`; none, once reported, when there's no player to act on.
+fn pick_player<'a>(config: &Config, players: &'a [Box<dyn MediaPlayer>], policy: SelectionPolicy) -> Result<Option<&'a dyn MediaPlayer>, PlayingError> {
+    let ranking = selection::ranking(config, policy, players)?;
+    let picked = selected_player(players, &ranking, policy)?;
+    if picked.is_none() {
+        eprintln!("nothing is playing");
+    }
+    Ok(picked)
+}
+
+/// The player that is playing, or else the first paused one of the ranking, for the servers which
+/// shouldn't lose track of a player just because it's paused.
+fn current_player<'a>(players: &'a [Box<dyn MediaPlayer>], ranking: &[String]) -> Result<Option<&'a dyn MediaPlayer>, PlayingError> {
+    let _span = timing::span("identity match");
+    if let Some(p) = active_player(players, ranking)? {
+        return Ok(Some(p))
+    }
+    for id in ranking {
+        for p in players {
+            if picks(id, p.as_ref()) && p.status()? == Status::Paused {
+                return Ok(Some(p.as_ref()))
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// `seconds` in microseconds, as players seek by, backwards when negative; the operations built
+/// without parsing them, as by the library, can hold any number.
+fn offset(seconds: f32) -> Result<i64, PlayingError> {
+    Duration::try_from_secs_f32(seconds.abs()).ok()
+        .and_then(|d| i64::try_from(d.as_micros()).ok())
+        .map(|micros| if seconds < 0.0 { -micros } else { micros })
+        .ok_or_else(|| PlayingError::bad_command(format!("cannot seek by {:e} seconds", seconds)))
+}
+
+fn run_operation(p: &dyn MediaPlayer, op: &Operation, config: &Config) -> Result<(), PlayingError> {
+    match op {
+        // there's nothing to seek in a livestream, players would jump around or ignore it
+        Operation::Rewind { .. } | Operation::Forward { .. } | Operation::SeekRelative { .. } | Operation::Seek { .. } | Operation::SeekPreset { .. } | Operation::Replay { .. } if p.track()?.is_live() => {
+            eprintln!("{} is playing a livestream, not seeking", p.identity());
+        }
+        Operation::Toggle => {
+            if let Status::Playing = p.status()? {
+                p.pause()?
+            } else {
+                p.play()?
+            }
+        },
+        Operation::Play { .. } => p.play()?,
+        Operation::Pause => p.pause()?,
+        Operation::Next => p.next()?,
+        Operation::Previous { smart } => {
+            let DurationSpec(restart_after) = config.previous.restart_after;
+            // like a remote: the first press goes back to the start of the track
+            if (*smart || config.previous.smart) && p.position()?.is_some_and(|pos| pos > restart_after) && !p.track()?.is_live() {
+                p.set_position(Duration::ZERO)?
+            } else {
+                p.previous()?
+            }
+        }
+        Operation::Rewind { seconds } => p.seek(-offset(*seconds)?)?,
+        Operation::Forward { seconds } => p.seek(offset(*seconds)?)?,
+        Operation::SeekRelative { seconds } => p.seek(offset(*seconds)?)?,
+        Operation::Seek { seconds } => {
+            let position = Duration::try_from_secs_f32(*seconds)
+                .map_err(|_| PlayingError::bad_command(format!("cannot seek to {} seconds", seconds)))?;
+            p.set_position(position)?
+        }
+        Operation::Chapter { offset } => p.chapter(*offset)?,
+        Operation::FrameStep { back } => p.frame_step(*back)?,
+        Operation::PlaylistJump { position } => p.playlist_jump(*position)?,
+        Operation::AbLoop => p.ab_loop()?,
+        Operation::Replay { seconds } => {
+            p.seek(-offset(*seconds)?)?;
+            if p.status()? != Status::Playing {
+                p.play()?
+            }
+        }
+        Operation::RepeatThis { times } => repeat::start(p, *times, config)?,
+        Operation::StopAfterCurrent { wait } => timer::stop_after_current(p, *wait, config)?,
+        Operation::SeekPreset { name } => match preset::resolve(&config.seek_presets, &config.seek_preset_rules, name, p)? {
+            SeekTarget::Position(position) => p.set_position(position)?,
+            SeekTarget::Offset { back, by } => {
+                let offset = by.as_micros() as i64;
+                p.seek(if back { -offset } else { offset })?
+            }
+        },
+    }
+    Ok(())
+}
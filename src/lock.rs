@@ -0,0 +1,125 @@
+use serde::Deserialize;
+
+use crate::{backend::{self, Backend, BackendKind, MediaPlayer, Status}, config::Config, format, PlayingError};
+
+/// The `[lock]` table of the config file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LockConfig {
+    /// Identities of the players paused when the session locks, every player when empty.
+    pub players: Vec<String>,
+    /// Resume the players that were paused once the session unlocks.
+    pub resume: bool,
+}
+
+/// Pauses the configured players that are playing, returning their bus names (or identities).
+fn pause(config: &LockConfig, backends: &[Box<dyn Backend>]) -> Result<Vec<String>, PlayingError> {
+    let mut paused = vec![];
+    for p in backend::players(backends)? {
+        let wanted = config.players.is_empty() || config.players.iter().any(|id| format::selects(id, p.identity()));
+        if wanted && p.status()? == Status::Playing {
+            p.pause()?;
+            println!("session locked, pausing {}", p.identity());
+            paused.push(key(p.as_ref()));
+        }
+    }
+    Ok(paused)
+}
+
+/// Resumes the players of `paused` that are still paused, leaving those stopped meanwhile.
+fn resume(paused: &[String], backends: &[Box<dyn Backend>]) -> Result<(), PlayingError> {
+    for p in backend::players(backends)? {
+        if paused.contains(&key(p.as_ref())) && p.status()? == Status::Paused {
+            p.play()?;
+            println!("session unlocked, resuming {}", p.identity());
+        }
+    }
+    Ok(())
+}
+
+fn key(p: &dyn MediaPlayer) -> String {
+    p.bus_name().unwrap_or(p.identity()).to_owned()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn dbus_error(e: dbus::Error) -> PlayingError {
+    PlayingError { kind: crate::PlayingErrorKind::DBus, code: 2, inner: e.into() }
+}
+
+/// Listens for the session locking, through the screensaver on the session bus and logind on the
+/// system bus, sending `true` on lock and `false` on unlock.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn listen(tx: std::sync::mpsc::Sender<bool>) -> Result<(dbus::blocking::Connection, Option<dbus::blocking::Connection>), PlayingError> {
+    use std::time::Duration;
+    use dbus::{blocking::Connection, message::MatchRule};
+
+    let session = Connection::new_session().map_err(dbus_error)?;
+    // KDE and others implement the freedesktop name, GNOME its own
+    for interface in ["org.freedesktop.ScreenSaver", "org.gnome.ScreenSaver"] {
+        let tx = tx.clone();
+        let rule = MatchRule::new_signal(interface, "ActiveChanged");
+        session.add_match(rule, move |(active,): (bool,), _, _| tx.send(active).is_ok()).map_err(dbus_error)?;
+    }
+
+    // without a system bus, e.g. in a container, the screensaver is enough
+    let system = match Connection::new_system() {
+        Ok(system) => system,
+        Err(e) => {
+            eprintln!("cannot connect to the system bus, only listening to the screensaver: {}", e);
+            return Ok((session, None))
+        }
+    };
+    // only this session's signals, when logind can tell which one it is
+    let own: Option<dbus::Path> = system
+        .with_proxy("org.freedesktop.login1", "/org/freedesktop/login1", Duration::from_secs(5))
+        .method_call("org.freedesktop.login1.Manager", "GetSessionByPID", (std::process::id(),))
+        .map(|(path,): (dbus::Path,)| path)
+        .ok();
+    for (member, locked) in [("Lock", true), ("Unlock", false)] {
+        let tx = tx.clone();
+        let mut rule = MatchRule::new_signal("org.freedesktop.login1.Session", member);
+        rule.path = own.clone();
+        system.add_match(rule, move |(): (), _, _| tx.send(locked).is_ok()).map_err(dbus_error)?;
+    }
+    Ok((session, Some(system)))
+}
+
+/// Pauses the `[lock]` players while the session is locked, resuming them on unlock if asked.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn run(config: &Config, kind: BackendKind) -> Result<bool, PlayingError> {
+    use std::{sync::mpsc, time::Duration};
+
+    let (tx, locks) = mpsc::channel();
+    let (session, system) = listen(tx)?;
+    let mut paused: Vec<String> = vec![];
+    let mut locked = false;
+    loop {
+        session.process(Duration::from_millis(500)).map_err(dbus_error)?;
+        if let Some(ref system) = system {
+            system.process(Duration::from_millis(500)).map_err(dbus_error)?;
+        }
+        while let Ok(lock) = locks.try_recv() {
+            // both buses may tell about the same lock
+            if lock == locked {
+                continue
+            }
+            locked = lock;
+            // the backends are opened at every change, as mpd drops idle connections
+            let result = backend::open(kind, config).and_then(|backends| if lock {
+                pause(&config.lock, &backends).map(|p| paused = p)
+            } else if config.lock.resume {
+                resume(&std::mem::take(&mut paused), &backends)
+            } else {
+                Ok(())
+            });
+            if let Err(e) = result {
+                eprintln!("cannot {} the players: {}", if lock { "pause" } else { "resume" }, e.inner);
+            }
+        }
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn run(_: &Config, _: BackendKind) -> Result<bool, PlayingError> {
+    Err(PlayingError::unsupported("pausing on lock needs the d-bus session and system buses"))
+}
@@ -0,0 +1,316 @@
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum MigrateFrom {
+    Playerctl,
+}
+
+/// Config files where playerctl invocations usually live, relative to `$XDG_CONFIG_HOME`.
+const COMMON_FILES: &[&str] = &[
+    "sway/config",
+    "i3/config",
+    "hypr/hyprland.conf",
+    "sxhkd/sxhkdrc",
+    "waybar/config",
+    "waybar/config.jsonc",
+    "polybar/config",
+    "polybar/config.ini",
+    "eww/eww.yuck",
+    "i3blocks/config",
+];
+
+/// Maps playerctl's player names (the bus name part) to MPRIS identities.
+fn identity(name: &str) -> String {
+    match name {
+        "spotify" => "Spotify",
+        "firefox" => "Mozilla firefox",
+        "chromium" | "chrome" => "chrome",
+        "vlc" => "vlc",
+        "mpv" => "mpv",
+        "mpd" => "mpd",
+        n => n,
+    }.to_owned()
+}
+
+/// Equivalent playing.rs command of a playerctl command.
+fn command(args: &[String], all_players: bool, follow: bool) -> Option<String> {
+    let prefix = if all_players { "playing --mode multiple" } else { "playing" };
+    let mut cmd = args.iter().map(String::as_str);
+    let line = match cmd.next()? {
+        "play-pause" => "op toggle".to_owned(),
+        "play" => "op play".to_owned(),
+        "pause" | "stop" => "op pause".to_owned(),
+        "next" => "op next".to_owned(),
+        "previous" => "op previous".to_owned(),
+        "position" => match cmd.next() {
+            Some(p) if p.ends_with('+') => format!("op forward {}", p.trim_end_matches('+')),
+            Some(p) if p.ends_with('-') => format!("op rewind {}", p.trim_end_matches('-')),
+            Some(p) => format!("op seek {}", p),
+            None => "status --format '{position}'".to_owned(),
+        },
+        "status" => "status --format '{status}'".to_owned(),
+        "metadata" => match cmd.next() {
+            Some("xesam:url") => "url".to_owned(),
+            _ => "status".to_owned(),
+        },
+        _ => return None,
+    };
+    match follow && line.starts_with("status") {
+        true => Some(format!("{} {} --follow", prefix, line)),
+        false => Some(format!("{} {}", prefix, line)),
+    }
+}
+
+/// Splits a shell command line, stopping at the first separator or unbalanced quote (e.g. the end
+/// of the JSON string the command is embedded in).
+fn split(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut chars = line.chars();
+    let mut in_word = false;
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            ';' | '&' | '|' | '`' | ')' => break,
+            '\'' | '"' => {
+                let mut closed = false;
+                for q in chars.by_ref() {
+                    if q == c {
+                        closed = true;
+                        break
+                    }
+                    word.push(q);
+                }
+                if !closed {
+                    break
+                }
+                in_word = true;
+            }
+            '\\' => {
+                if let Some(e) = chars.next() {
+                    word.push(e);
+                    in_word = true;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Translates playerctl's `{{ expr }}` format to a playing.rs template, with a note for every
+/// construct that has no equivalent.
+fn translate_format(format: &str, notes: &mut Vec<String>) -> String {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start].replace('{', "{{").replace('}', "}}"));
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break
+        };
+        let expr = rest[start + 2..start + end].trim();
+        rest = &rest[start + end + 2..];
+
+        // `func(var, ...)` keeps the variable and drops the function
+        let var = match expr.split_once('(') {
+            Some((func, args)) => {
+                let var = args.trim_end_matches(')').split(',').next().unwrap_or("").trim();
+                if func.trim() != "duration" {
+                    notes.push(format!("`{}` is not supported, using `{}` as is", func.trim(), var));
+                }
+                var
+            }
+            None => expr,
+        };
+        let field = match var {
+            "artist" | "xesam:artist" | "xesam:albumArtist" => "artist",
+            "title" | "xesam:title" => "title",
+            "album" | "xesam:album" => "album",
+            "playerName" | "playerInstance" => "player",
+            "status" => "status",
+            "xesam:url" => "url",
+            "position" => "position",
+            "mpris:length" => "length",
+            v => {
+                notes.push(format!("no placeholder for `{}`, dropped", v));
+                continue
+            }
+        };
+        out.push('{');
+        out.push_str(field);
+        out.push('}');
+    }
+    out.push_str(&rest.replace('{', "{{").replace('}', "}}"));
+    out
+}
+
+#[derive(Default)]
+struct Migration {
+    ranking: Vec<String>,
+    formats: Vec<String>,
+    comments: Vec<String>,
+}
+
+impl Migration {
+    fn invocation(&mut self, origin: &str, args: &[String]) {
+        let mut all_players = false;
+        let mut follow = false;
+        let mut rest = vec![];
+        let mut notes = vec![];
+        let mut format = None;
+        let mut i = 0;
+        while i < args.len() {
+            let arg = args[i].as_str();
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f, Some(v.to_owned())),
+                _ => (arg, None),
+            };
+            let mut value = || inline.clone().or_else(|| {
+                i += 1;
+                args.get(i).cloned()
+            });
+            match flag {
+                "-p" | "--player" => {
+                    for p in value().unwrap_or_default().split(',').filter(|p| !p.is_empty()) {
+                        let id = identity(p);
+                        if p == "%any" || p == "playerctld" {
+                            notes.push(format!("`{}` has no equivalent, the ranking decides", p));
+                        } else if !self.ranking.contains(&id) {
+                            self.ranking.push(id);
+                        }
+                    }
+                }
+                "-i" | "--ignore-player" => {
+                    let ignored = value().unwrap_or_default();
+                    notes.push(format!("ignored players ({}) are simply left out of the ranking", ignored));
+                }
+                "-f" | "--format" => format = value(),
+                "-a" | "--all-players" => all_players = true,
+                "-F" | "--follow" => follow = true,
+                a => rest.push(a.to_owned()),
+            }
+            i += 1;
+        }
+
+        self.comments.push(format!("{}: playerctl {}", origin, args.join(" ")));
+        match command(&rest, all_players, follow) {
+            Some(c) => self.comments.push(format!("  -> {}", c)),
+            None => self.comments.push("  -> no equivalent command".to_owned()),
+        }
+        if let Some(f) = format {
+            let translated = translate_format(&f, &mut notes);
+            self.comments.push(format!("  format: {}", translated));
+            if !self.formats.contains(&translated) {
+                self.formats.push(translated);
+            }
+        }
+        self.comments.extend(notes.into_iter().map(|n| format!("  note: {}", n)));
+    }
+
+    fn scan(&mut self, path: &Path) -> bool {
+        let Ok(content) = std::fs::read_to_string(path) else { return false };
+        for (n, line) in content.lines().enumerate() {
+            for (i, _) in line.match_indices("playerctl") {
+                let after = &line[i + "playerctl".len()..];
+                // skip playerctld and words merely containing playerctl
+                if !(after.is_empty() || after.starts_with(char::is_whitespace)) {
+                    continue
+                }
+                // inside a string (e.g. waybar's JSON) the command ends with the string
+                let quoted = unescaped_quotes(&line[..i]).count() % 2 == 1;
+                let cmdline = match unescaped_quotes(after).next() {
+                    Some(end) if quoted => after[..end].replace("\\\"", "\""),
+                    _ => after.to_owned(),
+                };
+                let origin = format!("{}:{}", path.display(), n + 1);
+                self.invocation(&origin, &split(&cmdline));
+            }
+        }
+        true
+    }
+
+    fn toml(&self, playerctld: bool) -> String {
+        let mut out = String::from("# generated by `playing migrate --from playerctl`\n");
+        if playerctld {
+            out.push_str("# playerctld is running: playing.rs picks players by ranking, not by recent activity\n");
+        }
+        for c in &self.comments {
+            out.push_str(&format!("# {}\n", c));
+        }
+        if !self.ranking.is_empty() {
+            let ranking: Vec<String> = self.ranking.iter().map(|r| toml_string(r)).collect();
+            out.push_str(&format!("\nranking = [{}]\n", ranking.join(", ")));
+        }
+        if let Some((first, others)) = self.formats.split_first() {
+            out.push_str(&format!("\n[status]\nformat = {}\n", toml_string(first)));
+            for f in others {
+                out.push_str(&format!("# format = {}\n", toml_string(f)));
+            }
+        }
+        out
+    }
+}
+
+/// Byte offsets of the `"` in `s` that aren't escaped.
+fn unescaped_quotes(s: &str) -> impl Iterator<Item = usize> + '_ {
+    s.match_indices('"').map(|(i, _)| i).filter(|i| !s[..*i].ends_with('\\'))
+}
+
+fn toml_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn playerctld_running() -> bool {
+    let Ok(finder) = mpris::PlayerFinder::new() else { return false };
+    finder.find_all().is_ok_and(|players| players.iter().any(|p| p.bus_name() == "org.mpris.MediaPlayer2.playerctld"))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn playerctld_running() -> bool {
+    false
+}
+
+/// Prints a config equivalent to the playerctl invocations found in `files`, or in the usual
+/// window manager and bar configs when none are given.
+pub fn run(from: MigrateFrom, files: Vec<PathBuf>) -> bool {
+    match from {
+        MigrateFrom::Playerctl => {
+            let explicit = !files.is_empty();
+            let files = if !explicit {
+                let Some(base) = directories::BaseDirs::new() else { return false };
+                COMMON_FILES.iter().map(|f| base.config_dir().join(f)).collect()
+            } else {
+                files
+            };
+            let mut migration = Migration::default();
+            let mut read = 0;
+            for f in &files {
+                if migration.scan(f) {
+                    read += 1;
+                } else if explicit {
+                    eprintln!("cannot read {}", f.display());
+                }
+            }
+            if migration.comments.is_empty() {
+                eprintln!("no playerctl invocations found in {} files", read);
+                return false
+            }
+            print!("{}", migration.toml(playerctld_running()));
+            true
+        }
+    }
+}
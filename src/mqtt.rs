@@ -0,0 +1,218 @@
+use std::{sync::mpsc::{self, Receiver, RecvTimeoutError}, thread, time::Duration};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{access::Source, backend::{Backend, BackendKind}, config::Config, follow::{self, Snapshot}, status::StatusOptions, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// The `[mqtt]` table of the config file.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub client_id: String,
+    /// Prepended to every topic: `<prefix>/state`, `<prefix>/event`, `<prefix>/command`, ...
+    pub prefix: String,
+    /// Whether the state is published as a retained message.
+    pub retain: bool,
+    /// Run the commands (`toggle`, `seek 30`, ...) published to `<prefix>/command`.
+    pub commands: bool,
+    /// Announce the player to Home Assistant through MQTT discovery.
+    pub discovery: bool,
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        MqttConfig {
+            host: "localhost".to_owned(),
+            port: 1883,
+            username: None,
+            password: None,
+            client_id: "playing-rs".to_owned(),
+            prefix: "playing".to_owned(),
+            retain: true,
+            commands: false,
+            discovery: false,
+            discovery_prefix: "homeassistant".to_owned(),
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn topic(&self, name: &str) -> String {
+        format!("{}/{}", self.prefix.trim_end_matches('/'), name)
+    }
+}
+
+/// Home Assistant discovery messages, as `(topic, payload)`.
+///
+/// Home Assistant has no MQTT `media_player` platform, so the player shows up as a device with
+/// sensors for the state and, when commands are enabled, buttons and a position box to control it.
+fn discovery(config: &MqttConfig) -> Vec<(String, String)> {
+    let node: String = config.client_id.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    let device = json!({
+        "identifiers": [node],
+        "name": "playing.rs",
+        "manufacturer": "playing.rs",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let entity = |component: &str, id: &str, name: &str, mut fields: Value| {
+        fields["name"] = json!(name);
+        fields["unique_id"] = json!(format!("{}_{}", node, id));
+        fields["availability_topic"] = json!(config.topic("available"));
+        fields["device"] = device.clone();
+        (format!("{}/{}/{}/{}/config", config.discovery_prefix, component, node, id), fields.to_string())
+    };
+    let sensor = |id: &str, name: &str, field: &str| entity("sensor", id, name, json!({
+        "state_topic": config.topic("state"),
+        "value_template": format!("{{{{ value_json.{} }}}}", field),
+    }));
+
+    let mut messages = vec![
+        entity("sensor", "status", "Status", json!({
+            "state_topic": config.topic("state"),
+            "value_template": "{{ value_json.status }}",
+            "json_attributes_topic": config.topic("state"),
+            "icon": "mdi:music",
+        })),
+        sensor("title", "Title", "title"),
+        sensor("artist", "Artist", "artist"),
+        sensor("player", "Player", "player"),
+    ];
+    if config.commands {
+        let button = |id: &str, name: &str, command: &str, icon: &str| entity("button", id, name, json!({
+            "command_topic": config.topic("command"),
+            "payload_press": command,
+            "icon": icon,
+        }));
+        messages.extend([
+            button("toggle", "Play/pause", "toggle", "mdi:play-pause"),
+            button("next", "Next", "next", "mdi:skip-next"),
+            button("previous", "Previous", "previous", "mdi:skip-previous"),
+            button("rewind", "Rewind 10s", "rewind 10", "mdi:rewind-10"),
+            button("forward", "Forward 10s", "forward 10", "mdi:fast-forward-10"),
+            entity("number", "position", "Position", json!({
+                "command_topic": config.topic("command"),
+                "command_template": "seek {{ value }}",
+                "state_topic": config.topic("state"),
+                "value_template": "{{ value_json.position | default(0, true) | int }}",
+                "min": 0,
+                "max": 86400,
+                "mode": "box",
+                "unit_of_measurement": "s",
+            })),
+        ]);
+    }
+    messages
+}
+
+/// Connects to the broker, returning the client and the commands received on the command topic.
+///
+/// The connection is driven by its own thread, which subscribes again and announces the
+/// availability (and the discovery messages, if enabled) after every reconnection.
+fn connect(config: &MqttConfig) -> (Client, Receiver<String>) {
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(config.topic("available"), "offline", QoS::AtLeastOnce, true));
+    if let Some(ref user) = config.username {
+        options.set_credentials(user, config.password.as_deref().unwrap_or_default());
+    }
+    let (client, mut connection) = Client::new(options, 32);
+    let (tx, rx) = mpsc::channel();
+
+    let available = config.topic("available");
+    let discovery = if config.discovery { discovery(config) } else { vec![] };
+    let command = config.commands.then(|| config.topic("command"));
+    let announcer = client.clone();
+    thread::spawn(move || {
+        for notification in connection.iter() {
+            match notification {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    let _ = announcer.try_publish(&available, QoS::AtLeastOnce, true, "online");
+                    for (topic, payload) in &discovery {
+                        let _ = announcer.try_publish(topic, QoS::AtLeastOnce, true, payload.as_bytes());
+                    }
+                    if let Some(ref command) = command {
+                        let _ = announcer.try_subscribe(command, QoS::AtLeastOnce);
+                    }
+                }
+                // asked for on shutdown, after which the commands stop
+                Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+                Ok(Event::Incoming(Packet::Publish(p))) if Some(&p.topic) == command.as_ref() => {
+                    if tx.send(String::from_utf8_lossy(&p.payload).into_owned()).is_err() {
+                        return
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("mqtt: {}, reconnecting", e);
+                    thread::sleep(RECONNECT_DELAY);
+                }
+            }
+        }
+    });
+    (client, rx)
+}
+
+/// Publishes the state of the current player and its playback events, running the commands
+/// received from the broker when enabled.
+pub async fn run(config: &Config, kind: BackendKind, backends: &[Box<dyn Backend>]) -> Result<bool, PlayingError> {
+    let Some(ref mqtt) = config.mqtt else {
+        return Err(PlayingError::config("the mqtt publisher needs an [mqtt] table in the config"))
+    };
+    let ranking = config.ranking();
+    let opts = StatusOptions::published(config)?;
+    let (client, commands) = connect(mqtt);
+    crate::shutdown::install();
+
+    let mut last: Option<Snapshot> = None;
+    let mut published = false;
+    loop {
+        match follow::current(backends, &ranking) {
+            Ok(next) => {
+                let event = follow::event(last.as_ref(), next.as_ref());
+                if let Some(e) = event {
+                    client.publish(mqtt.topic("event"), QoS::AtLeastOnce, false, e).map_err(PlayingError::mqtt)?;
+                }
+                if event.is_some() || !published {
+                    let payload = next.as_ref().map_or_else(Snapshot::stopped, |s| s.json(&opts)).to_string();
+                    client.publish(mqtt.topic("state"), QoS::AtLeastOnce, mqtt.retain, payload).map_err(PlayingError::mqtt)?;
+                    published = true;
+                }
+                last = next;
+            }
+            Err(e) => eprintln!("cannot read the active player: {}", e.inner),
+        }
+
+        crate::timing::tick();
+        if crate::shutdown::requested() {
+            // the last will is only sent for connections that were lost
+            client.publish(mqtt.topic("available"), QoS::AtLeastOnce, true, "offline").map_err(PlayingError::mqtt)?;
+            client.disconnect().map_err(PlayingError::mqtt)?;
+            // the connection thread stops once both went out, unless the broker can't be reached
+            while commands.recv_timeout(RECONNECT_DELAY).is_ok() {}
+            return Ok(true)
+        }
+        match commands.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => {
+                let result = match crate::Remote::parse_line(&line) {
+                    // boxed, as dispatching is what started this loop
+                    Ok(action) => Box::pin(crate::dispatch(config, kind, Source::Mqtt, action)).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("mqtt command `{}` failed: {}: {}", line.trim(), e.kind, e.inner);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err(PlayingError::mqtt("the connection to the broker was lost")),
+        }
+    }
+}
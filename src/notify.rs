@@ -0,0 +1,189 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{backend::Track, follow::Snapshot, PlayingError};
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest cover art downloaded for an icon.
+const MAX_ART_SIZE: u64 = 8 << 20;
+
+/// A button of a notification: the key it's reported with and its label.
+pub type Action = (&'static str, &'static str);
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn dbus_error(e: dbus::Error) -> PlayingError {
+    PlayingError { kind: crate::PlayingErrorKind::DBus, code: 2, inner: e.into() }
+}
+
+fn last_path() -> Option<PathBuf> {
+    crate::store::path("notification.json")
+}
+
+/// The id of the last notification sent by any invocation, to replace it.
+fn load_last() -> u32 {
+    last_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_last(id: u32) {
+    let Some(path) = last_path() else { return };
+    if let Err(e) = crate::store::write_atomic(&path, id.to_string().as_bytes()) {
+        eprintln!("cannot save the notification id to {}: {}", path.display(), e);
+    }
+}
+
+/// The remote cover art at `url` in the cache dir, downloaded unless it was already.
+fn cached_art(url: &str) -> Option<PathBuf> {
+    use std::io::Read;
+
+    let dir = directories::ProjectDirs::from("org", "prabo", "playing")?.cache_dir().join("art");
+    let path = dir.join(format!("{:x}", md5::compute(url)));
+    if path.exists() {
+        return Some(path)
+    }
+    let response = ureq::get(url).timeout(TIMEOUT).call().ok()?;
+    let mut art = vec![];
+    response.into_reader().take(MAX_ART_SIZE).read_to_end(&mut art).ok()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    crate::store::write_atomic(&path, &art).ok()?;
+    Some(path)
+}
+
+/// The icon of a notification of `track`: its cover art, local or cached, else a generic one.
+fn icon(track: &Track) -> String {
+    match track.art_url.as_deref() {
+        Some(url) if url.starts_with("file://") => crate::follow::art_path(url),
+        Some(url) if url.starts_with("https://") || url.starts_with("http://") => cached_art(url)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or("media-playback-start".to_owned()),
+        _ => "media-playback-start".to_owned(),
+    }
+}
+
+/// Sends desktop notifications, each replacing the previous one instead of stacking up, even
+/// across invocations.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct Notifier {
+    conn: dbus::blocking::Connection,
+    last: u32,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl Notifier {
+    pub fn new() -> Result<Notifier, PlayingError> {
+        Ok(Notifier { conn: dbus::blocking::Connection::new_session().map_err(dbus_error)?, last: load_last() })
+    }
+
+    /// Shows a notification, returning its id.
+    pub fn send(&mut self, summary: &str, body: &str, icon: &str, actions: &[Action]) -> Result<u32, PlayingError> {
+        let actions: Vec<&str> = actions.iter().flat_map(|(key, label)| [*key, *label]).collect();
+        let (id,): (u32,) = self.conn.with_proxy(NAME, PATH, TIMEOUT)
+            .method_call(NAME, "Notify", ("playing.rs", self.last, icon, summary, body, actions, dbus::arg::PropMap::new(), -1i32))
+            .map_err(dbus_error)?;
+        if id != self.last {
+            save_last(id);
+        }
+        self.last = id;
+        Ok(id)
+    }
+
+    /// Shows the track of `snapshot`, with its cover art as icon.
+    pub fn track(&mut self, snapshot: &Snapshot, actions: &[Action]) -> Result<u32, PlayingError> {
+        let track = &snapshot.track;
+        let body = [track.artist(), track.album.as_ref()].into_iter().flatten().cloned().collect::<Vec<_>>().join(" — ");
+        self.send(track.title.as_deref().unwrap_or("Unknown"), &body, &icon(track), actions)
+    }
+
+    /// Reports the buttons clicked on notifications, as the id of the notification and the key of
+    /// the button, once [`Notifier::process`] runs.
+    pub fn on_action(&self, tx: std::sync::mpsc::Sender<(u32, String)>) -> Result<(), PlayingError> {
+        let rule = dbus::message::MatchRule::new_signal(NAME, "ActionInvoked");
+        self.conn.add_match(rule, move |(id, key): (u32, String), _, _| tx.send((id, key)).is_ok()).map_err(dbus_error)?;
+        Ok(())
+    }
+
+    pub fn process(&self, timeout: Duration) -> Result<(), PlayingError> {
+        self.conn.process(timeout).map_err(dbus_error)?;
+        Ok(())
+    }
+}
+
+/// Keeps notifying of every track the current player starts, with buttons to skip it and to
+/// toggle it in the Spotify favorites, so that the notification works as a small remote; the
+/// favorites queued while Spotify was unreachable are retried now and then.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub async fn run(config: &crate::config::Config, kind: crate::backend::BackendKind) -> Result<bool, PlayingError> {
+    use std::{sync::mpsc, time::Instant};
+    use crate::{access::Source, backend, favorites, follow, Operation};
+
+    const ACTIONS: &[Action] = &[("next", "⏭ Next"), ("favorite", "♥ Favorite")];
+    const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+    let mut retry_at = Instant::now();
+    let mut notifier = Notifier::new()?;
+    let (tx, clicks) = mpsc::channel();
+    notifier.on_action(tx)?;
+    let backends = backend::open(kind, config)?;
+    let ranking = config.ranking();
+    let mut last: Option<Snapshot> = None;
+    loop {
+        match follow::current(&backends, &ranking) {
+            Ok(next) => {
+                if let (Some(n), Some("player" | "track")) = (&next, follow::event(last.as_ref(), next.as_ref())) {
+                    if let Err(e) = notifier.track(n, ACTIONS) {
+                        eprintln!("cannot send a notification: {}", e.inner);
+                    }
+                }
+                last = next;
+            }
+            Err(e) => eprintln!("cannot read the active player: {}", e.inner),
+        }
+        notifier.process(Duration::from_secs(1))?;
+        while let Ok((id, key)) = clicks.try_recv() {
+            // buttons of notifications sent by others
+            if id != notifier.last {
+                continue
+            }
+            let action = match key.as_str() {
+                "next" => crate::Action::Operation(Operation::Next),
+                "favorite" => crate::Action::Favorite { command: None, poll: false, always: false, check: false, undo: false, notify: false },
+                _ => continue,
+            };
+            // boxed, as dispatching is what started this loop
+            if let Err(e) = Box::pin(crate::dispatch(config, kind, Source::Dbus, action)).await {
+                eprintln!("the `{}` button failed: {}: {}", key, e.kind, e.inner);
+            }
+        }
+        if Instant::now() >= retry_at {
+            retry_at = Instant::now() + RETRY_INTERVAL;
+            if let Err(e) = favorites::flush_queue().await {
+                eprintln!("cannot make the queued favorites: {}", e.inner);
+            }
+        }
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub struct Notifier;
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+impl Notifier {
+    pub fn new() -> Result<Notifier, PlayingError> {
+        Err(PlayingError::unsupported("notifications are only available with d-bus"))
+    }
+
+    pub fn send(&mut self, _: &str, _: &str, _: &str, _: &[Action]) -> Result<u32, PlayingError> {
+        Err(PlayingError::unsupported("notifications are only available with d-bus"))
+    }
+
+    pub fn track(&mut self, _: &Snapshot, _: &[Action]) -> Result<u32, PlayingError> {
+        Err(PlayingError::unsupported("notifications are only available with d-bus"))
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub async fn run(_: &crate::config::Config, _: crate::backend::BackendKind) -> Result<bool, PlayingError> {
+    Err(PlayingError::unsupported("notifications are only available with d-bus"))
+}
@@ -0,0 +1,95 @@
+use serde_json::{json, Value};
+
+use crate::{access::Source, backend::{self, BackendKind, Capabilities, MediaPlayer, Status}, config::Config, Action, Mode, Operation, PlayingError};
+
+/// The capability `op` needs from `p`, as named in [`Capabilities`].
+fn required(op: &Operation, p: &dyn MediaPlayer) -> &'static str {
+    match op {
+        Operation::Toggle if p.status().is_ok_and(|s| s == Status::Playing) => "pause",
+        Operation::Toggle | Operation::Play { .. } => "play",
+        Operation::Pause | Operation::StopAfterCurrent { .. } => "pause",
+        Operation::Next => "next",
+        Operation::Previous { .. } => "previous",
+        Operation::Rewind { .. } | Operation::Forward { .. } | Operation::SeekRelative { .. } | Operation::Seek { .. }
+            | Operation::Chapter { .. } | Operation::FrameStep { .. } | Operation::SeekPreset { .. } | Operation::Replay { .. } => "seek",
+        Operation::PlaylistJump { .. } | Operation::AbLoop | Operation::RepeatThis { .. } => "control",
+    }
+}
+
+fn has(capabilities: &Capabilities, name: &str) -> bool {
+    match name {
+        "play" => capabilities.play,
+        "pause" => capabilities.pause,
+        "next" => capabilities.next,
+        "previous" => capabilities.previous,
+        "seek" => capabilities.seek,
+        _ => capabilities.control,
+    }
+}
+
+/// How the operation would be resolved: the players considered, why each was picked or not,
+/// and whether the picked ones can run it.
+fn plan(config: &Config, kind: BackendKind, action: &Action, op: &Operation) -> Result<Value, PlayingError> {
+    let backends = backend::open(kind, config)?;
+    let players = backend::players(&backends)?;
+    let policy = config.selection_policy(op.permission_names()[0]);
+    let ranking = crate::selection::ranking(config, policy, &players)?;
+    let targets = crate::operation_targets(&players, &ranking, config, policy)?;
+
+    let mut candidates = vec![];
+    for p in &players {
+        let rank = ranking.iter().position(|id| crate::picks(id, p.as_ref()));
+        let selected = targets.iter().any(|t| std::ptr::addr_eq(*t, p.as_ref()));
+        let reason = if selected {
+            "selected"
+        } else if rank.is_none() {
+            "not in the ranking"
+        } else if !config.guards.allow(p.as_ref())? {
+            "held back by the --if-* guards"
+        } else {
+            "a player ranked higher was picked"
+        };
+        let needs = required(op, p.as_ref());
+        candidates.push(json!({
+            "player": p.identity(),
+            "rank": rank.map(|r| r + 1),
+            "status": p.status().ok().map(|s| s.name()),
+            "selected": selected,
+            "reason": reason,
+            "needs": needs,
+            "capable": p.capabilities().ok().map(|c| has(&c, needs)),
+        }));
+    }
+    Ok(json!({
+        "operation": op.permission_names()[0],
+        "allowed": config.access.check(Source::Cli, action).is_ok(),
+        "mode": if config.mode == Mode::Single { "single" } else { "multiple" },
+        "selection": policy.name(),
+        "ranking": ranking,
+        "candidates": candidates,
+        "targets": targets.iter().map(|t| t.identity()).collect::<Vec<_>>(),
+    }))
+}
+
+/// Prints what `action` would do for `--dry-run`, as JSON or as a summary.
+pub fn run(config: &Config, kind: BackendKind, action: &Action, as_json: bool) -> Result<bool, PlayingError> {
+    let Action::Operation(op) = action else {
+        return Err(PlayingError::unsupported("--dry-run only plans operations"))
+    };
+    let plan = plan(config, kind, action, op)?;
+    if as_json {
+        println!("{}", plan);
+        return Ok(true)
+    }
+
+    let list = |v: &Value| v.as_array().into_iter().flatten().filter_map(Value::as_str).collect::<Vec<_>>().join(", ");
+    println!("operation: {}{}", plan["operation"].as_str().unwrap_or_default(), if plan["allowed"] == true { "" } else { " (denied by the access rules)" });
+    println!("mode: {}", plan["mode"].as_str().unwrap_or_default());
+    println!("ranking: {}", list(&plan["ranking"]));
+    for c in plan["candidates"].as_array().into_iter().flatten() {
+        let capable = if c["capable"] == false { format!(", cannot {}", c["needs"].as_str().unwrap_or_default()) } else { String::new() };
+        println!("  {} ({}): {}{}", c["player"].as_str().unwrap_or_default(), c["status"].as_str().unwrap_or("unknown"), c["reason"].as_str().unwrap_or_default(), capable);
+    }
+    println!("targets: {}", list(&plan["targets"]));
+    Ok(true)
+}
@@ -0,0 +1,72 @@
+use std::{collections::HashMap, time::Duration};
+use serde::Deserialize;
+
+use crate::{backend::MediaPlayer, format, PlayingError};
+
+/// Where a preset seeks to: `85s` is a position, `+85s` and `-30s` are relative to the current one.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+#[serde(try_from = "String")]
+pub enum SeekTarget {
+    Position(Duration),
+    Offset { back: bool, by: Duration },
+}
+
+impl TryFrom<String> for SeekTarget {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let s = s.trim();
+        let (sign, rest) = if let Some(rest) = s.strip_prefix('+') {
+            (Some(false), rest)
+        } else if let Some(rest) = s.strip_prefix('-') {
+            (Some(true), rest)
+        } else {
+            (None, s)
+        };
+        let d = format::parse_duration(rest)
+            .ok_or_else(|| format!("invalid seek preset `{}`, expected e.g. `85s`, `-30s` or `+1:30`", s))?;
+        Ok(match sign {
+            Some(back) => SeekTarget::Offset { back, by: d },
+            None => SeekTarget::Position(d),
+        })
+    }
+}
+
+/// Overrides of the presets for some players or URLs, from `[[seek_preset_rules]]`:
+///
+/// ```toml
+/// [seek_presets]
+/// intro-skip = "85s"
+/// recap = "-30s"
+///
+/// [[seek_preset_rules]]
+/// url = "*crunchyroll.com*"
+/// presets = { intro-skip = "1:30" }
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PresetRule {
+    /// Identity of the player, e.g. `mpv`.
+    player: Option<String>,
+    /// Pattern of the URL, where `*` matches anything.
+    url: Option<String>,
+    presets: HashMap<String, SeekTarget>,
+}
+
+impl PresetRule {
+    fn applies(&self, player: &str, url: Option<&str>) -> bool {
+        self.player.as_ref().is_none_or(|p| p == player)
+            && self.url.as_ref().is_none_or(|pattern| url.is_some_and(|u| format::glob(pattern, u)))
+    }
+}
+
+/// The preset `name` for `p`: the first matching rule defining it, else the global one.
+pub fn resolve(presets: &HashMap<String, SeekTarget>, rules: &[PresetRule], name: &str, p: &dyn MediaPlayer) -> Result<SeekTarget, PlayingError> {
+    let url = p.track()?.url;
+    rules.iter()
+        .filter(|r| r.applies(p.identity(), url.as_deref()))
+        .find_map(|r| r.presets.get(name))
+        .or_else(|| presets.get(name))
+        .copied()
+        .ok_or_else(|| PlayingError::config(format!("no seek preset named `{}`", name)))
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashMap, path::PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::MediaPlayer;
+
+/// Selector of `--player` standing for the player that last changed playback state.
+pub const LAST_ACTIVE: &str = "last-active";
+
+/// Which player was last active, as playerctld tracks it, kept in the data dir and updated by
+/// every invocation since nothing watches the players in between.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct State {
+    last_active: Option<String>,
+    /// Status of every player at the last invocation, to tell which ones changed since.
+    statuses: HashMap<String, String>,
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("recent.json")
+}
+
+impl State {
+    fn load() -> State {
+        path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let Some(path) = path() else { return };
+        if let Err(e) = crate::store::write_atomic(&path, serde_json::to_string(self).unwrap_or_default().as_bytes()) {
+            eprintln!("cannot save the active player to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Records the status of `players`; one whose status changed since the last invocation, or
+/// that appeared playing, becomes the last active, those now playing first.
+pub fn observe(players: &[Box<dyn MediaPlayer>]) {
+    let mut state = State::load();
+    let statuses: HashMap<String, String> = players.iter()
+        .filter_map(|p| Some((p.identity().to_owned(), p.status().ok()?.name().to_owned())))
+        .collect();
+    let changed = |id: &String, status: &String| match state.statuses.get(id) {
+        Some(before) => before != status,
+        None => status == "playing",
+    };
+    let active = statuses.iter()
+        .filter(|(id, status)| changed(id, status))
+        .max_by_key(|(_, status)| *status == "playing")
+        .map(|(id, _)| id.clone());
+    if active.is_none() && statuses == state.statuses {
+        return
+    }
+    if active.is_some() {
+        state.last_active = active;
+    }
+    state.statuses = statuses;
+    state.save();
+}
+
+/// Makes `player` the last active, e.g. after running an operation on it.
+pub fn touch(player: &str) {
+    let mut state = State::load();
+    if state.last_active.as_deref() != Some(player) {
+        state.last_active = Some(player.to_owned());
+        state.save();
+    }
+}
+
+pub fn last_active() -> Option<String> {
+    State::load().last_active
+}
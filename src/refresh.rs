@@ -0,0 +1,89 @@
+use std::{path::Path, sync::atomic::{AtomicBool, Ordering}, thread::sleep, time::{Duration, Instant}};
+
+use crate::PlayingError;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+/// Set to end the current [`wait`] early without asking for a refresh, e.g. on a seek.
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+/// How often [`wait`] looks for a request, short enough to feel immediate.
+const SLICE: Duration = Duration::from_millis(20);
+
+#[cfg(unix)]
+extern "C" fn request(_: libc::c_int) {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Makes SIGUSR1 ask `status --follow` to poll the players and print the line again, e.g.
+/// after the bar restarted; see [`take`].
+#[cfg(unix)]
+pub fn install() {
+    let handler = request as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGUSR1, handler);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// Whether a refresh was asked for since the last call.
+pub fn take() -> bool {
+    REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Ends the current [`wait`], for the players to be polled right away.
+pub fn wake() {
+    WOKEN.store(true, Ordering::SeqCst);
+}
+
+/// Sleeps for `duration`, or less when a refresh is asked for or [`wake`] is called, returning
+/// whether either was.
+pub fn wait(duration: Duration) -> bool {
+    let until = Instant::now() + duration;
+    loop {
+        if REQUESTED.load(Ordering::SeqCst) || WOKEN.swap(false, Ordering::SeqCst) {
+            return true
+        }
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false
+        }
+        sleep(left.min(SLICE));
+    }
+}
+
+/// Listens on the socket at `path` for lines of commands, replacing a stale one: `refresh`
+/// does what SIGUSR1 does, e.g. `echo refresh | nc -U <path>`.
+#[cfg(unix)]
+pub fn listen(path: &Path) -> Result<(), PlayingError> {
+    use std::{io::{BufRead, BufReader, Write}, os::unix::net::UnixListener};
+
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let Ok(reader) = stream.try_clone() else { continue };
+            let mut stream = stream;
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                let answer = match line.trim() {
+                    "refresh" => {
+                        REQUESTED.store(true, Ordering::SeqCst);
+                        "ok".to_owned()
+                    }
+                    "" => continue,
+                    other => format!("unknown command `{}`", other),
+                };
+                if writeln!(stream, "{}", answer).is_err() {
+                    break
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen(_: &Path) -> Result<(), PlayingError> {
+    Err(PlayingError::unsupported("control sockets need a unix system"))
+}
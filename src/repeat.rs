@@ -0,0 +1,77 @@
+use std::{env, process::{Command, Stdio}, thread::{self, sleep}, time::Duration};
+
+use crate::{backend::{self, Backend, LoopMode, MediaPlayer}, config::Config, follow::Snapshot, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// A position this close to the start, right after a later one, means the track started over.
+const RESTART_WINDOW: Duration = Duration::from_secs(3);
+
+/// Loops the track of `p` and starts `repeat-watch` in the background, which puts the current
+/// loop mode back once the track has started over `times` times.
+pub fn start(p: &dyn MediaPlayer, times: u32, config: &Config) -> Result<(), PlayingError> {
+    let previous = p.loop_mode()?;
+    if previous == LoopMode::Track {
+        eprintln!("{} is already repeating its track", p.identity());
+        return Ok(())
+    }
+    p.set_loop_mode(LoopMode::Track)?;
+
+    let mut watcher = Command::new(env::current_exe()?);
+    if let Some(ref path) = config.path {
+        watcher.arg("--config").arg(path);
+    }
+    if let Some(ref socket) = config.mpv.socket {
+        watcher.arg("--mpv-socket").arg(socket);
+    }
+    watcher.args(["repeat-watch", p.identity(), &times.to_string(), previous.name()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null());
+    match watcher.spawn() {
+        Ok(mut child) => {
+            // reaped here when the servers run this, which outlive it
+            thread::spawn(move || child.wait());
+            Ok(())
+        }
+        Err(e) => {
+            // nothing would stop the loop otherwise
+            p.set_loop_mode(previous)?;
+            Err(e.into())
+        }
+    }
+}
+
+fn restarted(last: &Snapshot, next: &Snapshot) -> bool {
+    match (last.position, next.position) {
+        (Some(before), Some(after)) => last.same_track(next) && before >= RESTART_WINDOW && after < RESTART_WINDOW,
+        _ => false,
+    }
+}
+
+/// Waits for the track of `player` to start over `times` times, then sets the loop mode back to
+/// `restore`. Switching to another track restores it right away.
+pub fn watch(backends: &[Box<dyn Backend>], player: &str, times: u32, restore: LoopMode) -> Result<bool, PlayingError> {
+    let mut last: Option<Snapshot> = None;
+    let mut restarts = 0;
+    crate::shutdown::install();
+    loop {
+        let players = backend::players(backends)?;
+        let Some(p) = players.iter().find(|p| p.identity() == player) else { return Ok(false) };
+        // somebody changed it meanwhile, which wins
+        if p.loop_mode()? != LoopMode::Track {
+            return Ok(true)
+        }
+        let snapshot = Snapshot::take(p.as_ref())?;
+        if let Some(ref l) = last {
+            if restarted(l, &snapshot) {
+                restarts += 1;
+            }
+            // the track would loop forever once nothing watches it anymore
+            if !l.same_track(&snapshot) || restarts >= times || crate::shutdown::requested() {
+                p.set_loop_mode(restore)?;
+                return Ok(true)
+            }
+        }
+        last = Some(snapshot);
+        sleep(POLL_INTERVAL);
+    }
+}
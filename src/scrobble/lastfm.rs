@@ -0,0 +1,128 @@
+use std::time::Duration;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{Failure, Listen, Provider};
+use crate::PlayingError;
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Most scrobbles accepted by a single `track.scrobble` call.
+const BATCH_SIZE: usize = 50;
+
+/// The `[lastfm]` table of the config file. The key and secret come from an API account
+/// (https://www.last.fm/api/account/create); without a `session_key` one is requested with the
+/// username and password at startup.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LastfmConfig {
+    api_key: String,
+    api_secret: String,
+    session_key: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+pub struct Lastfm {
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl Lastfm {
+    pub fn connect(config: &LastfmConfig) -> Result<Self, PlayingError> {
+        let mut lastfm = Lastfm {
+            api_key: config.api_key.clone(),
+            api_secret: config.api_secret.clone(),
+            session_key: config.session_key.clone().unwrap_or_default(),
+        };
+        if lastfm.session_key.is_empty() {
+            let (Some(username), Some(password)) = (&config.username, &config.password) else {
+                return Err(PlayingError::config("[lastfm] needs either session_key or username and password"))
+            };
+            let session = lastfm.call("auth.getMobileSession", vec![("username", username.clone()), ("password", password.clone())], false)
+                .map_err(|e| PlayingError::scrobble(format!("cannot log in to last.fm: {}", e)))?;
+            lastfm.session_key = session["session"]["key"].as_str()
+                .ok_or_else(|| PlayingError::scrobble("last.fm returned no session key"))?
+                .to_owned();
+        }
+        Ok(lastfm)
+    }
+
+    /// Signs the parameters as described in https://www.last.fm/api/authspec#_8-signing-calls.
+    fn sign(&self, params: &[(&str, String)]) -> String {
+        let mut sorted: Vec<&(&str, String)> = params.iter().collect();
+        sorted.sort_by_key(|(k, _)| *k);
+        let mut payload: String = sorted.iter().map(|(k, v)| format!("{}{}", k, v)).collect();
+        payload.push_str(&self.api_secret);
+        format!("{:x}", md5::compute(payload))
+    }
+
+    fn call(&self, method: &str, mut params: Vec<(&str, String)>, with_session: bool) -> Result<Value, Failure> {
+        params.push(("method", method.to_owned()));
+        params.push(("api_key", self.api_key.clone()));
+        if with_session {
+            params.push(("sk", self.session_key.clone()));
+        }
+        let signature = self.sign(&params);
+        params.push(("api_sig", signature));
+        // `format` is not part of the signature
+        params.push(("format", "json".to_owned()));
+
+        let form: Vec<(&str, &str)> = params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let body = match ureq::post(API_URL).timeout(TIMEOUT).send_form(&form) {
+            Ok(r) => r.into_string().map_err(|e| Failure::Retry(e.to_string()))?,
+            // errors come with a JSON body explaining them
+            Err(ureq::Error::Status(_, r)) => r.into_string().map_err(|e| Failure::Retry(e.to_string()))?,
+            Err(e) => return Err(Failure::Retry(e.to_string())),
+        };
+        let response: Value = serde_json::from_str(&body)
+            .map_err(|_| Failure::Retry(format!("unexpected response: {}", body.trim())))?;
+        match response["error"].as_u64() {
+            None => Ok(response),
+            // service offline, temporarily unavailable or rate limited
+            Some(code @ (11 | 16 | 29)) => Err(Failure::Retry(format!("{} (error {})", response["message"].as_str().unwrap_or("unknown error"), code))),
+            Some(code) => Err(Failure::Rejected(format!("{} (error {})", response["message"].as_str().unwrap_or("unknown error"), code))),
+        }
+    }
+}
+
+impl Provider for Lastfm {
+    fn name(&self) -> &'static str {
+        "lastfm"
+    }
+
+    fn batch_size(&self) -> usize {
+        BATCH_SIZE
+    }
+
+    fn now_playing(&self, listen: &Listen) -> Result<(), Failure> {
+        let mut params = vec![("artist", listen.artist.clone()), ("track", listen.title.clone())];
+        if let Some(ref album) = listen.album {
+            params.push(("album", album.clone()));
+        }
+        if let Some(length) = listen.length {
+            params.push(("duration", length.to_string()));
+        }
+        self.call("track.updateNowPlaying", params, true).map(|_| ())
+    }
+
+    fn scrobble(&self, listens: &[Listen]) -> Result<(), Failure> {
+        let keys: Vec<[String; 5]> = (0..listens.len())
+            .map(|i| ["artist", "track", "timestamp", "album", "duration"].map(|k| format!("{}[{}]", k, i)))
+            .collect();
+        let mut params = vec![];
+        for (l, [artist, track, timestamp, album, duration]) in listens.iter().zip(&keys) {
+            params.push((artist.as_str(), l.artist.clone()));
+            params.push((track.as_str(), l.title.clone()));
+            params.push((timestamp.as_str(), l.timestamp.to_string()));
+            if let Some(ref a) = l.album {
+                params.push((album.as_str(), a.clone()));
+            }
+            if let Some(d) = l.length {
+                params.push((duration.as_str(), d.to_string()));
+            }
+        }
+        self.call("track.scrobble", params, true).map(|_| ())
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{Failure, Listen, Provider};
+
+const DEFAULT_URL: &str = "https://api.listenbrainz.org";
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Most listens accepted by a single `submit-listens` call.
+const BATCH_SIZE: usize = 1000;
+
+/// The `[listenbrainz]` table of the config file, with the user token from
+/// https://listenbrainz.org/settings/.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ListenBrainzConfig {
+    pub token: String,
+    /// Root of the API, for self-hosted instances.
+    url: Option<String>,
+}
+
+impl ListenBrainzConfig {
+    /// Root of the API, without the trailing slash.
+    pub fn api_url(&self) -> String {
+        self.url.as_deref().unwrap_or(DEFAULT_URL).trim_end_matches('/').to_owned()
+    }
+}
+
+pub struct ListenBrainz {
+    token: String,
+    url: String,
+}
+
+impl ListenBrainz {
+    pub fn new(config: &ListenBrainzConfig) -> Self {
+        ListenBrainz {
+            token: config.token.clone(),
+            url: config.api_url(),
+        }
+    }
+
+    fn submit(&self, listen_type: &str, payload: Vec<Value>) -> Result<(), Failure> {
+        let response = ureq::post(&format!("{}/1/submit-listens", self.url))
+            .timeout(TIMEOUT)
+            .set("Authorization", &format!("Token {}", self.token))
+            .send_json(json!({ "listen_type": listen_type, "payload": payload }));
+        match response {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(code, r)) => {
+                let body = r.into_string().unwrap_or_default();
+                let message = serde_json::from_str::<Value>(&body).ok()
+                    .and_then(|v| v["error"].as_str().map(str::to_owned))
+                    .unwrap_or(body);
+                // rate limited or down, anything else is about the listens themselves
+                if code == 429 || code >= 500 {
+                    Err(Failure::Retry(format!("{} (http {})", message, code)))
+                } else {
+                    Err(Failure::Rejected(format!("{} (http {})", message, code)))
+                }
+            }
+            Err(e) => Err(Failure::Retry(e.to_string())),
+        }
+    }
+}
+
+fn track_metadata(listen: &Listen) -> Value {
+    let mut info = json!({
+        "submission_client": "playing.rs",
+        "submission_client_version": env!("CARGO_PKG_VERSION"),
+    });
+    if let Some(length) = listen.length {
+        info["duration_ms"] = json!(length * 1000);
+    }
+    let mut meta = json!({
+        "artist_name": listen.artist,
+        "track_name": listen.title,
+        "additional_info": info,
+    });
+    if let Some(ref album) = listen.album {
+        meta["release_name"] = json!(album);
+    }
+    meta
+}
+
+impl Provider for ListenBrainz {
+    fn name(&self) -> &'static str {
+        "listenbrainz"
+    }
+
+    fn batch_size(&self) -> usize {
+        BATCH_SIZE
+    }
+
+    fn now_playing(&self, listen: &Listen) -> Result<(), Failure> {
+        self.submit("playing_now", vec![json!({ "track_metadata": track_metadata(listen) })])
+    }
+
+    fn scrobble(&self, listens: &[Listen]) -> Result<(), Failure> {
+        let payload = listens.iter()
+            .map(|l| json!({ "listened_at": l.timestamp, "track_metadata": track_metadata(l) }))
+            .collect();
+        self.submit(if listens.len() == 1 { "single" } else { "import" }, payload)
+    }
+}
@@ -0,0 +1,261 @@
+use std::{fmt::Display, path::PathBuf, thread::sleep, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::{self, Backend, MediaPlayer}, config::Config, PlayingError};
+
+mod lastfm;
+mod listenbrainz;
+
+pub use lastfm::LastfmConfig;
+pub use listenbrainz::ListenBrainzConfig;
+use lastfm::Lastfm;
+use listenbrainz::ListenBrainz;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Tracks shorter than this are never scrobbled.
+const MIN_LENGTH: Duration = Duration::from_secs(30);
+/// Listening time after which a track is scrobbled even when it's less than half of it.
+const MAX_THRESHOLD: Duration = Duration::from_secs(240);
+/// Time before sending the queued listens again after a failure.
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// A played track, as submitted to the scrobbling service.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// Unix time the track started playing at.
+    pub timestamp: u64,
+    /// Length of the track in seconds.
+    pub length: Option<u64>,
+}
+
+impl Listen {
+    fn is_same_track(&self, other: &Listen) -> bool {
+        self.artist == other.artist && self.title == other.title && self.album == other.album
+    }
+}
+
+/// Why a submission failed.
+pub enum Failure {
+    /// The service couldn't be reached or is temporarily unavailable.
+    Retry(String),
+    /// The service refused the submission, sending it again won't help.
+    Rejected(String),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Retry(e) | Failure::Rejected(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A service listens are submitted to.
+trait Provider {
+    /// Name used in messages and for the queue file.
+    fn name(&self) -> &'static str;
+    /// Most listens accepted by a single submission.
+    fn batch_size(&self) -> usize;
+    fn now_playing(&self, listen: &Listen) -> Result<(), Failure>;
+    fn scrobble(&self, listens: &[Listen]) -> Result<(), Failure>;
+}
+
+/// Listens that couldn't be submitted yet, kept on disk across restarts.
+struct Queue {
+    path: Option<PathBuf>,
+    listens: Vec<Listen>,
+}
+
+impl Queue {
+    fn load(name: &str) -> Queue {
+        let path = crate::store::path(&format!("{}-queue.json", name));
+        let listens = path.as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        Queue { path, listens }
+    }
+
+    fn save(&self) {
+        let Some(ref path) = self.path else { return };
+        if let Err(e) = crate::store::write_atomic(path, serde_json::to_string(&self.listens).unwrap_or_default().as_bytes()) {
+            eprintln!("cannot save the scrobble queue to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// The track being listened to and for how long it has been played.
+struct Current {
+    listen: Listen,
+    played: Duration,
+    playing: bool,
+    scrobbled: bool,
+}
+
+/// Whether a track of `length` played for `played` is to be scrobbled: for half its length or
+/// four minutes, whichever comes first.
+pub fn is_due(length: Option<Duration>, played: Duration) -> bool {
+    match length {
+        Some(l) if l < MIN_LENGTH => false,
+        Some(l) => played >= (l / 2).min(MAX_THRESHOLD),
+        // without a length only the four minutes are certain
+        None => played >= MAX_THRESHOLD,
+    }
+}
+
+impl Current {
+    fn is_due(&self) -> bool {
+        is_due(self.listen.length.map(Duration::from_secs), self.played)
+    }
+}
+
+/// Submits to a provider, queueing the listens it can't take right now.
+struct Scrobbler {
+    provider: Box<dyn Provider>,
+    queue: Queue,
+    retry_at: Instant,
+}
+
+impl Scrobbler {
+    fn new(provider: Box<dyn Provider>) -> Scrobbler {
+        let queue = Queue::load(provider.name());
+        Scrobbler { provider, queue, retry_at: Instant::now() }
+    }
+
+    fn now_playing(&self, listen: &Listen) {
+        if let Err(e) = self.provider.now_playing(listen) {
+            eprintln!("{}: cannot update now playing: {}", self.provider.name(), e);
+        }
+    }
+
+    fn scrobble(&mut self, listen: Listen) {
+        self.queue.listens.push(listen);
+        self.flush();
+    }
+
+    /// Submits the queued listens, oldest first.
+    fn flush(&mut self) {
+        let before = self.queue.listens.len();
+        while !self.queue.listens.is_empty() {
+            let n = self.queue.listens.len().min(self.provider.batch_size());
+            match self.provider.scrobble(&self.queue.listens[..n]) {
+                Ok(()) => {}
+                Err(Failure::Retry(e)) => {
+                    self.retry_at = Instant::now() + RETRY_INTERVAL;
+                    eprintln!("{}: cannot scrobble, {} listens queued: {}", self.provider.name(), self.queue.listens.len(), e);
+                    break
+                }
+                Err(Failure::Rejected(e)) => eprintln!("{}: rejected {} listens: {}", self.provider.name(), n, e),
+            }
+            self.queue.listens.drain(..n);
+        }
+        if self.queue.listens.len() != before {
+            self.queue.save();
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// What the active player is playing, if it has enough metadata to be scrobbled and isn't a
+/// livestream.
+fn playing(backends: &[Box<dyn Backend>], ranking: &[String]) -> Result<Option<Listen>, PlayingError> {
+    let players = backend::players(backends)?;
+    let Some(p) = crate::active_player(&players, ranking)? else { return Ok(None) };
+    listen(p)
+}
+
+fn listen(p: &dyn MediaPlayer) -> Result<Option<Listen>, PlayingError> {
+    let track = p.track()?;
+    if track.is_live() {
+        return Ok(None)
+    }
+    let (Some(artist), Some(title)) = (track.artist().cloned(), track.title) else { return Ok(None) };
+    let position = p.position()?.unwrap_or_default();
+    Ok(Some(Listen {
+        artist,
+        title,
+        album: track.album,
+        timestamp: now().saturating_sub(position.as_secs()),
+        length: track.length.map(|l| l.as_secs()),
+    }))
+}
+
+/// Sends the now playing notification of the active player and the queued scrobbles to every
+/// configured provider; as a daemon, keeps watching the active player and scrobbles what it plays.
+pub fn run(config: &Config, backends: &[Box<dyn Backend>], daemon: bool) -> Result<bool, PlayingError> {
+    let mut scrobblers = vec![];
+    if let Some(ref lastfm) = config.lastfm {
+        scrobblers.push(Scrobbler::new(Box::new(Lastfm::connect(lastfm)?)));
+    }
+    if let Some(ref listenbrainz) = config.listenbrainz {
+        scrobblers.push(Scrobbler::new(Box::new(ListenBrainz::new(listenbrainz))));
+    }
+    if scrobblers.is_empty() {
+        return Err(PlayingError::config("scrobbling needs a [lastfm] or [listenbrainz] table in the config"))
+    }
+    let ranking = config.ranking();
+    scrobblers.iter_mut().for_each(Scrobbler::flush);
+    if daemon {
+        crate::shutdown::install();
+    }
+
+    let mut current: Option<Current> = None;
+    let mut last_tick = Instant::now();
+    loop {
+        let elapsed = last_tick.elapsed();
+        last_tick = Instant::now();
+        match playing(backends, &ranking) {
+            Ok(Some(listen)) => match current {
+                Some(ref mut c) if c.listen.is_same_track(&listen) => {
+                    // only count time between two ticks that both saw the track playing
+                    if c.playing {
+                        c.played += elapsed;
+                    }
+                    c.playing = true;
+                }
+                _ => {
+                    scrobblers.iter().for_each(|s| s.now_playing(&listen));
+                    current = Some(Current { listen, played: Duration::ZERO, playing: true, scrobbled: false });
+                }
+            },
+            Ok(None) => {
+                if let Some(ref mut c) = current {
+                    c.playing = false;
+                }
+            }
+            Err(e) if daemon => eprintln!("cannot read the active player: {}", e.inner),
+            Err(e) => return Err(e),
+        }
+
+        if let Some(ref mut c) = current {
+            if !c.scrobbled && c.is_due() {
+                c.scrobbled = true;
+                scrobblers.iter_mut().for_each(|s| s.scrobble(c.listen.clone()));
+            }
+        }
+
+        if !daemon {
+            return Ok(current.is_some())
+        }
+        if crate::shutdown::requested() {
+            // a last try, then whatever is left waits on disk for the next start
+            for s in &mut scrobblers {
+                s.flush();
+                s.queue.save();
+            }
+            return Ok(true)
+        }
+        sleep(POLL_INTERVAL);
+        for s in &mut scrobblers {
+            if !s.queue.listens.is_empty() && Instant::now() >= s.retry_at {
+                s.flush();
+            }
+        }
+    }
+}
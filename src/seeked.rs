@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SEEKED: AtomicBool = AtomicBool::new(false);
+
+/// Interface of the signal that the seeking operations send, for the players which don't tell
+/// about their seeks themselves, e.g. mpv over IPC or mpd.
+#[cfg(all(unix, not(target_os = "macos")))]
+const INTERFACE: &str = "org.playing.Events";
+
+/// Makes `status --follow` learn of seeks as they happen, through the `Seeked` signals of the
+/// MPRIS players and of the seeking operations, however short the seek; see [`take`].
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn listen() {
+    use std::time::Duration;
+    use dbus::{blocking::Connection, channel::MatchingReceiver, message::MatchRule};
+
+    std::thread::spawn(|| {
+        let conn = match Connection::new_session() {
+            Ok(conn) => conn,
+            // seeks are still told apart from playback on the next poll, when long enough
+            Err(e) => return eprintln!("cannot listen for seeks on the session bus: {}", e),
+        };
+        for interface in ["org.mpris.MediaPlayer2.Player", INTERFACE] {
+            let rule = MatchRule::new_signal(interface, "Seeked");
+            if let Err(e) = conn.add_match_no_cb(&rule.match_str()) {
+                return eprintln!("cannot listen for seeks on the session bus: {}", e);
+            }
+            conn.start_receive(rule, Box::new(|_, _| {
+                SEEKED.store(true, Ordering::SeqCst);
+                crate::refresh::wake();
+                true
+            }));
+        }
+        while conn.process(Duration::from_secs(60)).is_ok() {}
+    });
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn listen() {}
+
+/// Tells the followers that the player `identity` was seeked; failing to only delays their line
+/// until the next poll.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn announce(identity: &str) {
+    use dbus::{blocking::Connection, channel::Sender, Message};
+
+    let Ok(conn) = Connection::new_session() else { return };
+    let Ok(signal) = Message::new_signal("/org/playing/Events", INTERFACE, "Seeked") else { return };
+    let _ = conn.send(signal.append1(identity));
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn announce(_: &str) {}
+
+/// Whether a seek was signaled since the last call.
+pub fn take() -> bool {
+    SEEKED.swap(false, Ordering::SeqCst)
+}
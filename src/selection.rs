@@ -0,0 +1,120 @@
+use std::{collections::HashMap, io::{BufRead, IsTerminal, Write}, process::{Command, Stdio}};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{backend::MediaPlayer, config::Config, recent, PlayingError};
+
+/// How the player a command acts on is picked among the running ones of the ranking.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelectionPolicy {
+    /// The first running player of the ranking, even when one below it is the one playing
+    StaticRanking,
+    /// The player that last changed playback state, else the first running one of the ranking
+    LastActive,
+    /// A playing player of the ranking before a paused one ranked above it
+    #[default]
+    PlayingFirst,
+    /// Ask which of the running players, on the terminal or with `[selection] picker`
+    Interactive,
+}
+
+impl SelectionPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectionPolicy::StaticRanking => "static-ranking",
+            SelectionPolicy::LastActive => "last-active",
+            SelectionPolicy::PlayingFirst => "playing-first",
+            SelectionPolicy::Interactive => "interactive",
+        }
+    }
+}
+
+/// The `[selection]` table of the config file, e.g.
+///
+/// ```toml
+/// [selection]
+/// policy = "last-active"
+/// picker = "rofi -dmenu -p player"
+///
+/// [selection.actions]
+/// toggle = "interactive"
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectionConfig {
+    /// Policy of the commands `actions` misses, else `playing-first`.
+    pub policy: Option<SelectionPolicy>,
+    /// Policies by command, named as in `[access]`, e.g. `toggle` or `status`.
+    pub actions: HashMap<String, SelectionPolicy>,
+    /// Command reading the running players on stdin, one per line, and printing the chosen one,
+    /// e.g. `fzf` or `rofi -dmenu`; else `interactive` asks on the terminal.
+    pub picker: Option<String>,
+    /// Policy of `--selection` or `--strict-ranking`, over every other one.
+    #[serde(skip)]
+    pub forced: Option<SelectionPolicy>,
+}
+
+/// The ranking that `policy` walks among `players`: the last active player first, or only the
+/// one the user picked. `--player` takes precedence over every policy.
+pub fn ranking(config: &Config, policy: SelectionPolicy, players: &[Box<dyn MediaPlayer>]) -> Result<Vec<String>, PlayingError> {
+    let ranking = config.ranking();
+    if config.player.is_some() {
+        return Ok(ranking)
+    }
+    match policy {
+        SelectionPolicy::StaticRanking | SelectionPolicy::PlayingFirst => Ok(ranking),
+        SelectionPolicy::LastActive => match recent::last_active() {
+            Some(last) if players.iter().any(|p| p.identity() == last) => {
+                Ok(std::iter::once(last.clone()).chain(ranking.into_iter().filter(|id| *id != last)).collect())
+            }
+            // no player was active yet, or it's gone
+            _ => Ok(ranking),
+        },
+        SelectionPolicy::Interactive => {
+            let candidates: Vec<&dyn MediaPlayer> = crate::numbered(players).into_iter()
+                .filter(|p| ranking.iter().any(|id| crate::picks(id, *p)))
+                .collect();
+            let picked = match candidates.len() {
+                0 | 1 => candidates.first().copied(),
+                _ => ask(config, &candidates)?,
+            };
+            Ok(picked.map(|p| p.bus_name().unwrap_or(p.identity()).to_owned()).into_iter().collect())
+        }
+    }
+}
+
+/// Which of `candidates` the user picks, none if they cancel.
+fn ask<'a>(config: &Config, candidates: &[&'a dyn MediaPlayer]) -> Result<Option<&'a dyn MediaPlayer>, PlayingError> {
+    let mut lines = vec![];
+    for (i, p) in candidates.iter().enumerate() {
+        lines.push(format!("{}  {}  {}", i + 1, p.identity(), p.status()?.name()));
+    }
+    let answer = match config.selection.picker {
+        Some(ref picker) => {
+            let mut child = Command::new("sh")
+                .args(["-c", picker])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(lines.join("\n").as_bytes())?;
+            }
+            // a cancelled picker exits with an error and prints nothing
+            String::from_utf8_lossy(&child.wait_with_output()?.stdout).into_owned()
+        }
+        None if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() => {
+            eprintln!("{}", lines.join("\n"));
+            eprint!("player? ");
+            let mut answer = String::new();
+            std::io::stdin().lock().read_line(&mut answer)?;
+            answer
+        }
+        None => return Err(PlayingError::config("interactive selection needs a terminal, or a picker in [selection]")),
+    };
+    let Some(number) = answer.split_whitespace().next() else { return Ok(None) };
+    match number.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| candidates.get(i)) {
+        Some(p) => Ok(Some(*p)),
+        None => Err(PlayingError::bad_command(format!("there's no player number {}", number))),
+    }
+}
@@ -0,0 +1,269 @@
+use std::{collections::VecDeque, io::Write, net::SocketAddr, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{access::Source, backend::{self, Backend, BackendKind}, config::Config, follow::{self, Snapshot}, status::StatusOptions, PlayingError, PlayingErrorKind};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Comments sent on quiet streams, so that closed ones are noticed.
+const HEARTBEAT: Duration = Duration::from_secs(15);
+/// Events kept for the clients that reconnect, e.g. after a suspend.
+const BACKLOG: usize = 100;
+/// How long clients wait before reconnecting, in milliseconds.
+const RETRY: u64 = 2000;
+/// Size of the largest cover art served, in bytes.
+const MAX_ART_SIZE: u64 = 16 << 20;
+
+fn json_response(status: u16, body: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("valid header"))
+}
+
+fn error_response(e: &PlayingError) -> Response<std::io::Cursor<Vec<u8>>> {
+    let status = match e.kind {
+        PlayingErrorKind::Denied => 403,
+        PlayingErrorKind::BadCommand => 400,
+        PlayingErrorKind::Unsupported => 501,
+        _ => 500,
+    };
+    json_response(status, &json!({ "error": e.inner.to_string() }))
+}
+
+/// The page of `GET /ui`, a remote for phones and other browsers.
+const UI: &str = include_str!("ui.html");
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+/// The type of the image in `data`, told by its first bytes, none when it's no image.
+fn image_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// The image at the local `path`, none when it's anything else: the players set the URL of the
+/// art, which would otherwise let them have any file read by the clients.
+fn local_art(path: &str) -> Result<Option<(Vec<u8>, &'static str)>, PlayingError> {
+    let Ok(path) = std::fs::canonicalize(path) else { return Ok(None) };
+    let image = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase)
+        .is_some_and(|e| matches!(e.as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp"));
+    let metadata = std::fs::metadata(&path)?;
+    if !image || !metadata.is_file() || metadata.len() > MAX_ART_SIZE {
+        return Ok(None)
+    }
+    let data = std::fs::read(&path)?;
+    Ok(image_type(&data).map(|content_type| (data, content_type)))
+}
+
+/// The cover art of the current track: the file itself when local, else a redirect to it.
+fn art(snapshot: Option<&Snapshot>) -> Result<Response<std::io::Cursor<Vec<u8>>>, PlayingError> {
+    let no_art = || json_response(404, &json!({ "error": "no cover art" }));
+    let Some(url) = snapshot.and_then(|s| s.track.art_url.as_deref()) else { return Ok(no_art()) };
+    if !url.starts_with("file://") {
+        return Ok(Response::from_data(vec![]).with_status_code(302).with_header(header("Location", url)))
+    }
+    Ok(match local_art(&follow::art_path(url))? {
+        Some((data, content_type)) => Response::from_data(data).with_header(header("Content-Type", content_type)),
+        None => no_art(),
+    })
+}
+
+fn state(snapshot: Option<&Snapshot>, opts: &StatusOptions) -> Value {
+    snapshot.map_or_else(Snapshot::stopped, |s| s.json(opts))
+}
+
+fn players(backends: &[Box<dyn Backend>]) -> Result<Value, PlayingError> {
+    let players = backend::players(backends)?;
+    Ok(players.iter().map(|p| json!({ "player": p.identity(), "status": p.status().ok().map(|s| s.name()) })).collect())
+}
+
+/// Clients of `GET /events`, each sent a server-sent event for every change of the current player.
+///
+/// Events have ids of the start time of the server and a sequence number, e.g. `1718000000-42`,
+/// so that a client reconnecting with the last one it got (as `Last-Event-ID`) is sent the
+/// events it missed, or the current state if they're no longer kept or the server restarted.
+struct Streams {
+    clients: Vec<Box<dyn Write + Send>>,
+    last: Option<Snapshot>,
+    sent: Option<Instant>,
+    started: u64,
+    seq: u64,
+    backlog: VecDeque<(u64, String)>,
+}
+
+impl Streams {
+    fn new() -> Streams {
+        Streams {
+            clients: vec![],
+            last: None,
+            sent: None,
+            started: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            seq: 0,
+            backlog: VecDeque::new(),
+        }
+    }
+
+    /// The events after the one of id `last_id`, none when they can't be told.
+    fn missed(&self, last_id: &str) -> Option<Vec<&str>> {
+        let (started, seq) = last_id.split_once('-')?;
+        let seq: u64 = seq.parse().ok()?;
+        if started.parse() != Ok(self.started) || seq > self.seq {
+            return None
+        }
+        // the backlog must still hold the event right after it
+        if seq < self.seq && self.backlog.front().is_none_or(|(first, _)| *first > seq + 1) {
+            return None
+        }
+        Some(self.backlog.iter().filter(|(s, _)| *s > seq).map(|(_, m)| m.as_str()).collect())
+    }
+
+    /// Takes over the connection of `request`, starting the stream with the events the client
+    /// missed or the current state.
+    fn open(&mut self, request: Request, snapshot: Option<Snapshot>, opts: &StatusOptions) {
+        let last_id = request.headers().iter()
+            .find(|h| h.field.equiv("Last-Event-ID"))
+            .map(|h| h.value.as_str().to_owned());
+        let messages = match last_id.as_deref().and_then(|id| self.missed(id)) {
+            Some(missed) => missed.concat(),
+            None => format!("id: {}-{}\nevent: state\ndata: {}\n\n", self.started, self.seq, state(snapshot.as_ref(), opts)),
+        };
+        let mut client = request.into_writer();
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\n\r\nretry: {}\n\n", RETRY);
+        if client.write_all(head.as_bytes()).and_then(|_| client.write_all(messages.as_bytes())).and_then(|_| client.flush()).is_ok() {
+            self.clients.push(client);
+            self.last = snapshot;
+        }
+    }
+
+    /// Sends the event `name` with `data` to every client, keeping it for the ones that reconnect.
+    fn event(&mut self, name: &str, data: &Value) {
+        self.seq += 1;
+        let message = format!("id: {}-{}\nevent: {}\ndata: {}\n\n", self.started, self.seq, name, data);
+        if self.backlog.len() == BACKLOG {
+            self.backlog.pop_front();
+        }
+        self.backlog.push_back((self.seq, message.clone()));
+        self.send(&message);
+    }
+
+    /// Sends `message` to every client, dropping the ones that went away.
+    fn send(&mut self, message: &str) {
+        self.clients.retain_mut(|c| c.write_all(message.as_bytes()).and_then(|_| c.flush()).is_ok());
+        self.sent = Some(Instant::now());
+    }
+
+    fn poll(&mut self, backends: &[Box<dyn Backend>], ranking: &[String], opts: &StatusOptions) {
+        match follow::current(backends, ranking) {
+            Ok(next) => {
+                if let Some(e) = follow::event(self.last.as_ref(), next.as_ref()) {
+                    self.event(e, &state(next.as_ref(), opts));
+                }
+                self.last = next;
+            }
+            Err(e) => eprintln!("cannot read the active player: {}", e.inner),
+        }
+        if !self.clients.is_empty() && self.sent.is_none_or(|s| s.elapsed() >= HEARTBEAT) {
+            self.send(":\n\n");
+        }
+    }
+}
+
+/// Whether a `POST` comes from the page of `GET /ui` or from outside of browsers, which send no
+/// `Origin`: any other page could otherwise run operations from the browser of a user.
+fn same_origin(request: &Request) -> bool {
+    let value = |name: &'static str| request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str());
+    match (value("Origin"), value("Host")) {
+        (None, _) => true,
+        (Some(origin), Some(host)) => origin.split_once("://").is_some_and(|(_, o)| o.eq_ignore_ascii_case(host)),
+        (Some(_), None) => false,
+    }
+}
+
+/// The operation of a `POST`: the path names it and its arguments follow, as further path
+/// segments or in the body, e.g. `POST /seek/30` or `POST /rewind` with `10` as body.
+fn operation(request: &mut Request) -> Result<String, PlayingError> {
+    let mut line = request.url().split('?').next().unwrap_or_default().split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ");
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body)?;
+    line.push(' ');
+    line.push_str(&body);
+    Ok(line)
+}
+
+/// Serves the state of the players and runs operations over HTTP:
+///
+/// - `GET /status`: the current player, its track and position as JSON
+/// - `GET /players`: every player and its status
+/// - `GET /art`: the cover art of the current track
+/// - `GET /ui`: a page showing the current track with buttons to control it
+/// - `GET /events`: server-sent events named after what changed (`track`, `playing`, `seek`, ...),
+///   each with the new state as data; clients reconnecting with `Last-Event-ID` get the ones
+///   they missed
+/// - `POST /<operation>[/<args>]`: runs an operation, e.g. `POST /toggle` or `POST /seek/30`
+///
+/// Web pages elsewhere may read the state, but only `GET /ui` runs operations from browsers.
+pub async fn run(config: &Config, kind: BackendKind, backends: &[Box<dyn Backend>], addr: SocketAddr) -> Result<bool, PlayingError> {
+    let server = Server::http(addr).map_err(|e| PlayingError::from(e as Box<dyn std::error::Error>))?;
+    let ranking = config.ranking();
+    let opts = StatusOptions::published(config)?;
+    eprintln!("listening on http://{}", addr);
+    let mut streams = Streams::new();
+    crate::shutdown::install();
+    loop {
+        streams.poll(backends, &ranking, &opts);
+        crate::timing::tick();
+        if crate::shutdown::requested() {
+            // so that clients tell it from a dropped connection and don't reconnect right away
+            streams.send("event: shutdown\ndata: {}\n\n");
+            return Ok(true)
+        }
+        let Some(mut request) = server.recv_timeout(POLL_INTERVAL)? else { continue };
+        let path = request.url().split('?').next().unwrap_or_default().trim_end_matches('/').to_owned();
+        let shared = *request.method() == Method::Get;
+        let response = match (request.method(), path.as_str()) {
+            (Method::Get, "/status") => config.access.check_names(Source::Http, &["status"], true)
+                .and_then(|_| follow::current(backends, &ranking))
+                .map(|s| json_response(200, &state(s.as_ref(), &opts))),
+            (Method::Get, "/events") => match config.access.check_names(Source::Http, &["status"], true)
+                .and_then(|_| follow::current(backends, &ranking))
+            {
+                Ok(snapshot) => {
+                    streams.open(request, snapshot, &opts);
+                    continue
+                }
+                Err(e) => Err(e),
+            },
+            (Method::Get, "/art") => config.access.check_names(Source::Http, &["status"], true)
+                .and_then(|_| follow::current(backends, &ranking))
+                .and_then(|s| art(s.as_ref())),
+            (Method::Get, "/ui" | "") => config.access.check_names(Source::Http, &["status"], true)
+                .map(|_| Response::from_string(UI).with_header(header("Content-Type", "text/html; charset=utf-8"))),
+            (Method::Get, "/players") => config.access.check_names(Source::Http, &["player"], true)
+                .and_then(|_| players(backends))
+                .map(|p| json_response(200, &p)),
+            (Method::Post, _) if !same_origin(&request) => Err(PlayingError::denied("operations are only run from the page of /ui")),
+            (Method::Post, _) => match operation(&mut request).and_then(|line| crate::Remote::parse_line(&line)) {
+                // boxed, as dispatching is what started this loop
+                Ok(action) => Box::pin(crate::dispatch(config, kind, Source::Http, action)).await
+                    .map(|_| json_response(200, &json!({ "ok": true }))),
+                Err(e) => Err(e),
+            },
+            _ => Ok(json_response(404, &json!({ "error": "not found" }))),
+        };
+        let mut response = response.unwrap_or_else(|e| error_response(&e));
+        if shared {
+            // lets web pages elsewhere on the LAN read the state
+            response.add_header(header("Access-Control-Allow-Origin", "*"));
+        }
+        if let Err(e) = request.respond(response) {
+            eprintln!("cannot answer a request: {}", e);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::{ffi::CString, sync::mpsc, time::Duration};
+use dbus::{blocking::{stdintf::org_freedesktop_dbus::RequestNameReply, LocalConnection}, channel::{MatchingReceiver, Sender}, message::MatchRule, strings::ErrorName, Message};
+
+use crate::{access::Source, backend::{self, BackendKind}, config::Config, follow::{self, Snapshot}, status::StatusOptions, PlayingError, PlayingErrorKind};
+
+const NAME: &str = "org.playing.Manager";
+const INTERFACE: &str = "org.playing.Manager";
+
+const INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="org.playing.Manager">
+    <method name="Toggle"/>
+    <method name="Play"/>
+    <method name="Pause"/>
+    <method name="Next"/>
+    <method name="Previous"/>
+    <method name="Command">
+      <arg name="command" type="s" direction="in"/>
+    </method>
+    <method name="Status">
+      <arg name="status" type="s" direction="out"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="xml" type="s" direction="out"/>
+    </method>
+  </interface>
+</node>"#;
+
+fn dbus_error(e: dbus::Error) -> PlayingError {
+    PlayingError { kind: PlayingErrorKind::DBus, code: 2, inner: e.into() }
+}
+
+fn error_reply(call: &Message, name: &str, message: &str) -> Message {
+    call.error(&ErrorName::from(name), &CString::new(message.replace('\0', "")).unwrap_or_default())
+}
+
+fn failure(call: &Message, e: &PlayingError) -> Message {
+    let name = match e.kind {
+        PlayingErrorKind::Denied => "org.freedesktop.DBus.Error.AccessDenied",
+        PlayingErrorKind::BadCommand => "org.freedesktop.DBus.Error.InvalidArgs",
+        PlayingErrorKind::Unsupported => "org.freedesktop.DBus.Error.NotSupported",
+        _ => "org.freedesktop.DBus.Error.Failed",
+    };
+    error_reply(call, name, &e.inner.to_string())
+}
+
+async fn answer(config: &Config, kind: BackendKind, call: &Message) -> Result<Message, PlayingError> {
+    let member = call.member().map(|m| m.to_string()).unwrap_or_default();
+    // the interface is optional in method calls
+    let interface = call.interface().map_or(INTERFACE.to_owned(), |i| i.to_string());
+    let line = match (interface.as_str(), member.as_str()) {
+        ("org.freedesktop.DBus.Introspectable", "Introspect") => return Ok(call.method_return().append1(INTROSPECTION)),
+        (INTERFACE, "Status") => {
+            config.access.check_names(Source::Dbus, &["status"], true)?;
+            let backends = backend::open(kind, config)?;
+            let snapshot = follow::current(&backends, &config.ranking())?;
+            let opts = StatusOptions::published(config)?;
+            return Ok(call.method_return().append1(snapshot.as_ref().map_or_else(Snapshot::stopped, |s| s.json(&opts)).to_string()))
+        }
+        (INTERFACE, "Command") => call.read1::<&str>().map_err(|e| PlayingError::bad_command(e.to_string()))?.to_owned(),
+        (INTERFACE, "Toggle" | "Play" | "Pause" | "Next" | "Previous") => member.to_lowercase(),
+        _ => return Ok(error_reply(call, "org.freedesktop.DBus.Error.UnknownMethod", &format!("no method {}.{}", interface, member))),
+    };
+    let action = crate::Remote::parse_line(&line)?;
+    // boxed, as dispatching is what started this loop
+    Box::pin(crate::dispatch(config, kind, Source::Dbus, action)).await?;
+    Ok(call.method_return())
+}
+
+/// Serves `org.playing.Manager` on the session bus, so that other programs get the ranking's
+/// pick of player without running this binary: `Toggle`, `Play`, `Pause`, `Next` and
+/// `Previous`, `Command` taking any operation (e.g. `seek 30`), and `Status` returning the
+/// current player's state as JSON.
+pub async fn run(config: &Config, kind: BackendKind) -> Result<bool, PlayingError> {
+    let conn = LocalConnection::new_session().map_err(dbus_error)?;
+    if conn.request_name(NAME, false, true, true).map_err(dbus_error)? == RequestNameReply::Exists {
+        return Err(PlayingError { kind: PlayingErrorKind::DBus, code: 2, inner: format!("{} is already served on the session bus", NAME).into() })
+    }
+    let (tx, calls) = mpsc::channel();
+    conn.start_receive(MatchRule::new_method_call(), Box::new(move |call, _| tx.send(call).is_ok()));
+    eprintln!("serving {} on the session bus", NAME);
+    loop {
+        conn.process(Duration::from_secs(1)).map_err(dbus_error)?;
+        while let Ok(call) = calls.try_recv() {
+            let reply = answer(config, kind, &call).await.unwrap_or_else(|e| failure(&call, &e));
+            if !call.get_no_reply() && conn.send(reply).is_err() {
+                eprintln!("cannot answer a call to {}", call.member().map(|m| m.to_string()).unwrap_or_default());
+            }
+        }
+    }
+}
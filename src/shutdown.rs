@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn request(_: libc::c_int) {
+    // a second signal means the flush is taking too long
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) }
+    }
+}
+
+/// Makes SIGTERM and SIGINT ask the daemon to stop, see [`requested`], instead of killing it
+/// right away; a second one still does.
+#[cfg(unix)]
+pub fn install() {
+    let handler = request as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// Whether the daemon was asked to stop, after which it saves what it holds and returns.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,60 @@
+use std::{io::{BufRead, BufReader}, process::{Command, Stdio}};
+use serde::Deserialize;
+
+use crate::{backend::{self, BackendKind, Status}, config::Config, format, PlayingError};
+
+/// The `[sink]` table of the config file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SinkConfig {
+    /// Identities of the players paused when the audio output goes away, every player when empty.
+    pub players: Vec<String>,
+}
+
+fn default_sink() -> Option<String> {
+    let out = Command::new("pactl").arg("get-default-sink").output().ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+fn pause(config: &Config, kind: BackendKind, why: &str) -> Result<(), PlayingError> {
+    // opened at every change, as mpd drops idle connections
+    let backends = backend::open(kind, config)?;
+    for p in backend::players(&backends)? {
+        let wanted = config.sink.players.is_empty() || config.sink.players.iter().any(|id| format::selects(id, p.identity()));
+        if wanted && p.status()? == Status::Playing {
+            p.pause()?;
+            println!("{}, pausing {}", why, p.identity());
+        }
+    }
+    Ok(())
+}
+
+/// Pauses the `[sink]` players when the default audio output changes or goes away, e.g. when
+/// headphones are unplugged or a Bluetooth headset disconnects, so they don't go on playing
+/// through the speakers.
+///
+/// Follows `pactl subscribe`, which PulseAudio and PipeWire (through pipewire-pulse) both serve.
+pub fn run(config: &Config, kind: BackendKind) -> Result<bool, PlayingError> {
+    let mut default = default_sink()
+        .ok_or_else(|| PlayingError::unsupported("cannot get the default sink, is pactl installed and the sound server running?"))?;
+    let mut pactl = Command::new("pactl").arg("subscribe").stdout(Stdio::piped()).spawn()?;
+    let events = BufReader::new(pactl.stdout.take().expect("piped stdout"));
+    for line in events.lines() {
+        let line = line?;
+        // e.g. `Event 'remove' on sink #57` or `Event 'change' on server #-1`; a removed sink
+        // other than the default one doesn't matter, else the server falls back to another one
+        if !line.contains("'remove' on sink ") && !line.contains("'change' on server") {
+            continue
+        }
+        let Some(now) = default_sink() else { continue };
+        if now == default {
+            continue
+        }
+        if let Err(e) = pause(config, kind, &format!("the audio output changed to {}", now)) {
+            eprintln!("cannot pause the players: {}", e.inner);
+        }
+        default = now;
+    }
+    let _ = pactl.wait();
+    Err(PlayingError::unsupported("pactl subscribe stopped, the sound server went away"))
+}
@@ -0,0 +1,130 @@
+use std::{thread::sleep, time::Duration};
+use serde::Deserialize;
+
+use crate::{backend::{self, Backend, MediaPlayer}, config::Config, follow::Snapshot, format::{self, DurationSpec}, sponsorblock::SponsorBlock, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What to skip in some tracks, from the `[[skip]]` tables of the config file:
+///
+/// ```toml
+/// [[skip]]
+/// url = "*example-podcast*"
+/// intro = "90s"
+/// chapters = ["Sponsor*", "Ad break"]
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SkipRule {
+    /// Identity of the player, e.g. `mpv`.
+    player: Option<String>,
+    /// Pattern of the URL, where `*` matches anything.
+    url: Option<String>,
+    /// Length of the intro, skipped when a track starts within it.
+    intro: Option<DurationSpec>,
+    /// Length of the outro, skipped to the next track once reached.
+    outro: Option<DurationSpec>,
+    /// Patterns of the titles of the chapters to skip.
+    #[serde(default)]
+    chapters: Vec<String>,
+}
+
+impl SkipRule {
+    fn applies(&self, player: &str, url: Option<&str>) -> bool {
+        self.player.as_ref().is_none_or(|p| p == player)
+            && self.url.as_ref().is_none_or(|pattern| url.is_some_and(|u| format::glob(pattern, u)))
+    }
+}
+
+/// What was already skipped in the current track, so that seeking back into it is respected.
+#[derive(Default)]
+struct Skipped {
+    intro: bool,
+    outro: bool,
+    chapters: Vec<usize>,
+    segments: Vec<String>,
+}
+
+/// Skips what the rules and SponsorBlock say in the active player's tracks.
+pub fn run(config: &Config, backends: &[Box<dyn Backend>]) -> Result<bool, PlayingError> {
+    if config.skip.is_empty() && config.sponsorblock.is_none() {
+        return Err(PlayingError::config("skipping needs [[skip]] rules or a [sponsorblock] table in the config"))
+    }
+    let sponsorblock = config.sponsorblock.as_ref().map(SponsorBlock::new);
+    let ranking = config.ranking();
+    let mut last: Option<Snapshot> = None;
+    let mut skipped = Skipped::default();
+    loop {
+        let players = backend::players(backends);
+        let result = players.and_then(|players| {
+            let Some(p) = crate::active_player(&players, &ranking)? else { return Ok(None) };
+            let snapshot = Snapshot::take(p)?;
+            if !last.as_ref().is_some_and(|l| l.same_track(&snapshot)) {
+                skipped = Skipped::default();
+            }
+            apply(p, &snapshot, &config.skip, sponsorblock.as_ref(), &mut skipped)?;
+            Ok(Some(snapshot))
+        });
+        match result {
+            Ok(snapshot) => last = snapshot,
+            Err(e) => eprintln!("cannot read the active player: {}", e.inner),
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+fn apply(p: &dyn MediaPlayer, snapshot: &Snapshot, rules: &[SkipRule], sponsorblock: Option<&SponsorBlock>, skipped: &mut Skipped) -> Result<(), PlayingError> {
+    let Some(position) = snapshot.position else { return Ok(()) };
+    if snapshot.track.is_live() {
+        return Ok(())
+    }
+
+    if let (Some(sponsorblock), Some(url)) = (sponsorblock, snapshot.track.url.as_deref()) {
+        let segments = sponsorblock.segments(url);
+        if let Some(s) = segments.iter().find(|s| s.start <= position && position < s.end && !skipped.segments.contains(&s.id)) {
+            skipped.segments.push(s.id.clone());
+            println!("{}: skipping a {} segment", p.identity(), s.category);
+            return p.set_position(s.end)
+        }
+    }
+
+    let rules: Vec<&SkipRule> = rules.iter().filter(|r| r.applies(p.identity(), snapshot.track.url.as_deref())).collect();
+    if rules.is_empty() {
+        return Ok(())
+    }
+
+    // only when the track starts in the intro, not when seeking back to it later
+    if !skipped.intro {
+        skipped.intro = true;
+        if let Some(DurationSpec(intro)) = rules.iter().find_map(|r| r.intro) {
+            if position < intro {
+                println!("{}: skipping the intro", p.identity());
+                return p.set_position(intro)
+            }
+        }
+    }
+
+    if let (false, Some(length), Some(DurationSpec(outro))) = (skipped.outro, snapshot.track.plausible_length(), rules.iter().find_map(|r| r.outro)) {
+        if position + outro >= length {
+            skipped.outro = true;
+            println!("{}: skipping the outro", p.identity());
+            return p.next()
+        }
+    }
+
+    if rules.iter().all(|r| r.chapters.is_empty()) {
+        return Ok(())
+    }
+    let chapters = p.chapters()?;
+    let Some(current) = chapters.iter().rposition(|c| c.start <= position) else { return Ok(()) };
+    let title = chapters[current].title.as_deref().unwrap_or_default();
+    if skipped.chapters.contains(&current) || !rules.iter().any(|r| r.chapters.iter().any(|c| format::glob(c, title))) {
+        return Ok(())
+    }
+    skipped.chapters.push(current);
+    println!("{}: skipping the chapter `{}`", p.identity(), title);
+    match chapters.get(current + 1) {
+        Some(next) => p.set_position(next.start),
+        None => p.next(),
+    }
+}
@@ -0,0 +1,105 @@
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+use serde::Deserialize;
+use serde_json::Value;
+
+const DEFAULT_URL: &str = "https://sponsor.ajay.app";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The `[sponsorblock]` table of the config file.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SponsorBlockConfig {
+    /// Categories to skip, see https://wiki.sponsor.ajay.app/w/Types#Category.
+    categories: Vec<String>,
+    /// Root of the API, for mirrors.
+    url: String,
+}
+
+impl Default for SponsorBlockConfig {
+    fn default() -> Self {
+        SponsorBlockConfig { categories: vec!["sponsor".to_owned()], url: DEFAULT_URL.to_owned() }
+    }
+}
+
+/// A part of a video to skip.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub id: String,
+    pub category: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// The id of the YouTube video at `url`, if it is one.
+fn video_id(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    let (host, path) = rest.split_once('/')?;
+    let host = host.trim_start_matches("www.").trim_start_matches("m.").trim_start_matches("music.");
+    let id = match host {
+        "youtu.be" => path,
+        "youtube.com" => match path.split_once('?') {
+            Some(("watch", query)) => query.split('&').find_map(|kv| kv.strip_prefix("v="))?,
+            _ => path.strip_prefix("shorts/").or_else(|| path.strip_prefix("embed/")).or_else(|| path.strip_prefix("live/"))?,
+        },
+        _ => return None,
+    };
+    let id = id.split(['?', '&', '#', '/']).next()?;
+    (!id.is_empty()).then_some(id)
+}
+
+/// Segments of YouTube videos, fetched once per video.
+pub struct SponsorBlock<'a> {
+    config: &'a SponsorBlockConfig,
+    cache: RefCell<HashMap<String, Vec<Segment>>>,
+}
+
+impl<'a> SponsorBlock<'a> {
+    pub fn new(config: &'a SponsorBlockConfig) -> Self {
+        SponsorBlock { config, cache: RefCell::new(HashMap::new()) }
+    }
+
+    fn fetch(&self, id: &str) -> Result<Vec<Segment>, String> {
+        let categories = serde_json::to_string(&self.config.categories).unwrap_or_default();
+        let response = ureq::get(&format!("{}/api/skipSegments", self.config.url.trim_end_matches('/')))
+            .timeout(TIMEOUT)
+            .query("videoID", id)
+            .query("categories", &categories)
+            .call();
+        let body: Value = match response {
+            Ok(r) => r.into_json().map_err(|e| e.to_string())?,
+            // no segments for this video
+            Err(ureq::Error::Status(404, _)) => return Ok(vec![]),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(body.as_array().into_iter().flatten().filter_map(|s| {
+            // mute and chapter segments aren't meant to be skipped
+            if s["actionType"].as_str().is_some_and(|a| a != "skip") {
+                return None
+            }
+            let start = s["segment"][0].as_f64()?.max(0.0);
+            let end = s["segment"][1].as_f64()?.max(start);
+            // whatever the API answers, e.g. times longer than a duration holds
+            Some(Segment {
+                id: s["UUID"].as_str().unwrap_or_default().to_owned(),
+                category: s["category"].as_str().unwrap_or_default().to_owned(),
+                start: Duration::try_from_secs_f64(start).ok()?,
+                end: Duration::try_from_secs_f64(end).ok()?,
+            })
+        }).collect())
+    }
+
+    /// The segments to skip in the video at `url`, none when it's not on YouTube.
+    pub fn segments(&self, url: &str) -> Vec<Segment> {
+        let Some(id) = video_id(url) else { return vec![] };
+        if let Some(segments) = self.cache.borrow().get(id) {
+            return segments.clone()
+        }
+        let segments = self.fetch(id).unwrap_or_else(|e| {
+            // not retried until the next run, asking every second wouldn't help
+            eprintln!("cannot get the sponsorblock segments of {}: {}", id, e);
+            vec![]
+        });
+        self.cache.borrow_mut().insert(id.to_owned(), segments.clone());
+        segments
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use clap::Subcommand;
+use rspotify::{model::{ArtistId, Device, Market, PlayableId, PlayableItem, PlaylistId, RecommendationsAttribute, SearchResult, SearchType, TrackId}, prelude::{BaseClient, Id, OAuthClient}, AuthCodeSpotify};
+
+use crate::PlayingError;
+
+/// Playlists fetched per request when looking one up by name, the most the API allows.
+const PAGE_SIZE: u32 = 50;
+
+#[derive(Subcommand, Debug)]
+pub enum PlaylistCommand {
+    /// Adds the track Spotify plays to the playlist of the user with this name
+    Add {
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SpotifyCommand {
+    /// Lists the Spotify Connect devices, the one playing marked with `*`
+    Devices,
+    /// Moves playback to another device, by name or id, e.g. `kitchen`
+    Transfer {
+        device: String,
+        /// Start playing there even if it's paused now
+        #[arg(long)]
+        play: bool,
+    },
+    /// Searches for a track and lines it up after the one playing, e.g. `queue daft punk one more time`
+    Queue {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Queues tracks like the one playing after it, as the radio of the app does
+    Radio {
+        /// How many tracks to queue, at most 100
+        #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..=100))]
+        limit: u32,
+    },
+}
+
+pub fn spotify_error(e: rspotify::ClientError) -> PlayingError {
+    PlayingError::from_spotifav(Box::new(e))
+}
+
+/// The track Spotify plays, none when it plays something else, e.g. an episode or a local file.
+async fn current_track(cli: &AuthCodeSpotify) -> Result<Option<TrackId<'static>>, PlayingError> {
+    let playing = cli.current_user_playing_item().await.map_err(spotify_error)?;
+    let Some(PlayableItem::Track(track)) = playing.and_then(|p| p.item) else { return Ok(None) };
+    Ok(track.id)
+}
+
+/// Whether the track Spotify plays is in the user's saved tracks, none when it plays no track.
+pub async fn is_saved(cli: &AuthCodeSpotify) -> Result<Option<bool>, PlayingError> {
+    let Some(id) = current_track(cli).await? else { return Ok(None) };
+    let saved = cli.current_user_saved_tracks_contains([id]).await.map_err(spotify_error)?;
+    Ok(saved.first().copied())
+}
+
+fn cache_path() -> Option<std::path::PathBuf> {
+    crate::store::path("spotify-playlists.json")
+}
+
+/// The ids of the user's playlists by name, as last fetched.
+fn cached_playlists() -> HashMap<String, String> {
+    cache_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+async fn fetch_playlists(cli: &AuthCodeSpotify) -> Result<HashMap<String, String>, PlayingError> {
+    let mut playlists = HashMap::new();
+    let mut offset = 0;
+    loop {
+        let page = cli.current_user_playlists_manual(Some(PAGE_SIZE), Some(offset)).await.map_err(spotify_error)?;
+        let n = page.items.len() as u32;
+        playlists.extend(page.items.into_iter().map(|p| (p.name, p.id.id().to_owned())));
+        offset += n;
+        if page.next.is_none() || n == 0 {
+            break
+        }
+    }
+    if let Some(path) = cache_path() {
+        if let Err(e) = crate::store::write_atomic(&path, serde_json::to_string(&playlists).unwrap_or_default().as_bytes()) {
+            eprintln!("cannot save the playlists to {}: {}", path.display(), e);
+        }
+    }
+    Ok(playlists)
+}
+
+/// The id of the playlist `name` in `playlists`, matched exactly or else ignoring case.
+fn find(playlists: &HashMap<String, String>, name: &str) -> Option<PlaylistId<'static>> {
+    let id = playlists.get(name)
+        .or_else(|| playlists.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, id)| id))?;
+    PlaylistId::from_id(id.clone()).ok()
+}
+
+/// The id of the user's playlist `name`, from the cache unless it doesn't know it, as when the
+/// playlist was created or renamed since.
+async fn playlist(cli: &AuthCodeSpotify, name: &str) -> Result<PlaylistId<'static>, PlayingError> {
+    if let Some(id) = find(&cached_playlists(), name) {
+        return Ok(id)
+    }
+    find(&fetch_playlists(cli).await?, name)
+        .ok_or_else(|| PlayingError::config(format!("no playlist named `{}`", name)))
+}
+
+pub async fn run_playlist(cli: &AuthCodeSpotify, command: PlaylistCommand) -> Result<bool, PlayingError> {
+    match command {
+        PlaylistCommand::Add { name } => {
+            let Some(track) = current_track(cli).await? else {
+                eprintln!("spotify is not playing a track");
+                return Ok(false)
+            };
+            let id = playlist(cli, &name).await?;
+            cli.playlist_add_items(id, [PlayableId::Track(track)], None).await.map_err(spotify_error)?;
+            println!("added song to {}", name);
+            Ok(true)
+        }
+    }
+}
+
+/// The device named `name`, ignoring case, or else the only one whose name starts with it, or
+/// with the id `name`.
+fn find_device<'a>(devices: &'a [Device], name: &str) -> Result<&'a Device, PlayingError> {
+    if let Some(d) = devices.iter().find(|d| d.name.eq_ignore_ascii_case(name) || d.id.as_deref() == Some(name)) {
+        return Ok(d)
+    }
+    let lower = name.to_lowercase();
+    let matching: Vec<&Device> = devices.iter().filter(|d| d.name.to_lowercase().starts_with(&lower)).collect();
+    match matching[..] {
+        [d] => Ok(d),
+        [] => Err(PlayingError::config(format!("no device named `{}`, see `spotify devices`", name))),
+        _ => Err(PlayingError::config(format!("`{}` could be {}", name, matching.iter().map(|d| d.name.as_str()).collect::<Vec<_>>().join(", ")))),
+    }
+}
+
+pub async fn run(cli: &AuthCodeSpotify, command: SpotifyCommand) -> Result<bool, PlayingError> {
+    match command {
+        SpotifyCommand::Devices => {
+            let devices = cli.device().await.map_err(spotify_error)?;
+            for d in &devices {
+                let volume = d.volume_percent.map(|v| format!(", {}%", v)).unwrap_or_default();
+                println!("{} {} ({}{})", if d.is_active { "*" } else { " " }, d.name, format!("{:?}", d._type).to_lowercase(), volume);
+            }
+            Ok(!devices.is_empty())
+        }
+        SpotifyCommand::Transfer { device, play } => {
+            let devices = cli.device().await.map_err(spotify_error)?;
+            let d = find_device(&devices, &device)?;
+            let Some(ref id) = d.id else {
+                return Err(PlayingError::unsupported(format!("{} can't be controlled through the Web API", d.name)))
+            };
+            // `None` keeps playing or paused as it was
+            cli.transfer_playback(id, play.then_some(true)).await.map_err(spotify_error)?;
+            println!("moved playback to {}", d.name);
+            Ok(true)
+        }
+        SpotifyCommand::Queue { query } => {
+            let query = query.join(" ");
+            // the market of the user leaves out the tracks they can't play
+            let result = cli.search(&query, SearchType::Track, Some(Market::FromToken), None, Some(1), None).await.map_err(spotify_error)?;
+            let SearchResult::Tracks(page) = result else { return Ok(false) };
+            let Some((id, track)) = page.items.into_iter().find_map(|t| Some((t.id.clone()?, t))) else {
+                eprintln!("no track found for `{}`", query);
+                return Ok(false)
+            };
+            cli.add_item_to_queue(PlayableId::Track(id), None).await.map_err(spotify_error)?;
+            let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+            println!("queued {} - {}", artists.join(", "), track.name);
+            Ok(true)
+        }
+        SpotifyCommand::Radio { limit } => {
+            let Some(seed) = current_track(cli).await? else {
+                eprintln!("spotify is not playing a track");
+                return Ok(false)
+            };
+            let recommendations = cli.recommendations(
+                [] as [RecommendationsAttribute; 0],
+                None::<[ArtistId; 0]>,
+                None::<[&str; 0]>,
+                Some([seed]),
+                Some(Market::FromToken),
+                Some(limit),
+            ).await.map_err(spotify_error)?;
+            // the queue has no endpoint taking several tracks
+            let mut queued = 0;
+            for id in recommendations.tracks.into_iter().filter_map(|t| t.id) {
+                cli.add_item_to_queue(PlayableId::Track(id), None).await.map_err(spotify_error)?;
+                queued += 1;
+            }
+            println!("queued {} tracks", queued);
+            Ok(queued > 0)
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::{collections::HashMap, time::Duration};
+use clap::ValueEnum;
+use console::style;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{backend::{self, BackendKind, MediaPlayer, Status, Track}, config::Config, format::{self, DurationSpec, Template}, Player, PlayingError};
+
+pub use playing_format::{DEFAULT_ELLIPSIS, DEFAULT_FORMAT, DEFAULT_LIVE_FORMAT, FIELDS, MAX_STATUS_LEN};
+use playing_format::LineOptions;
+
+/// Color of the line near the end of the track in polybar output, unless configured.
+const DEFAULT_ENDING_COLOR: &str = "#ff5555";
+
+/// The `[status]` table of the config file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusConfig {
+    pub format: Option<String>,
+    pub live_format: Option<String>,
+    /// Time left in the track from which it counts as ending.
+    pub ending_within: Option<DurationSpec>,
+    /// Color of the line while the track is ending, in polybar output.
+    pub ending_color: Option<String>,
+    /// Version whose default output to keep, as `--compat`.
+    pub compat: Option<Compat>,
+    /// Columns the line is cut to, as `--max-length`.
+    pub max_length: Option<usize>,
+    /// Columns the title is cut to, as `--max-title`.
+    pub max_title: Option<usize>,
+    /// Columns the album is cut to, as `--max-album`.
+    pub max_album: Option<usize>,
+    /// Columns the artist is cut to, as `--max-artist`.
+    pub max_artist: Option<usize>,
+    /// What ends what was cut, as `--ellipsis`.
+    pub ellipsis: Option<String>,
+    /// Markup the line is escaped for, as `--escape`.
+    pub escape: Option<Escape>,
+    /// What `{state_icon}` shows for each playback status.
+    pub state_icons: StateIcons,
+    /// Icons of the players, as `--icons`.
+    pub icons: Option<IconSet>,
+    /// Icons of players by identity, replacing the built-in ones, e.g. `"Tauon" = "T"`.
+    pub player_icons: HashMap<String, String>,
+    /// Colors of the icons of players by identity with `escape = "pango"`, e.g.
+    /// `"Spotify" = "#1db954"`.
+    pub player_colors: HashMap<String, HexColor>,
+    /// What `{artist}` shows of tracks without any artist, `Unknown` by default.
+    pub unknown_artist: Option<String>,
+    /// What goes between artists, as `--artist-separator`.
+    pub artist_separator: Option<String>,
+    /// Artists shown before the others are counted, as `--max-artists`.
+    pub max_artists: Option<usize>,
+}
+
+/// A color of the config file, written in hex as pango takes it, e.g. `#1db954`.
+#[derive(Clone, PartialEq, Eq, Deserialize, Debug)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub String);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.strip_prefix('#') {
+            Some(hex) if matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(HexColor(s)),
+            _ => Err(format!("invalid color `{}`, expected e.g. `#1db954`", s)),
+        }
+    }
+}
+
+/// Marks around the color and the icon in the uncut line, which [`StatusOptions::frame`] turns
+/// into a span once the line is cut and escaped; noncharacters, which no title has.
+const COLOR_START: char = '\u{FDD0}';
+const ICON_START: char = '\u{FDD1}';
+const ICON_END: char = '\u{FDD2}';
+
+/// `line` without the marks of a colored icon, and the color and icon if it has one.
+fn unmark(line: &str) -> (String, Option<(&str, &str)>) {
+    let marked = line.split_once(COLOR_START).and_then(|(before, rest)| {
+        let (color, rest) = rest.split_once(ICON_START)?;
+        let (icon, after) = rest.split_once(ICON_END)?;
+        Some((format!("{}{}{}", before, icon, after), (color, icon)))
+    });
+    match marked {
+        Some((line, colored)) => (line, Some(colored)),
+        None => (line.to_owned(), None),
+    }
+}
+
+/// The `[status.state_icons]` table of the config file, e.g. `paused = "󰏤"`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StateIcons {
+    pub playing: String,
+    pub paused: String,
+    pub stopped: String,
+}
+
+impl Default for StateIcons {
+    fn default() -> Self {
+        StateIcons {
+            playing: playing_format::state_icon("playing").to_owned(),
+            paused: playing_format::state_icon("paused").to_owned(),
+            stopped: playing_format::state_icon("stopped").to_owned(),
+        }
+    }
+}
+
+impl StateIcons {
+    fn of(&self, status: Status) -> &str {
+        match status {
+            Status::Playing => &self.playing,
+            Status::Paused => &self.paused,
+            Status::Stopped => &self.stopped,
+        }
+    }
+}
+
+/// Versions whose output changed since, which `--compat` keeps printing the way they did.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+pub enum Compat {
+    /// Livestreams shown with the regular format, before they had their own
+    #[value(name = "0.1")]
+    #[serde(rename = "0.1")]
+    V0_1,
+}
+
+/// Exit code of `status -q` while a player is playing.
+pub const EXIT_PLAYING: i32 = 0;
+/// Exit code of `status -q` while a player is paused and none is playing.
+pub const EXIT_PAUSED: i32 = 1;
+/// Exit code of `status -q` without any player, apart from the codes of errors.
+pub const EXIT_NO_PLAYER: i32 = 32;
+/// Exit code of `status -q` while every player is stopped.
+pub const EXIT_STOPPED: i32 = 33;
+
+/// The exit codes of `status -q` in `--help`, which scripts rely on: never change them.
+pub const QUIET_HELP: &str = "Exit codes with -q:
+  0   a player is playing
+  1   a player is paused, and none is playing
+  32  no player is running
+  33  players are running, but all stopped
+With --quiet-verbose, the state is also printed as playing, paused, stopped or none.";
+
+/// Markup the line is escaped for, so that e.g. an `&` in a title doesn't break the bar's.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Escape {
+    #[default]
+    None,
+    /// Pango markup, e.g. for waybar
+    Pango,
+    Html,
+}
+
+impl From<Escape> for playing_format::Escape {
+    fn from(escape: Escape) -> Self {
+        match escape {
+            Escape::None => playing_format::Escape::None,
+            Escape::Pango => playing_format::Escape::Pango,
+            Escape::Html => playing_format::Escape::Html,
+        }
+    }
+}
+
+/// What the line tells besides its text, the classes of waybar's output.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Classes {
+    /// The track is about to end.
+    pub ending: bool,
+    /// The player is paused, which is only shown with `--include-paused`.
+    pub paused: bool,
+}
+
+/// Which glyphs stand for the players, e.g. for terminals without a patched font.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum IconSet {
+    /// Glyphs of the Nerd Fonts
+    #[default]
+    Nerd,
+    Emoji,
+    /// Short names in brackets, e.g. `[mpv]`
+    Ascii,
+    None,
+}
+
+impl IconSet {
+    /// Icon of paused players, replacing the one of the player.
+    fn paused(self) -> &'static str {
+        match self {
+            IconSet::Nerd => "",
+            IconSet::Emoji => "⏸️",
+            IconSet::Ascii => "[||]",
+            IconSet::None => "",
+        }
+    }
+}
+
+/// How the line is wrapped for the program showing it.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Output {
+    Plain,
+    /// JSON with `text` and `class`, for waybar's `return-type = "json"`
+    Waybar,
+    /// Colored with polybar's `%{F}` tags
+    Polybar,
+}
+
+pub struct StatusOptions<'a> {
+    pub template: Template,
+    pub live_template: Template,
+    pub no_icon: bool,
+    pub spaces_after_icon: usize,
+    pub ending_within: Option<Duration>,
+    pub ending_color: String,
+    pub output: Output,
+    /// Columns the line is cut to, wide characters counting twice.
+    pub max_length: usize,
+    /// How often a line wider than `max_length` scrolls by a character in follow mode, instead
+    /// of being cut.
+    pub scroll: Option<Duration>,
+    /// Columns the fields are cut to before the line is, if they're limited on their own.
+    pub max_title: Option<usize>,
+    pub max_album: Option<usize>,
+    pub max_artist: Option<usize>,
+    /// What ends the line and the fields that were cut, e.g. `…`.
+    pub ellipsis: String,
+    pub escape: Escape,
+    /// Show the first paused player of the ranking when none is playing.
+    pub include_paused: bool,
+    pub state_icons: StateIcons,
+    pub icons: IconSet,
+    pub player_icons: HashMap<String, String>,
+    pub player_colors: HashMap<String, HexColor>,
+    /// Display names of players by identity, shown by `{name}`.
+    pub names: HashMap<String, String>,
+    pub unknown_artist: String,
+    /// What goes between artists, `, ` by default.
+    pub artist_separator: String,
+    /// Artists shown before the others are only counted, as in `A, B & 2 more`.
+    pub max_artists: Option<usize>,
+    /// Print the card of several lines of `--pretty` instead of the line.
+    pub pretty: bool,
+    /// Print nothing when the line is the one the same command printed last.
+    pub changed_only: bool,
+    /// Where `{favorite}` looks the tracks up.
+    pub config: &'a Config,
+}
+
+impl<'a> StatusOptions<'a> {
+    /// Picks the format given on the command line, then the configured one, then the default.
+    ///
+    /// Livestreams get the live format picked the same way, falling back to the regular format
+    /// when that one is customized, so that scripts parsing a custom format keep working. So do
+    /// they with `--compat 0.1`, which predates the live format.
+    pub fn new(format: Option<&str>, live_format: Option<&str>, config: &'a Config, no_icon: bool, spaces_after_icon: usize) -> Result<Self, PlayingError> {
+        let names = config.players.iter().filter_map(|(id, p)| Some((id.clone(), p.name.clone()?))).collect();
+        let whole = config;
+        let config = &config.status;
+        let format = format.or(config.format.as_deref());
+        let default_live = match config.compat {
+            Some(Compat::V0_1) => DEFAULT_FORMAT,
+            None => DEFAULT_LIVE_FORMAT,
+        };
+        let live_format = live_format.or(config.live_format.as_deref()).or(format).unwrap_or(default_live);
+        let template = Template::parse(format.unwrap_or(DEFAULT_FORMAT), FIELDS).map_err(PlayingError::config)?;
+        let live_template = Template::parse(live_format, FIELDS).map_err(PlayingError::config)?;
+        Ok(StatusOptions {
+            template,
+            live_template,
+            no_icon,
+            spaces_after_icon,
+            ending_within: config.ending_within.map(|DurationSpec(d)| d),
+            ending_color: config.ending_color.clone().unwrap_or(DEFAULT_ENDING_COLOR.to_owned()),
+            output: Output::Plain,
+            max_length: config.max_length.unwrap_or(MAX_STATUS_LEN),
+            scroll: None,
+            max_title: config.max_title,
+            max_album: config.max_album,
+            max_artist: config.max_artist,
+            ellipsis: config.ellipsis.clone().unwrap_or(DEFAULT_ELLIPSIS.to_owned()),
+            escape: config.escape.unwrap_or_default(),
+            include_paused: false,
+            state_icons: config.state_icons.clone(),
+            icons: config.icons.unwrap_or_default(),
+            player_icons: config.player_icons.clone(),
+            player_colors: config.player_colors.clone(),
+            names,
+            unknown_artist: config.unknown_artist.clone().unwrap_or("Unknown".to_owned()),
+            artist_separator: config.artist_separator.clone().unwrap_or(", ".to_owned()),
+            max_artists: config.max_artists,
+            pretty: false,
+            changed_only: false,
+            config: whole,
+        })
+    }
+
+    /// The options of the states that `serve`, `mqtt`, `events` and `dbus` publish: the fields as
+    /// the config shows them, without the markup of any bar.
+    pub fn published(config: &'a Config) -> Result<Self, PlayingError> {
+        let mut opts = StatusOptions::new(None, None, config, false, 1)?;
+        opts.escape = Escape::None;
+        Ok(opts)
+    }
+
+    /// Wraps `line` for the output, with the `classes` of the track.
+    fn wrap(&self, line: String, classes: Classes) -> String {
+        match self.output {
+            Output::Plain => line,
+            Output::Waybar => {
+                let names = [("ending", classes.ending), ("paused", classes.paused)];
+                let class: Vec<_> = names.into_iter().filter_map(|(name, set)| set.then_some(name)).collect();
+                json!({ "text": line, "class": class }).to_string()
+            }
+            // a `%{` of the title would be taken for a formatting tag
+            Output::Polybar if classes.ending => format!("%{{F{}}}{}%{{F-}}", self.ending_color, playing_format::escape_polybar(&line)),
+            Output::Polybar => playing_format::escape_polybar(&line),
+        }
+    }
+
+    /// The line as printed, cut to `max_length` or scrolled by `offset` characters.
+    pub fn frame(&self, line: &str, offset: usize, classes: Classes) -> String {
+        let (line, colored) = unmark(line);
+        let line = match self.scroll {
+            Some(_) => playing_format::scroll(&line, offset, self.max_length),
+            None => playing_format::truncate(line, self.max_length, &self.ellipsis),
+        };
+        // escaped once cut, so that no entity is cut in half nor counted in the length
+        let line = playing_format::escape(&line, self.escape.into());
+        // an icon cut or scrolled in half stays as it is
+        let line = match colored {
+            Some((color, icon)) if !icon.is_empty() => {
+                let icon = playing_format::escape_pango(icon);
+                line.replacen(&icon, &format!("<span foreground=\"{}\">{}</span>", color, icon), 1)
+            }
+            _ => line,
+        };
+        self.wrap(line, classes)
+    }
+
+    /// Prints `text`, unless it's what the same command printed last with `changed_only`, for
+    /// polling bars not to redraw.
+    pub fn print(&self, text: &str) {
+        if self.changed_only {
+            // the arguments tell apart the bars running different commands
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            if let Some(path) = crate::store::path(&format!("status/{:x}", md5::compute(args.join("\0")))) {
+                if std::fs::read_to_string(&path).is_ok_and(|last| last == text) {
+                    return
+                }
+                if let Err(e) = crate::store::write_atomic(&path, text.as_bytes()) {
+                    eprintln!("cannot keep the line in {}: {}", path.display(), e);
+                }
+            }
+        }
+        println!("{}", text);
+    }
+
+    /// Whether `line` is wider than `max_length`, and so cut or scrolled.
+    pub fn overflows(&self, line: &str) -> bool {
+        playing_format::width(&unmark(line).0) > self.max_length
+    }
+
+    /// The options of the fields, as the templates of `playing-format` take them.
+    fn line_options(&self) -> LineOptions {
+        LineOptions {
+            no_icon: self.no_icon,
+            spaces_after_icon: self.spaces_after_icon,
+            max_title: self.max_title,
+            max_album: self.max_album,
+            max_artist: self.max_artist,
+            unknown_artist: self.unknown_artist.clone(),
+            max_length: self.max_length,
+            ellipsis: self.ellipsis.clone(),
+            escape: self.escape.into(),
+        }
+    }
+
+    /// `field` cut to `max` columns, if it's limited.
+    fn cut(&self, field: String, max: Option<usize>) -> String {
+        match max {
+            Some(max) => playing_format::truncate(field, max, &self.ellipsis),
+            None => field,
+        }
+    }
+
+    /// `artists` joined, the ones past `max_artists` only counted; `None` without any.
+    fn artists(&self, artists: &[String]) -> Option<String> {
+        if artists.is_empty() {
+            return None
+        }
+        let shown = self.max_artists.unwrap_or(usize::MAX).clamp(1, artists.len());
+        let mut joined = artists[..shown].join(&self.artist_separator);
+        if shown < artists.len() {
+            joined.push_str(&format!(" & {} more", artists.len() - shown));
+        }
+        Some(joined)
+    }
+
+    /// What's printed without a player, e.g. `No media`.
+    pub fn idle(&self, text: &str) -> String {
+        self.wrap(text.to_owned(), Classes::default())
+    }
+}
+
+/// The exit code of `status -q`, telling the playback state of the ranked players; with
+/// `verbose` the state is also printed as one word, for the conditionals of bars.
+pub fn quiet(config: &Config, kind: BackendKind, verbose: bool) -> Result<i32, PlayingError> {
+    let code = quiet_code(config, kind)?;
+    if verbose {
+        println!("{}", match code {
+            EXIT_PLAYING => "playing",
+            EXIT_PAUSED => "paused",
+            EXIT_STOPPED => "stopped",
+            _ => "none",
+        });
+    }
+    Ok(code)
+}
+
+fn quiet_code(config: &Config, kind: BackendKind) -> Result<i32, PlayingError> {
+    let backends = backend::open(kind, config)?;
+    let players = backend::players(&backends)?;
+    let ranking = config.ranking();
+    let mut ranked = players.iter().filter(|p| ranking.iter().any(|id| crate::picks(id, p.as_ref()))).peekable();
+    if ranked.peek().is_none() {
+        return Ok(EXIT_NO_PLAYER)
+    }
+    let mut code = EXIT_STOPPED;
+    for p in ranked {
+        match p.status()? {
+            Status::Playing => return Ok(EXIT_PLAYING),
+            Status::Paused => code = EXIT_PAUSED,
+            Status::Stopped => {}
+        }
+    }
+    Ok(code)
+}
+
+/// Renders the status line of `p`, `event` being what caused it in follow mode (e.g. `seek`),
+/// or its card with `pretty`.
+pub fn render(p: &dyn MediaPlayer, opts: &StatusOptions, event: Option<&str>) -> Result<String, PlayingError> {
+    if opts.pretty {
+        return card(p, opts)
+    }
+    let (line, classes) = line(p, opts, event)?;
+    Ok(opts.frame(&line, 0, classes))
+}
+
+/// The icon of `p`: the pause icon while paused, else the one of the config or the built-in one.
+fn icon<'o>(identity: &str, opts: &'o StatusOptions, paused: bool) -> &'o str {
+    match (opts.player_icons.get(identity), Player::parse(identity)) {
+        _ if paused => opts.icons.paused(),
+        _ if opts.icons == IconSet::None => "",
+        // the config's icons are for any player, built in or not
+        (Some(icon), _) => icon,
+        (None, Some(pl)) => pl.icon_in(opts.icons),
+        (None, None) => ""
+    }
+}
+
+/// Columns of the progress bar of `--pretty`.
+const BAR_WIDTH: usize = 30;
+
+/// The card of `status --pretty`, for terminals rather than bars: the icon and the title in
+/// bold, the artist, the album dimmed, and a progress bar.
+fn card(p: &dyn MediaPlayer, opts: &StatusOptions) -> Result<String, PlayingError> {
+    let track = p.track()?;
+    let paused = p.status()? == Status::Paused;
+    let position = p.position()?;
+    let icon = if opts.no_icon { "" } else { icon(p.identity(), opts, paused) };
+    let indent = " ".repeat(playing_format::width(icon) + opts.spaces_after_icon);
+    let title = opts.cut(track.title.clone().unwrap_or("Unknown".to_owned()), opts.max_title);
+    let mut card = format!("{}{}{}", icon, " ".repeat(opts.spaces_after_icon), style(title).bold());
+    let artist = opts.artists(track.artist_list()).unwrap_or(opts.unknown_artist.clone());
+    card.push_str(&format!("\n{}{}", indent, opts.cut(artist, opts.max_artist)));
+    if let Some(album) = track.album.clone() {
+        card.push_str(&format!("\n{}{}", indent, style(opts.cut(album, opts.max_album)).dim()));
+    }
+    match (track.plausible_length(), position) {
+        _ if track.is_live() => card.push_str(&format!("\n{}{}", indent, style("● live").red())),
+        (Some(length), Some(position)) => {
+            let filled = ((position.as_secs_f64() / length.as_secs_f64()).clamp(0.0, 1.0) * BAR_WIDTH as f64).round() as usize;
+            card.push_str(&format!(
+                "\n{}{}{} {} / {}",
+                indent,
+                style("━".repeat(filled)).cyan(),
+                style("─".repeat(BAR_WIDTH - filled)).dim(),
+                format::duration(position),
+                format::duration(length),
+            ));
+        }
+        (None, Some(position)) => card.push_str(&format!("\n{}{}", indent, format::duration(position))),
+        _ => {}
+    }
+    Ok(card)
+}
+
+/// The status line of `p` before it's cut and wrapped, and its classes.
+pub fn line(p: &dyn MediaPlayer, opts: &StatusOptions, event: Option<&str>) -> Result<(String, Classes), PlayingError> {
+    let reading = crate::timing::span("metadata read");
+    let track = p.track()?;
+    let live = track.is_live();
+    let template = if live { &opts.live_template } else { &opts.template };
+    let remaining = template.uses("remaining") || template.uses("ending") || (opts.ending_within.is_some() && !live);
+    let position = if remaining || template.uses("position") || template.uses("uptime") { p.position()? } else { None };
+    let rate = if remaining { p.rate()? } else { 1.0 };
+    let status = if template.uses("status") || template.uses("state_icon") || opts.include_paused { Some(p.status()?) } else { None };
+    let playlist = if template.uses("playlist_position") || template.uses("playlist_length") { p.playlist()? } else { None };
+    let favorite = if template.uses("favorite") { crate::favorites::is_favorite(opts.config, p.identity(), &track) } else { None };
+    drop(reading);
+
+    let _span = crate::timing::span("render");
+    let state = state(&Reading { identity: p.identity(), track: &track, status, position, rate, playlist, favorite }, opts);
+    let line = playing_format::render_fields(template, &state, event, &opts.line_options());
+    let ending = state["ending"].as_bool().unwrap_or_default();
+    Ok((line, Classes { ending, paused: status == Some(Status::Paused) }))
+}
+
+/// What the fields of a line are rendered from, as read of a player or kept in a snapshot.
+pub struct Reading<'r> {
+    pub identity: &'r str,
+    pub track: &'r Track,
+    /// None when the line doesn't show it.
+    pub status: Option<Status>,
+    pub position: Option<Duration>,
+    pub rate: f64,
+    pub playlist: Option<(usize, usize)>,
+    pub favorite: Option<bool>,
+}
+
+/// The state the fields of the line are rendered from, which `serve`, `mqtt` and the others
+/// publish too: pages rendering it with the same template show what `status` prints.
+pub fn state(reading: &Reading, opts: &StatusOptions) -> Value {
+    let track = reading.track;
+    let live = track.is_live();
+    let length = track.plausible_length();
+    let left = match (length, reading.position) {
+        (Some(l), Some(p)) if !live => Some(playing_format::remaining_time(l, p, reading.rate)),
+        _ => None,
+    };
+    let ending = opts.ending_within.zip(left).is_some_and(|(within, left)| left <= within);
+    let status = reading.status.unwrap_or(Status::Stopped);
+    let icon = match (opts.player_colors.get(reading.identity), icon(reading.identity, opts, status == Status::Paused)) {
+        (Some(HexColor(color)), icon) if opts.escape == Escape::Pango && !icon.is_empty() => {
+            format!("{}{}{}{}{}", COLOR_START, color, ICON_START, icon, ICON_END)
+        }
+        (_, icon) => icon.to_owned(),
+    };
+    json!({
+        "icon": icon,
+        "title": track.title,
+        "album": track.album,
+        "artist": opts.artists(track.artist_list()),
+        "album_artist": opts.artists(&track.album_artists),
+        "track_artist": opts.artists(&track.artists),
+        "player": reading.identity,
+        "name": opts.names.get(reading.identity),
+        "status": status.name(),
+        "state_icon": opts.state_icons.of(status),
+        "url": track.url,
+        "position": reading.position.map(|p| p.as_secs_f64()),
+        "length": length.map(|l| l.as_secs_f64()),
+        "rate": reading.rate,
+        "live": live,
+        "ending": ending,
+        "track_number": track.track_number,
+        "playlist_position": reading.playlist.map(|(p, _)| p),
+        "playlist_length": reading.playlist.map(|(_, l)| l),
+        "favorite": reading.favorite,
+    })
+}
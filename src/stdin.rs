@@ -0,0 +1,40 @@
+use std::io::BufRead;
+
+use crate::{access::Source, backend::{self, BackendKind}, config::Config, Action, PlayingError, PlayingErrorKind};
+
+/// Runs the commands read from stdin, one per line, until it's closed, e.g. piped by sxhkd or a
+/// script sending bursts of them. The backends are connected to once for all of them, and again
+/// after a command fails other than to parse, in case the connection was what broke.
+///
+/// A failing command is reported and the next one run; whether none failed is returned.
+pub async fn run(config: &Config, kind: BackendKind) -> Result<bool, PlayingError> {
+    // without them each command connects on its own, slower but still working
+    if let Err(e) = backend::keep_open(kind, config) {
+        eprintln!("cannot connect to the players: {}: {}", e.kind, e.inner);
+    }
+    let mut failed = false;
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let result = match crate::Streamed::parse_line(line) {
+            Ok(Action::Stdin) => Err(PlayingError::bad_command("stdin is reading already")),
+            // boxed, as dispatching is what started this loop
+            Ok(action) => Box::pin(crate::dispatch(config, kind, Source::Cli, action)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("`{}` failed: {}: {}", line, e.kind, e.inner);
+            failed = true;
+            if matches!(e.kind, PlayingErrorKind::BadCommand) {
+                continue
+            }
+            if let Err(e) = backend::keep_open(kind, config) {
+                eprintln!("cannot connect to the players: {}: {}", e.kind, e.inner);
+            }
+        }
+    }
+    Ok(!failed)
+}
@@ -0,0 +1,230 @@
+use std::{fs::{self, File}, io::{self, Write}, path::{Path, PathBuf}, sync::atomic::{AtomicUsize, Ordering}};
+use clap::Subcommand;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::{config::Config, history::{self, db_error, Storage, StorageKind}, PlayingError};
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Checks that every file kept in the data dir can be read back, the default
+    Check,
+    /// Sets the damaged files aside as `<name>.corrupt`, keeping what can still be read of them
+    Repair,
+}
+
+fn data_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("org", "prabo", "playing").map(|d| d.data_dir().to_owned())
+}
+
+/// The file `name` of the data dir.
+pub fn path(name: &str) -> Option<PathBuf> {
+    data_dir().map(|d| d.join(name))
+}
+
+/// Writes of this process so far, telling apart the temporary files of its threads.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// A file next to `path` that no other write uses, be it of another thread or process: they would
+/// otherwise write over each other's contents before renaming them.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.{}.tmp", std::process::id(), WRITES.fetch_add(1, Ordering::Relaxed)));
+    tmp.into()
+}
+
+/// The temporary files of the writes to `path` that never finished, including the `<name>.tmp`
+/// of earlier versions.
+fn unfinished(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else { return Ok(vec![]) };
+    if !dir.is_dir() {
+        return Ok(vec![])
+    }
+    let mut found = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?.path();
+        let left = entry.file_name().and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(name)?.strip_suffix(".tmp"))
+            .is_some_and(|id| id.split('.').skip(1).all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) && (id.is_empty() || id.starts_with('.')));
+        if left {
+            found.push(entry);
+        }
+    }
+    Ok(found)
+}
+
+/// Replaces the contents of `path` with `contents`, creating its directory if needed.
+///
+/// The contents go to a file next to it first, synced to the disk before being renamed over
+/// `path`, so that a crash leaves either the old contents or the new ones, never a mix.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+    if let Some(dir) = dir {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = tmp_path(path);
+    let written = File::create(&tmp)
+        .and_then(|mut file| file.write_all(contents).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&tmp, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e)
+    }
+    // the rename itself is only durable once the directory is
+    #[cfg(unix)]
+    if let Some(dir) = dir {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// How a file of the data dir is laid out.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Json,
+    /// A JSON value per line, appended to.
+    JsonLines,
+    Sqlite,
+}
+
+/// The files `playing` keeps, as `(description, path, kind)`.
+fn files(config: &Config) -> Vec<(&'static str, PathBuf, Kind)> {
+    let mut files = vec![];
+    if let Ok(Some(path)) = history::path(&config.history) {
+        files.push(("history", path, if config.history.storage == StorageKind::Jsonl { Kind::JsonLines } else { Kind::Sqlite }));
+    }
+    let named = [
+        ("last active player", "recent.json", Kind::Json),
+        ("learned players", "learned-players.json", Kind::Json),
+        ("sleep timer", "sleep-timer.json", Kind::Json),
+        ("Last.fm queue", "lastfm-queue.json", Kind::Json),
+        ("ListenBrainz queue", "listenbrainz-queue.json", Kind::Json),
+        ("Spotify playlists", "spotify-playlists.json", Kind::Json),
+        ("local favorites", "favorites.json", Kind::Json),
+        ("Spotify favorites queue", "favorite-queue.json", Kind::Json),
+        ("last favorite toggle", "favorite-last.json", Kind::Json),
+        ("last known favorite", "favorite-known.json", Kind::Json),
+        ("liked Spotify tracks", "spotify-likes.json", Kind::Json),
+        ("last notification", "notification.json", Kind::Json),
+        ("analytics", "analytics.jsonl", Kind::JsonLines),
+    ];
+    files.extend(named.into_iter().filter_map(|(what, name, kind)| Some((what, path(name)?, kind))));
+    files
+}
+
+/// What's wrong with the file at `path`, none when it's fine or missing.
+fn damage(path: &Path, kind: Kind) -> Result<Option<String>, PlayingError> {
+    if !path.exists() {
+        return Ok(None)
+    }
+    match kind {
+        Kind::Json => {
+            let contents = fs::read_to_string(path)?;
+            Ok(serde_json::from_str::<serde_json::Value>(&contents).err().map(|e| e.to_string()))
+        }
+        Kind::JsonLines => {
+            let contents = fs::read_to_string(path)?;
+            let bad = contents.lines().filter(|l| !l.trim().is_empty() && serde_json::from_str::<serde_json::Value>(l).is_err()).count();
+            Ok((bad > 0).then(|| format!("{} unreadable lines", bad)))
+        }
+        Kind::Sqlite => {
+            let conn = match Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+                Ok(conn) => conn,
+                Err(e) => return Ok(Some(e.to_string())),
+            };
+            let result: Result<Vec<String>, _> = conn.prepare("PRAGMA integrity_check")
+                .and_then(|mut s| s.query_map([], |r| r.get(0))?.collect());
+            Ok(match result {
+                Ok(lines) if lines == ["ok"] => None,
+                Ok(lines) => Some(lines.join("; ")),
+                Err(e) => Some(e.to_string()),
+            })
+        }
+    }
+}
+
+fn set_aside(path: &Path) -> io::Result<PathBuf> {
+    let mut corrupt = path.as_os_str().to_owned();
+    corrupt.push(".corrupt");
+    let corrupt = PathBuf::from(corrupt);
+    fs::rename(path, &corrupt)?;
+    Ok(corrupt)
+}
+
+/// Copies what can still be read of the database at `from` into a new one at `to`.
+fn salvage(from: &Path, to: &Path) -> Result<usize, PlayingError> {
+    let old = Connection::open_with_flags(from, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(db_error)?;
+    let new = history::SqliteStorage::open(to)?;
+    let last: i64 = old.query_row("SELECT MAX(id) FROM plays", [], |r| r.get::<_, Option<i64>>(0)).map_err(db_error)?.unwrap_or_default();
+    let mut statement = old.prepare("SELECT player, title, artist, album, url, length, listened, started_at FROM plays WHERE id = ?1").map_err(db_error)?;
+    let mut saved = 0;
+    // row by row, as a damaged page makes any query reading it fail altogether
+    for id in 1..=last {
+        if let Ok(play) = statement.query_row([id], history::Play::from_row) {
+            new.record(&play)?;
+            saved += 1;
+        }
+    }
+    Ok(saved)
+}
+
+fn repair(path: &Path, kind: Kind) -> Result<String, PlayingError> {
+    match kind {
+        Kind::Json => {
+            let corrupt = set_aside(path)?;
+            Ok(format!("moved to {}, starting over", corrupt.display()))
+        }
+        Kind::JsonLines => {
+            let contents = fs::read_to_string(path)?;
+            let kept: Vec<&str> = contents.lines().filter(|l| serde_json::from_str::<serde_json::Value>(l).is_ok()).collect();
+            write_atomic(path, (kept.join("\n") + "\n").as_bytes())?;
+            Ok(format!("kept the {} readable lines", kept.len()))
+        }
+        Kind::Sqlite => {
+            let corrupt = set_aside(path)?;
+            // the journal goes with the file it belongs to
+            for suffix in ["-wal", "-shm"] {
+                let mut journal = path.as_os_str().to_owned();
+                journal.push(suffix);
+                let journal = PathBuf::from(journal);
+                if journal.exists() {
+                    let mut moved = corrupt.as_os_str().to_owned();
+                    moved.push(suffix);
+                    fs::rename(&journal, PathBuf::from(moved))?;
+                }
+            }
+            let saved = salvage(&corrupt, path)?;
+            Ok(format!("recovered {} plays, the damaged file is {}", saved, corrupt.display()))
+        }
+    }
+}
+
+/// Checks the files of the data dir, repairing the damaged ones with `repair`. Fails when some
+/// file is damaged and left so.
+pub fn run(config: &Config, command: Option<DbCommand>) -> Result<bool, PlayingError> {
+    let repairing = matches!(command, Some(DbCommand::Repair));
+    let mut healthy = true;
+    for (what, path, kind) in files(config) {
+        for tmp in unfinished(&path)? {
+            // left by a write that never finished, the file itself is still whole
+            if repairing {
+                fs::remove_file(&tmp)?;
+                println!("{}: removed the unfinished write {}", what, tmp.display());
+            } else {
+                println!("{}: an unfinished write was left at {}", what, tmp.display());
+            }
+        }
+        match damage(&path, kind)? {
+            None if path.exists() => println!("{}: ok", what),
+            None => {}
+            Some(e) if repairing => println!("{}: {}, {}", what, e, repair(&path, kind)?),
+            Some(e) => {
+                println!("{}: damaged, {}: {}", what, path.display(), e);
+                healthy = false;
+            }
+        }
+    }
+    if !healthy {
+        eprintln!("run `playing db repair` to set the damaged files aside");
+    }
+    Ok(healthy)
+}
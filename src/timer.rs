@@ -0,0 +1,219 @@
+use std::{env, path::PathBuf, process::{Command, Stdio}, thread::{self, sleep}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::{self, BackendKind, MediaPlayer, Status}, config::Config, follow::Snapshot, format, PlayingError};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest sleep before looking at the clock again, which keeps the timer on time across suspends.
+const CLOCK_CHECK: Duration = Duration::from_secs(30);
+
+/// The sleep timer, or `op stop-after-current`, running in the background, kept in the data dir
+/// for `--status` and `--cancel`.
+#[derive(Serialize, Deserialize)]
+struct Timer {
+    pid: u32,
+    player: String,
+    /// Unix time to act at, none to act at the end of the track.
+    until: Option<u64>,
+    stop: bool,
+}
+
+fn path() -> Option<PathBuf> {
+    crate::store::path("sleep-timer.json")
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(unix)]
+fn alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn alive(_pid: u32) -> bool {
+    // the watcher removes the file when done, which has to be trusted here
+    true
+}
+
+#[cfg(unix)]
+fn kill(pid: u32) -> Result<(), PlayingError> {
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error().into())
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn kill(pid: u32) -> Result<(), PlayingError> {
+    Command::new("taskkill").args(["/F", "/PID", &pid.to_string()]).stdout(Stdio::null()).status()?;
+    Ok(())
+}
+
+impl Timer {
+    /// The running timer, if any; one whose watcher is gone is dropped.
+    fn load() -> Option<Timer> {
+        let path = path()?;
+        let timer: Timer = serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+        if !alive(timer.pid) {
+            let _ = std::fs::remove_file(path);
+            return None
+        }
+        Some(timer)
+    }
+
+    fn save(&self) -> Result<(), PlayingError> {
+        let Some(path) = path() else { return Err(PlayingError::config("cannot find the data dir to keep the sleep timer in")) };
+        crate::store::write_atomic(&path, serde_json::to_string(self).unwrap_or_default().as_bytes())?;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let verb = if self.stop { "stopping" } else { "pausing" };
+        match self.until {
+            Some(until) => format!("{} {} in {}", verb, self.player, format::duration(Duration::from_secs(until.saturating_sub(now())))),
+            None => format!("{} {} at the end of the track", verb, self.player),
+        }
+    }
+}
+
+/// Starts `sleep-timer-watch` in the background, which pauses (or stops) `p` after `after` or
+/// when its track ends. A timer already running is replaced.
+pub fn start(p: &dyn MediaPlayer, after: Option<Duration>, stop: bool, config: &Config) -> Result<bool, PlayingError> {
+    if let Some(running) = Timer::load() {
+        kill(running.pid)?;
+    }
+    let until = after.map(|a| now() + a.as_secs());
+
+    let mut watcher = Command::new(env::current_exe()?);
+    if let Some(ref path) = config.path {
+        watcher.arg("--config").arg(path);
+    }
+    if let Some(ref socket) = config.mpv.socket {
+        watcher.arg("--mpv-socket").arg(socket);
+    }
+    watcher.args(["sleep-timer-watch", p.identity()]);
+    if let Some(until) = until {
+        watcher.arg("--until").arg(until.to_string());
+    }
+    if stop {
+        watcher.arg("--stop");
+    }
+    let mut child = watcher.stdin(Stdio::null()).stdout(Stdio::null()).spawn()?;
+    let timer = Timer { pid: child.id(), player: p.identity().to_owned(), until, stop };
+    if let Err(e) = timer.save() {
+        // it couldn't be cancelled otherwise
+        let _ = child.kill();
+        return Err(e)
+    }
+    // reaped here when the servers run this, which outlive it
+    thread::spawn(move || child.wait());
+    println!("{}", timer.describe());
+    Ok(true)
+}
+
+/// Prints what the running timer will do and when.
+pub fn status() -> Result<bool, PlayingError> {
+    match Timer::load() {
+        Some(timer) => {
+            println!("{}", timer.describe());
+            Ok(true)
+        }
+        None => {
+            println!("no sleep timer");
+            Ok(false)
+        }
+    }
+}
+
+pub fn cancel() -> Result<bool, PlayingError> {
+    let Some(timer) = Timer::load() else {
+        eprintln!("no sleep timer to cancel");
+        return Ok(false)
+    };
+    kill(timer.pid)?;
+    if let Some(path) = path() {
+        let _ = std::fs::remove_file(path);
+    }
+    println!("cancelled {}", timer.describe());
+    Ok(true)
+}
+
+/// Sleeps until the unix time `until`.
+fn wait_until(until: u64) {
+    loop {
+        match until.checked_sub(now()) {
+            Some(left) if left > 0 => sleep(Duration::from_secs(left).min(CLOCK_CHECK)),
+            _ => return,
+        }
+    }
+}
+
+/// Waits for the track playing on `p` to end, by its length and position, or for it to switch to
+/// another track.
+fn wait_track_end(p: &dyn MediaPlayer) -> Result<(), PlayingError> {
+    let mut first: Option<Snapshot> = None;
+    loop {
+        let snapshot = Snapshot::take(p)?;
+        if snapshot.status == Status::Stopped || first.as_ref().is_some_and(|f| !f.same_track(&snapshot)) {
+            return Ok(())
+        }
+        if let (Status::Playing, Some(position), Some(length)) = (snapshot.status, snapshot.position, snapshot.track.plausible_length()) {
+            let left = format::remaining_time(length, position, snapshot.rate);
+            if left <= POLL_INTERVAL {
+                sleep(left);
+                return Ok(())
+            }
+        }
+        first.get_or_insert(snapshot);
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// Pauses `p` once its track is over, from a watcher in the background or, with `wait`, before
+/// returning. Disarms the watcher instead when one is already waiting for the end of its track.
+pub fn stop_after_current(p: &dyn MediaPlayer, wait: bool, config: &Config) -> Result<(), PlayingError> {
+    if let Some(armed) = Timer::load().filter(|t| t.until.is_none() && t.player == p.identity()) {
+        kill(armed.pid)?;
+        if let Some(path) = path() {
+            let _ = std::fs::remove_file(path);
+        }
+        println!("{} will keep playing", p.identity());
+        return Ok(())
+    }
+    if !wait {
+        return start(p, None, false, config).map(|_| ())
+    }
+    wait_track_end(p)?;
+    p.pause()
+}
+
+/// Waits for the unix time `until`, or for the end of the track when none, then pauses `player`,
+/// or stops it with `stop`.
+pub fn watch(config: &Config, kind: BackendKind, player: &str, until: Option<u64>, stop: bool) -> Result<bool, PlayingError> {
+    let result = (|| {
+        // connections opened before the time is up could be long closed by then
+        if let Some(until) = until {
+            wait_until(until);
+        }
+        let backends = backend::open(kind, config)?;
+        let players = backend::players(&backends)?;
+        let Some(p) = players.iter().find(|p| p.identity() == player) else { return Ok(false) };
+        if until.is_none() {
+            wait_track_end(p.as_ref())?;
+        }
+        if stop {
+            p.stop()?;
+        } else if p.status()? == Status::Playing {
+            p.pause()?;
+        }
+        Ok(true)
+    })();
+    if Timer::load().is_some_and(|t| t.pid == std::process::id()) {
+        if let Some(path) = path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    result
+}
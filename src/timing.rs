@@ -0,0 +1,127 @@
+use std::{cell::RefCell, sync::{atomic::{AtomicBool, Ordering}, Mutex}, time::{Duration, Instant}};
+
+/// How often the daemons print the percentiles of the stages with `--trace-timing`.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Times taken by each stage since the last report, in the order the stages first ran.
+struct Samples {
+    stages: Vec<(&'static str, Vec<Duration>)>,
+    since: Instant,
+}
+
+static SAMPLES: Mutex<Option<Samples>> = Mutex::new(None);
+
+thread_local! {
+    /// Stages running on this thread, innermost last, with the time each has taken itself so far
+    /// and when it last resumed.
+    static OPEN: RefCell<Vec<(&'static str, Duration, Instant)>> = const { RefCell::new(vec![]) };
+}
+
+/// Starts recording how long the stages take, for `--trace-timing`.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+    *SAMPLES.lock().unwrap_or_else(|e| e.into_inner()) = Some(Samples { stages: vec![], since: Instant::now() });
+}
+
+fn add(stage: &'static str, took: Duration) {
+    let mut samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(samples) = samples.as_mut() else { return };
+    match samples.stages.iter_mut().find(|(s, _)| *s == stage) {
+        Some((_, times)) => times.push(took),
+        None => samples.stages.push((stage, vec![took])),
+    }
+}
+
+/// A stage being timed, until dropped.
+pub struct Span {
+    recording: bool,
+}
+
+/// Times the stage `stage`, e.g. `bus scan`, until the returned span is dropped.
+///
+/// The time of a stage doesn't include the stages started within it, so that they add up;
+/// a stage started again within itself is counted once.
+pub fn span(stage: &'static str) -> Span {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Span { recording: false }
+    }
+    OPEN.with_borrow_mut(|open| {
+        let now = Instant::now();
+        if let Some((outer, took, resumed)) = open.last_mut() {
+            if *outer == stage {
+                return Span { recording: false }
+            }
+            *took += now - *resumed;
+        }
+        open.push((stage, Duration::ZERO, now));
+        Span { recording: true }
+    })
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.recording {
+            return
+        }
+        OPEN.with_borrow_mut(|open| {
+            let Some((stage, took, resumed)) = open.pop() else { return };
+            let now = Instant::now();
+            add(stage, took + (now - resumed));
+            if let Some((_, _, outer)) = open.last_mut() {
+                *outer = now;
+            }
+        });
+    }
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.2}ms", d.as_secs_f64() * 1000.0)
+}
+
+/// Prints the time each stage of the command took, to stderr.
+pub fn report() {
+    let samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(samples) = samples.as_ref() else { return };
+    let width = samples.stages.iter().map(|(s, _)| s.len()).max().unwrap_or_default().max("total".len());
+    for (stage, times) in &samples.stages {
+        let total: Duration = times.iter().sum();
+        let count = if times.len() > 1 { format!(" ({} times)", times.len()) } else { String::new() };
+        eprintln!("{:width$}  {:>9}{}", stage, millis(total), count);
+    }
+    eprintln!("{:width$}  {:>9}", "total", millis(samples.since.elapsed()));
+}
+
+/// The `p`th percentile of the sorted `times`.
+fn percentile(times: &[Duration], p: f64) -> Duration {
+    let rank = ((p * times.len() as f64).ceil() as usize).clamp(1, times.len());
+    times[rank - 1]
+}
+
+/// Called by the daemons once per poll: prints the percentiles of the stages every minute, to
+/// stderr, starting over afterwards.
+pub fn tick() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return
+    }
+    let mut samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(samples) = samples.as_mut() else { return };
+    if samples.since.elapsed() < REPORT_INTERVAL {
+        return
+    }
+    let width = samples.stages.iter().map(|(s, _)| s.len()).max().unwrap_or_default();
+    eprintln!("timings of the last {}s:", samples.since.elapsed().as_secs());
+    for (stage, times) in &mut samples.stages {
+        times.sort();
+        eprintln!("  {:width$}  p50 {:>9}  p90 {:>9}  p99 {:>9}  max {:>9}  ({} samples)",
+            stage,
+            millis(percentile(times, 0.5)),
+            millis(percentile(times, 0.9)),
+            millis(percentile(times, 0.99)),
+            millis(times[times.len() - 1]),
+            times.len());
+    }
+    samples.stages.clear();
+    samples.since = Instant::now();
+}
@@ -0,0 +1,74 @@
+use std::{thread::sleep, time::Duration};
+use chrono::{Local, NaiveTime, TimeDelta};
+
+use crate::{backend::{self, BackendKind}, config::Config, PlayingError};
+
+/// Longest sleep before looking at the clock again, which keeps the alarm on time across suspends.
+const CLOCK_CHECK: Duration = Duration::from_secs(30);
+const FADE_STEPS: u32 = 100;
+
+/// Parses the `--at` time, as `07:00` or `07:00:30`.
+pub fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S"))
+        .map_err(|_| format!("invalid time `{}`, expected e.g. `07:00`", s))
+}
+
+/// Sleeps until the next time the clock reads `at`, returning false if asked to stop meanwhile.
+fn wait_until(at: NaiveTime) -> bool {
+    let now = Local::now().naive_local();
+    let mut target = now.date().and_time(at);
+    if target <= now {
+        target += TimeDelta::days(1);
+    }
+    eprintln!("waking up at {}", target);
+    loop {
+        if crate::shutdown::requested() {
+            return false
+        }
+        let left = target - Local::now().naive_local();
+        match left.to_std() {
+            Ok(left) if !left.is_zero() => sleep(left.min(CLOCK_CHECK)),
+            _ => return true,
+        }
+    }
+}
+
+/// Waits for `at`, then plays `uri` (or resumes) on `player`, or the first running player of the
+/// ranking, raising its volume from silence to where it was over `fade`.
+pub fn run(config: &Config, kind: BackendKind, at: NaiveTime, uri: Option<&str>, fade: Duration, player: Option<&str>) -> Result<bool, PlayingError> {
+    crate::shutdown::install();
+    if !wait_until(at) {
+        return Ok(false)
+    }
+
+    let backends = backend::open(kind, config)?;
+    let players = backend::players(&backends)?;
+    let found = match player {
+        Some(id) => players.iter().find(|p| crate::picks(id, p.as_ref())),
+        None => config.ranking().iter().find_map(|id| players.iter().find(|p| crate::picks(id, p.as_ref()))),
+    };
+    let Some(p) = found else {
+        eprintln!("no player to wake up with");
+        return Ok(false)
+    };
+
+    // a muted player would stay silent
+    let volume = Some(p.volume()?).filter(|v| *v > 0.0).unwrap_or(1.0);
+    p.set_volume(0.0)?;
+    match uri {
+        Some(uri) => p.open(uri)?,
+        None => p.play()?,
+    }
+    let step = fade / FADE_STEPS;
+    for i in 1..=FADE_STEPS {
+        if crate::shutdown::requested() {
+            // rather than leaving it quieter than it was
+            p.set_volume(volume)?;
+            break
+        }
+        sleep(step);
+        p.set_volume(volume * f64::from(i) / f64::from(FADE_STEPS))?;
+    }
+    Ok(true)
+}
@@ -0,0 +1,80 @@
+//! The exact output of the commands that bars and scripts parse, for representative states of
+//! the players. A change to these snapshots breaks somebody's setup: keep the old output behind
+//! `--compat` instead.
+
+use std::process::Command;
+
+/// The states in `tests/fixtures/states`, each a fixture of a single line.
+const STATES: &[&str] = &["playing", "paused", "none", "live", "untagged", "long", "several"];
+
+/// Runs the binary with `args` on the players of `state`, returning the exit code and what's printed.
+fn run(state: &str, args: &[&str]) -> String {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let out = Command::new(env!("CARGO_BIN_EXE_playing-rs"))
+        .args(["--config", &format!("{}/default.toml", dir), "--fixture", &format!("{}/states/{}.jsonl", dir, state)])
+        .args(args)
+        .output()
+        .expect("the binary runs");
+    format!("exit: {}\n{}", out.status.code().unwrap_or(-1), String::from_utf8(out.stdout).expect("utf-8 output"))
+}
+
+fn check(name: &str, args: &[&str]) {
+    for state in STATES {
+        insta::assert_snapshot!(format!("{}-{}", name, state), run(state, args), &args.join(" "));
+    }
+}
+
+#[test]
+fn status() {
+    check("status", &["status"]);
+}
+
+#[test]
+fn status_quiet() {
+    check("status-quiet", &["status", "-q"]);
+}
+
+#[test]
+fn status_quiet_verbose() {
+    check("status-quiet-verbose", &["status", "--quiet-verbose"]);
+}
+
+#[test]
+fn status_quiet_stopped() {
+    // not one of the states, as only the exit codes tell stopped players from paused ones
+    for (name, args) in [("status-quiet-stopped", &["status", "-q"][..]), ("status-quiet-verbose-stopped", &["status", "--quiet-verbose"])] {
+        insta::assert_snapshot!(name, run("stopped", args), &args.join(" "));
+    }
+}
+
+#[test]
+fn status_waybar() {
+    check("status-waybar", &["status", "--output", "waybar"]);
+}
+
+#[test]
+fn status_polybar_escapes_tags() {
+    // not one of the states, as only polybar tells `%{` apart
+    let args = ["status", "--output", "polybar"];
+    insta::assert_snapshot!("status-polybar-tags", run("tags", &args), &args.join(" "));
+}
+
+#[test]
+fn status_compat() {
+    check("status-compat-0.1", &["--compat", "0.1", "status"]);
+}
+
+#[test]
+fn url() {
+    check("url", &["url"]);
+}
+
+#[test]
+fn player() {
+    check("player", &["player"]);
+}
+
+#[test]
+fn plan_json() {
+    check("plan", &["--dry-run", "--json", "op", "toggle"]);
+}
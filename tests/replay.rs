@@ -0,0 +1,82 @@
+use std::process::Command;
+
+/// Replays `fixture` with the config `config`, both in `tests/fixtures`, returning what's printed.
+fn replay(config: &str, fixture: &str) -> String {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let out = Command::new(env!("CARGO_BIN_EXE_playing-rs"))
+        .args(["--config", &format!("{}/{}", dir, config), "replay", &format!("{}/{}", dir, fixture)])
+        .output()
+        .expect("the binary runs");
+    assert!(out.status.success(), "replay failed: {}", String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).expect("utf-8 output")
+}
+
+#[test]
+fn pause_seek_and_track_change() {
+    let out = replay("hooks.toml", "pause-resume.jsonl");
+    let lines: Vec<serde_json::Value> = out.lines().map(|l| serde_json::from_str(l).expect("json lines")).collect();
+    let events: Vec<&str> = lines.iter().filter_map(|l| l["event"].as_str()).collect();
+    assert_eq!(events, ["player", "paused", "playing", "seek", "track", "player"]);
+
+    let hooks: Vec<&str> = lines.iter().filter_map(|l| l["hook"].as_str()).collect();
+    assert_eq!(hooks, ["on_track_change", "on_pause", "on_track_change", "on_stop"]);
+
+    assert_eq!(lines[0]["line"], "\u{f36e} Song A // Album @ Artist");
+    assert_eq!(lines.last().unwrap()["line"], "No media");
+}
+
+#[test]
+fn scrobbles_once_half_played() {
+    let out = replay("hooks.toml", "pause-resume.jsonl");
+    let scrobbles: Vec<serde_json::Value> = out.lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).expect("json lines"))
+        .filter_map(|l| l.get("scrobble").cloned())
+        .collect();
+    assert_eq!(scrobbles.len(), 2);
+    // paused for half a minute, which doesn't count
+    assert_eq!(scrobbles[0]["title"], "Song A");
+    assert_eq!(scrobbles[0]["played"], 112.0);
+    assert_eq!(scrobbles[1]["title"], "Song B");
+}
+
+#[test]
+fn seeks_within_the_fixture() {
+    use std::io::Write;
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_playing-rs"))
+        .args(["--config", &format!("{}/default.toml", dir), "--fixture", &format!("{}/states/playing.jsonl", dir), "stdin"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("the binary runs");
+    // one process, for the seeks to move the position of the same frame
+    let commands = "seek-relative 30\nstatus --format {position}\nrewind 50\nstatus --format {position}\nseek 10\nstatus --format {position}\n";
+    child.stdin.take().expect("piped stdin").write_all(commands.as_bytes()).expect("commands written");
+    let out = child.wait_with_output().expect("the binary runs");
+    assert!(out.status.success(), "stdin failed: {}", String::from_utf8_lossy(&out.stderr));
+    // from 0:42
+    assert_eq!(String::from_utf8(out.stdout).expect("utf-8 output"), "1:12\n0:22\n0:10\n");
+}
+
+#[test]
+fn refuses_seconds_that_are_no_time() {
+    use std::io::Write;
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_playing-rs"))
+        .args(["--config", &format!("{}/default.toml", dir), "--fixture", &format!("{}/states/playing.jsonl", dir), "stdin"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("the binary runs");
+    // as published to `<prefix>/command`, e.g. by the position box of Home Assistant
+    let commands = "seek nan\nseek 1e30\nseek-relative inf\nforward -1\nreplay NaN\nstatus --format {position}\n";
+    child.stdin.take().expect("piped stdin").write_all(commands.as_bytes()).expect("commands written");
+    let out = child.wait_with_output().expect("the binary runs");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!stderr.contains("panicked"), "a command panicked: {}", stderr);
+    assert_eq!(stderr.lines().filter(|l| l.contains("failed: BadCommand")).count(), 5, "{}", stderr);
+    assert!(!out.status.success());
+    // the position is left alone, and the commands after them still run
+    assert_eq!(String::from_utf8(out.stdout).expect("utf-8 output"), "0:42\n");
+}